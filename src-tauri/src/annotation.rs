@@ -0,0 +1,324 @@
+// MarkPix - 图片标注工具
+// 标注数据模型：与前端 src/types/index.ts 保持一致
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 标注文档：一组标注和可选的裁剪蒙版（对应前端 HistoryState）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationDocument {
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub crop_mask: Option<CropMask>,
+}
+
+impl AnnotationDocument {
+    /// 从 JSON 文件读取标注文档
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("读取标注文件失败: {} ({})", path.display(), e))?;
+        Self::from_json(&content)
+    }
+
+    /// 从 JSON 字符串解析标注文档
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        serde_json::from_str(content).map_err(|e| format!("解析标注文件失败: {}", e))
+    }
+}
+
+/// 裁剪蒙版
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CropMask {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// 基础标注属性（位置与变换）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseAnnotation {
+    #[serde(default)]
+    pub id: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub rotation: Option<f32>,
+    #[serde(default)]
+    pub scale_x: Option<f32>,
+    #[serde(default)]
+    pub scale_y: Option<f32>,
+    #[serde(default)]
+    pub visible: Option<bool>,
+    #[serde(default)]
+    pub locked: Option<bool>,
+}
+
+/// 所有标注类型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Annotation {
+    Rectangle(RectAnnotation),
+    Ellipse(EllipseAnnotation),
+    Arrow(ArrowAnnotation),
+    Line(LineAnnotation),
+    Text(TextAnnotation),
+    Brush(BrushAnnotation),
+    Marker(MarkerAnnotation),
+    Blur(BlurAnnotation),
+    Image(ImageAnnotation),
+    Magnifier(MagnifierAnnotation),
+}
+
+impl Annotation {
+    /// 获取基础属性
+    pub fn base(&self) -> &BaseAnnotation {
+        match self {
+            Annotation::Rectangle(a) => &a.base,
+            Annotation::Ellipse(a) => &a.base,
+            Annotation::Arrow(a) => &a.base,
+            Annotation::Line(a) => &a.base,
+            Annotation::Text(a) => &a.base,
+            Annotation::Brush(a) => &a.base,
+            Annotation::Marker(a) => &a.base,
+            Annotation::Blur(a) => &a.base,
+            Annotation::Image(a) => &a.base,
+            Annotation::Magnifier(a) => &a.base,
+        }
+    }
+}
+
+/// 线条样式：solid 或 dashed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+}
+
+/// 箭头样式：normal 或 filled
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrowStyle {
+    #[default]
+    Normal,
+    Filled,
+}
+
+/// 序号标记样式：filled 或 outlined
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerStyle {
+    #[default]
+    Filled,
+    Outlined,
+}
+
+/// 文字样式：normal 或 bubble
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextStyle {
+    #[default]
+    Normal,
+    Bubble,
+}
+
+/// 气泡尾巴位置：left 或 right
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BubbleTailPosition {
+    #[default]
+    Left,
+    Right,
+}
+
+/// 矩形标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RectAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub width: f32,
+    pub height: f32,
+    pub stroke: String,
+    pub stroke_width: f32,
+    #[serde(default)]
+    pub fill: String,
+    #[serde(default)]
+    pub fill_opacity: f32,
+    #[serde(default)]
+    pub corner_radius: Option<f32>,
+}
+
+/// 椭圆标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EllipseAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub radius_x: f32,
+    pub radius_y: f32,
+    pub stroke: String,
+    pub stroke_width: f32,
+    #[serde(default)]
+    pub fill: String,
+    #[serde(default)]
+    pub fill_opacity: f32,
+}
+
+/// 箭头标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArrowAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub points: Vec<f32>,
+    pub stroke: String,
+    pub stroke_width: f32,
+    #[serde(default)]
+    pub line_style: LineStyle,
+    #[serde(default)]
+    pub arrow_style: ArrowStyle,
+    #[serde(default)]
+    pub pointer_length: Option<f32>,
+    #[serde(default)]
+    pub pointer_width: Option<f32>,
+}
+
+/// 直线标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub points: Vec<f32>,
+    pub stroke: String,
+    pub stroke_width: f32,
+    #[serde(default)]
+    pub line_style: LineStyle,
+}
+
+/// 文字标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub text: String,
+    pub font_size: f32,
+    pub font_family: String,
+    pub fill: String,
+    #[serde(default)]
+    pub text_style: TextStyle,
+    #[serde(default)]
+    pub bubble_stroke: Option<String>,
+    #[serde(default)]
+    pub bubble_fill: Option<String>,
+    #[serde(default)]
+    pub bubble_tail_position: Option<BubbleTailPosition>,
+    #[serde(default)]
+    pub background_color: Option<String>,
+    #[serde(default)]
+    pub padding: Option<f32>,
+    #[serde(default)]
+    pub width: Option<f32>,
+}
+
+/// 画笔标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrushAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub points: Vec<f32>,
+    pub stroke: String,
+    pub stroke_width: f32,
+    #[serde(default)]
+    pub tension: Option<f32>,
+    #[serde(default)]
+    pub line_cap: Option<String>,
+    #[serde(default)]
+    pub line_join: Option<String>,
+}
+
+/// 序号标记值：数字或字母
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MarkerValue {
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for MarkerValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarkerValue::Number(n) => write!(f, "{}", n),
+            MarkerValue::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// 序号标记标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub value: MarkerValue,
+    #[serde(default)]
+    pub marker_style: MarkerStyle,
+    #[serde(default)]
+    pub marker_type: Option<String>,
+    pub size: f32,
+    pub fill: String,
+    pub text_color: String,
+    #[serde(default)]
+    pub stroke: Option<String>,
+    #[serde(default)]
+    pub stroke_width: Option<f32>,
+}
+
+/// 马赛克区域标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlurAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub blur_radius: f32,
+    #[serde(default)]
+    pub corner_radius: f32,
+}
+
+/// 图片贴图标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub width: f32,
+    pub height: f32,
+    /// base64 data URL 或本地文件路径
+    pub src: String,
+}
+
+/// 放大镜标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MagnifierAnnotation {
+    #[serde(flatten)]
+    pub base: BaseAnnotation,
+    pub source_x: f32,
+    pub source_y: f32,
+    pub source_radius: f32,
+    pub target_radius: f32,
+    #[serde(default)]
+    pub scale: f32,
+}
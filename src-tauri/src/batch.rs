@@ -0,0 +1,244 @@
+// MarkPix - 图片标注工具
+// 批量渲染：将同一份标注模板并行应用到多张图片
+
+use crate::annotation::AnnotationDocument;
+use crate::export::{self, ExportConfig, ExportOptions, OutputFormat};
+use crate::output_pattern::{ensure_parent_dir, OutputPattern, PatternContext};
+use crate::render;
+use image::RgbaImage;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// 批量任务参数
+pub struct BatchJob<'a> {
+    /// 标注模板（所有图片共用）
+    pub template: &'a AnnotationDocument,
+    /// 已展开的输入图片列表
+    pub inputs: Vec<PathBuf>,
+    /// 输出文件名模式，按每个输入文件展开
+    pub output_pattern: &'a OutputPattern,
+    /// 显式指定的输出格式，None 时按输出扩展名决定
+    pub output_format: Option<OutputFormat>,
+    pub export: &'a ExportConfig,
+    pub quality: Option<u8>,
+}
+
+/// 单个文件的处理结果
+pub struct BatchItem {
+    pub input: PathBuf,
+    pub result: Result<PathBuf, String>,
+}
+
+/// 批量任务报告（顺序与输入一致）
+pub struct BatchReport {
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.items.iter().filter(|item| item.result.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.items.iter().filter_map(|item| match &item.result {
+            Ok(_) => None,
+            Err(err) => Some((item.input.as_path(), err.as_str())),
+        })
+    }
+}
+
+/// 并行执行批量渲染，每完成一个文件通过 on_progress 回调报告进度
+pub fn run_batch(
+    job: &BatchJob,
+    on_progress: impl Fn(usize, usize, &BatchItem) + Sync,
+) -> BatchReport {
+    let total = job.inputs.len();
+    let done = AtomicUsize::new(0);
+    let claimed = Mutex::new(HashMap::new());
+
+    // 不依赖图片内容的模式按输入顺序预先分配输出路径，保证 {counter} 与冲突判定稳定
+    let planned: Vec<Option<Result<PathBuf, String>>> = job
+        .inputs
+        .iter()
+        .map(|input| {
+            (!job.output_pattern.needs_image()).then(|| claim_output(job, &claimed, input, None))
+        })
+        .collect();
+
+    let items = job
+        .inputs
+        .par_iter()
+        .zip(planned)
+        .map(|(input, planned)| {
+            let result = planned
+                .transpose()
+                .and_then(|planned| render_one(job, &claimed, input, planned));
+            let item = BatchItem {
+                input: input.clone(),
+                result,
+            };
+            on_progress(done.fetch_add(1, Ordering::SeqCst) + 1, total, &item);
+            item
+        })
+        .collect();
+
+    BatchReport { items }
+}
+
+/// 为输入分配输出路径，多个输入映射到同一输出时只保留第一个
+fn claim_output(
+    job: &BatchJob,
+    claimed: &Mutex<HashMap<PathBuf, PathBuf>>,
+    input: &Path,
+    image: Option<&RgbaImage>,
+) -> Result<PathBuf, String> {
+    let mut claimed = claimed.lock().unwrap();
+    let context = PatternContext::new(Some(input), image);
+    let output = job
+        .output_pattern
+        .resolve(&context, |path| path.exists() || claimed.contains_key(path));
+    if output.exists() && output.canonicalize().ok() == input.canonicalize().ok() {
+        return Err(format!("输出路径与输入相同: {}", output.display()));
+    }
+    if let Some(owner) = claimed.get(&output) {
+        return Err(format!(
+            "输出路径与 {} 冲突: {}",
+            owner.display(),
+            output.display()
+        ));
+    }
+    claimed.insert(output.clone(), input.to_path_buf());
+    Ok(output)
+}
+
+fn render_one(
+    job: &BatchJob,
+    claimed: &Mutex<HashMap<PathBuf, PathBuf>>,
+    input: &Path,
+    planned: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let background = render::load_image(input)?;
+    let rendered = render::render_document(&background, job.template)?;
+    let output = match planned {
+        Some(output) => output,
+        None => claim_output(job, claimed, input, Some(&rendered))?,
+    };
+    let format = match job.output_format {
+        Some(format) => format,
+        None => OutputFormat::from_path(&output)?,
+    };
+    let options = ExportOptions::new(format, job.export, job.quality)?;
+    ensure_parent_dir(&output)?;
+    export::save_image(&rendered, &output, &options)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在 dir 下写入同名的两张图片 a/shot.png 与 b/shot.png
+    fn colliding_inputs(dir: &Path) -> Vec<PathBuf> {
+        ["a", "b"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name).join("shot.png");
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                RgbaImage::from_pixel(4, 3, image::Rgba([0, 128, 255, 255]))
+                    .save(&path)
+                    .unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn run(inputs: Vec<PathBuf>, pattern: &str) -> BatchReport {
+        let template = AnnotationDocument::default();
+        let output_pattern = OutputPattern::parse(pattern).unwrap();
+        let export = ExportConfig::default();
+        let job = BatchJob {
+            template: &template,
+            inputs,
+            output_pattern: &output_pattern,
+            output_format: None,
+            export: &export,
+            quality: None,
+        };
+        run_batch(&job, |_, _, _| {})
+    }
+
+    #[test]
+    fn colliding_outputs_keep_the_first_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = colliding_inputs(dir.path());
+        let pattern = format!("{}/out/{{input_file_base}}.jpg", dir.path().display());
+        let report = run(inputs.clone(), &pattern);
+
+        let output = dir.path().join("out").join("shot.jpg");
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.items[0].result.as_ref().unwrap(), &output);
+        assert_eq!(
+            image::guess_format(&std::fs::read(&output).unwrap()).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        let failed: Vec<_> = report.failed().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, inputs[1]);
+        assert_eq!(
+            failed[0].1,
+            format!(
+                "输出路径与 {} 冲突: {}",
+                inputs[0].display(),
+                output.display()
+            )
+        );
+    }
+
+    #[test]
+    fn counter_gives_colliding_inputs_distinct_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = colliding_inputs(dir.path());
+        let pattern = format!(
+            "{}/out/{{input_file_base}}_{{counter}}.png",
+            dir.path().display()
+        );
+        let report = run(inputs, &pattern);
+        assert_eq!(report.succeeded(), 2);
+        let outputs: Vec<_> = report
+            .items
+            .iter()
+            .map(|item| item.result.clone().unwrap())
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                dir.path().join("out").join("shot_1.png"),
+                dir.path().join("out").join("shot_2.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn collision_is_detected_for_patterns_needing_the_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = colliding_inputs(dir.path());
+        let pattern = format!("{}/{{width}}x{{height}}.png", dir.path().display());
+        let report = run(inputs, &pattern);
+        assert_eq!(report.succeeded(), 1);
+        let (_, err) = report.failed().next().unwrap();
+        assert!(err.starts_with("输出路径与 "), "{}", err);
+        assert!(dir.path().join("4x3.png").exists());
+    }
+
+    #[test]
+    fn output_equal_to_input_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs = colliding_inputs(dir.path());
+        let report = run(inputs[..1].to_vec(), "{input_file}");
+        let (_, err) = report.failed().next().unwrap();
+        assert!(err.starts_with("输出路径与输入相同"), "{}", err);
+    }
+}
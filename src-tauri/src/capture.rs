@@ -0,0 +1,379 @@
+// MarkPix - 图片标注工具
+// 屏幕截图：调用可配置的外部截图工具（Wayland: grim/slurp，X11: maim/slop）
+
+use crate::input::{self, InputError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Output, Stdio};
+
+/// 截图配置：按会话类型分别配置命令模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// Wayland 会话使用的命令
+    pub wayland: CaptureCommands,
+    /// X11 会话使用的命令
+    pub x11: CaptureCommands,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            wayland: CaptureCommands {
+                select: "slurp".to_string(),
+                region: "grim -g \"{geometry}\" \"{file}\"".to_string(),
+                full: "grim \"{file}\"".to_string(),
+                output: "grim -o \"{output}\" \"{file}\"".to_string(),
+            },
+            x11: CaptureCommands {
+                select: "slop -f '%g'".to_string(),
+                region: "maim -g \"{geometry}\" \"{file}\"".to_string(),
+                full: "maim \"{file}\"".to_string(),
+                output: String::new(),
+            },
+        }
+    }
+}
+
+/// 一种会话下的截图命令模板（通过 sh -c 执行）
+///
+/// 截图命令中的 `{file}` 替换为临时文件路径；模板不含 `{file}` 时，
+/// 命令输出到 stdout 的数据即为截图。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureCommands {
+    /// 框选区域的命令，输出的第一行作为 `{geometry}`；为空时由 region 命令自行框选
+    pub select: String,
+    /// 截取区域，`{geometry}` 为框选结果
+    pub region: String,
+    /// 截取全部屏幕
+    pub full: String,
+    /// 截取指定显示器，`{output}` 为显示器名称；为空表示不支持
+    pub output: String,
+}
+
+/// 截图范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// 先框选区域再截图
+    Region,
+    /// 全部屏幕
+    Full,
+    /// 指定名称的显示器
+    Output(String),
+}
+
+/// 图形会话类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    Wayland,
+    X11,
+}
+
+impl Session {
+    /// 按 WAYLAND_DISPLAY / DISPLAY 检测当前会话，优先 Wayland
+    pub fn detect() -> Option<Self> {
+        let is_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        if is_set("WAYLAND_DISPLAY") {
+            Some(Self::Wayland)
+        } else if is_set("DISPLAY") {
+            Some(Self::X11)
+        } else {
+            None
+        }
+    }
+
+    /// 对应的配置节名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wayland => "wayland",
+            Self::X11 => "x11",
+        }
+    }
+}
+
+/// 截图时的错误
+#[derive(Debug)]
+pub enum CaptureError {
+    /// 未检测到图形会话
+    NoSession,
+    /// 当前会话未配置所需的命令
+    NotConfigured { key: String },
+    /// 用户取消了区域选择
+    Cancelled,
+    /// 截图命令执行失败
+    Command { command: String, message: String },
+    /// 截图结果不是有效图片
+    Input(InputError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSession => write!(f, "未检测到图形会话（WAYLAND_DISPLAY 与 DISPLAY 均未设置）"),
+            Self::NotConfigured { key } => write!(f, "截图命令未配置: {}", key),
+            Self::Cancelled => write!(f, "已取消截图"),
+            Self::Command { command, message } => {
+                write!(f, "截图命令执行失败: {} ({})", command, message)
+            }
+            Self::Input(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<InputError> for CaptureError {
+    fn from(err: InputError) -> Self {
+        Self::Input(err)
+    }
+}
+
+impl CaptureConfig {
+    /// 指定会话使用的命令
+    pub fn commands(&self, session: Session) -> &CaptureCommands {
+        match session {
+            Session::Wayland => &self.wayland,
+            Session::X11 => &self.x11,
+        }
+    }
+
+    /// 检查模板是否缺少必需的占位符
+    pub fn validate(&self) -> Result<(), String> {
+        for session in [Session::Wayland, Session::X11] {
+            let commands = self.commands(session);
+            let key = |field: &str| format!("capture.{}.{}", session.name(), field);
+            if !commands.select.trim().is_empty() && !commands.region.contains("{geometry}") {
+                return Err(format!(
+                    "{} 缺少 {{geometry}} 占位符（已配置 {}）",
+                    key("region"),
+                    key("select")
+                ));
+            }
+            if !commands.output.trim().is_empty() && !commands.output.contains("{output}") {
+                return Err(format!("{} 缺少 {{output}} 占位符", key("output")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 在当前会话中截图并保存到临时文件，返回文件路径
+pub fn capture(config: &CaptureConfig, mode: &CaptureMode) -> Result<PathBuf, CaptureError> {
+    let session = Session::detect().ok_or(CaptureError::NoSession)?;
+    capture_with(config.commands(session), session, mode)
+}
+
+/// 使用指定会话的命令截图
+pub fn capture_with(
+    commands: &CaptureCommands,
+    session: Session,
+    mode: &CaptureMode,
+) -> Result<PathBuf, CaptureError> {
+    let require = |template: &str, field: &str| {
+        if template.trim().is_empty() {
+            Err(CaptureError::NotConfigured {
+                key: format!("capture.{}.{}", session.name(), field),
+            })
+        } else {
+            Ok(template.to_string())
+        }
+    };
+    let template = match mode {
+        CaptureMode::Region => {
+            let template = require(&commands.region, "region")?;
+            let geometry = if commands.select.trim().is_empty() {
+                String::new()
+            } else {
+                select_region(&commands.select)?
+            };
+            template.replace("{geometry}", &geometry)
+        }
+        CaptureMode::Full => require(&commands.full, "full")?,
+        CaptureMode::Output(name) => require(&commands.output, "output")?.replace("{output}", name),
+    };
+
+    if !template.contains("{file}") {
+        let data = run_shell(&template)?;
+        return Ok(input::save_temp_image(&data, "capture", "截图命令输出")?);
+    }
+    let file = input::temp_file_path("capture", "png")?;
+    run_shell(&template.replace("{file}", &file.to_string_lossy()))?;
+    input::detect_file_format(&file)?;
+    Ok(file)
+}
+
+/// 运行框选命令，取输出的第一行作为区域
+///
+/// 无输出且以 0 或 1 退出（slurp、slop 按 Esc 取消时的行为）视为取消；
+/// 命令不存在（退出码 127）等其他失败按命令错误报告。
+fn select_region(command: &str) -> Result<String, CaptureError> {
+    let output = spawn_shell(command)?;
+    let geometry = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string);
+    match (geometry, output.status.code()) {
+        (Some(geometry), Some(0)) => Ok(geometry),
+        (None, Some(0 | 1)) => Err(CaptureError::Cancelled),
+        _ => Err(status_error(command, output.status)),
+    }
+}
+
+/// 通过 sh -c 执行命令并返回 stdout，非零退出时返回错误
+fn run_shell(command: &str) -> Result<Vec<u8>, CaptureError> {
+    let output = spawn_shell(command)?;
+    if !output.status.success() {
+        return Err(status_error(command, output.status));
+    }
+    Ok(output.stdout)
+}
+
+/// 通过 sh -c 执行命令（stderr 直接透传给用户），仅在无法启动 sh 时返回错误
+fn spawn_shell(command: &str) -> Result<Output, CaptureError> {
+    Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| CaptureError::Command {
+            command: command.to_string(),
+            message: e.to_string(),
+        })
+}
+
+/// 按退出状态生成命令错误
+fn status_error(command: &str, status: ExitStatus) -> CaptureError {
+    CaptureError::Command {
+        command: command.to_string(),
+        message: match status.code() {
+            Some(code) => format!("退出码 {}", code),
+            None => "被信号终止".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 写入 1x1 的 PNG 作为截图命令输出的固定图片
+    fn fixture_png(dir: &tempfile::TempDir) -> (PathBuf, Vec<u8>) {
+        let path = dir.path().join("fixture.png");
+        image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+        let data = fs::read(&path).unwrap();
+        (path, data)
+    }
+
+    fn stub(select: &str, region: &str, full: &str) -> CaptureCommands {
+        CaptureCommands {
+            select: select.to_string(),
+            region: region.to_string(),
+            full: full.to_string(),
+            output: String::new(),
+        }
+    }
+
+    #[test]
+    fn region_passes_geometry_and_reads_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (fixture, data) = fixture_png(&dir);
+        let commands = stub(
+            "echo 0,0 1x1",
+            &format!(
+                "test \"{{geometry}}\" = '0,0 1x1' && cat '{}' > \"{{file}}\"",
+                fixture.display()
+            ),
+            "",
+        );
+        let file = capture_with(&commands, Session::Wayland, &CaptureMode::Region).unwrap();
+        assert_eq!(fs::read(&file).unwrap(), data);
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn template_without_file_reads_stdout() {
+        let dir = tempfile::tempdir().unwrap();
+        let (fixture, data) = fixture_png(&dir);
+        let commands = stub("", "", &format!("cat '{}'", fixture.display()));
+        let file = capture_with(&commands, Session::X11, &CaptureMode::Full).unwrap();
+        assert_eq!(file.extension().unwrap(), "png");
+        assert_eq!(fs::read(&file).unwrap(), data);
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn missing_template_is_not_configured() {
+        let commands = stub("slurp", "", "");
+        for (mode, key) in [
+            (CaptureMode::Region, "capture.wayland.region"),
+            (CaptureMode::Full, "capture.wayland.full"),
+            (
+                CaptureMode::Output("DP-1".to_string()),
+                "capture.wayland.output",
+            ),
+        ] {
+            match capture_with(&commands, Session::Wayland, &mode) {
+                Err(CaptureError::NotConfigured { key: actual }) => assert_eq!(actual, key),
+                other => panic!("{:?}: {:?}", mode, other),
+            }
+        }
+    }
+
+    #[test]
+    fn aborted_selection_is_cancelled() {
+        // slurp 按 Esc 时无输出并以 1 退出；框选工具也可能无输出直接以 0 退出
+        for select in ["exit 1", "true"] {
+            let commands = stub(select, "cat \"{geometry}\" > \"{file}\"", "");
+            let result = capture_with(&commands, Session::Wayland, &CaptureMode::Region);
+            assert!(
+                matches!(result, Err(CaptureError::Cancelled)),
+                "{}: {:?}",
+                select,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn failing_selection_is_a_command_error() {
+        for select in [
+            "markpix-missing-select-tool",
+            "exit 3",
+            "echo 0,0 1x1; exit 1",
+        ] {
+            let commands = stub(select, "cat \"{geometry}\" > \"{file}\"", "");
+            let result = capture_with(&commands, Session::Wayland, &CaptureMode::Region);
+            assert!(
+                matches!(result, Err(CaptureError::Command { .. })),
+                "{}: {:?}",
+                select,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_capture_output_is_rejected() {
+        let commands = stub("", "", "echo not-an-image");
+        let result = capture_with(&commands, Session::Wayland, &CaptureMode::Full);
+        assert!(
+            matches!(result, Err(CaptureError::Input(_))),
+            "{:?}",
+            result
+        );
+
+        let commands = stub("", "", "exit 2");
+        let result = capture_with(&commands, Session::Wayland, &CaptureMode::Full);
+        assert!(
+            matches!(result, Err(CaptureError::Command { .. })),
+            "{:?}",
+            result
+        );
+    }
+}
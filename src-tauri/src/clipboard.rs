@@ -0,0 +1,318 @@
+// MarkPix - 图片标注工具
+// 读取剪贴板图片：通过 wl-paste (Wayland) / xclip (X11) 按 MIME 类型协商
+
+use crate::input::{self, InputError};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// 按优先级尝试的剪贴板类型
+const IMAGE_MIME_TYPES: &[&str] = &["image/png", "image/jpeg"];
+/// 文件管理器复制文件时使用的类型
+const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+/// 读取剪贴板时的错误
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// 未找到可用的剪贴板工具
+    NoBackend,
+    /// 剪贴板中没有图片
+    NoImage,
+    /// 剪贴板内容无法作为图片保存
+    Input(InputError),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoBackend => write!(
+                f,
+                "无法读取剪贴板，请确保已安装 wl-paste (wl-clipboard) 或 xclip"
+            ),
+            Self::NoImage => write!(f, "剪贴板中没有图片"),
+            Self::Input(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+impl From<InputError> for ClipboardError {
+    fn from(err: InputError) -> Self {
+        Self::Input(err)
+    }
+}
+
+/// 剪贴板命令行工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    WlPaste,
+    Xclip,
+}
+
+/// 命令的执行结果
+#[derive(Clone)]
+struct CommandOutput {
+    success: bool,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/// 执行剪贴板命令，无法启动时返回 None（测试中替换为模拟的命令）
+type Runner<'a> = &'a dyn Fn(&str, &[&str]) -> Option<CommandOutput>;
+
+impl Backend {
+    /// Wayland 会话优先使用 wl-paste，否则优先 xclip
+    fn candidates() -> [Backend; 2] {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            [Backend::WlPaste, Backend::Xclip]
+        } else {
+            [Backend::Xclip, Backend::WlPaste]
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Backend::WlPaste => "wl-paste",
+            Backend::Xclip => "xclip",
+        }
+    }
+
+    /// 剪贴板为空时工具输出的错误信息
+    fn is_empty_clipboard(self, stderr: &str) -> bool {
+        match self {
+            Backend::WlPaste => {
+                stderr.contains("Nothing is copied") || stderr.contains("No selection")
+            }
+            Backend::Xclip => stderr.contains("target TARGETS not available"),
+        }
+    }
+
+    /// 列出剪贴板当前提供的 MIME 类型
+    ///
+    /// 剪贴板为空时返回空列表；工具无法启动或因其他原因失败（如没有对应的显示服务器）时返回 None，
+    /// 由调用方改用下一个工具。
+    fn list_types(self, run: Runner) -> Option<Vec<String>> {
+        let args: &[&str] = match self {
+            Backend::WlPaste => &["--list-types"],
+            Backend::Xclip => &["-selection", "clipboard", "-o", "-t", "TARGETS"],
+        };
+        let output = run(self.program(), args)?;
+        if !output.success {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return self.is_empty_clipboard(&stderr).then(Vec::new);
+        }
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect(),
+        )
+    }
+
+    /// 读取指定类型的剪贴板内容
+    fn read(self, mime_type: &str, run: Runner) -> Option<Vec<u8>> {
+        let args: &[&str] = match self {
+            Backend::WlPaste => &["--no-newline", "--type", mime_type],
+            Backend::Xclip => &["-selection", "clipboard", "-o", "-t", mime_type],
+        };
+        run(self.program(), args)
+            .filter(|output| output.success)
+            .map(|output| output.stdout)
+            .filter(|data| !data.is_empty())
+    }
+}
+
+/// 执行命令并收集输出，无法启动时返回 None
+fn run_command(program: &str, args: &[&str]) -> Option<CommandOutput> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    Some(CommandOutput {
+        success: output.status.success(),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+/// 读取剪贴板中的图片数据
+///
+/// 依次尝试 image/png、image/jpeg，最后回退到 text/uri-list 中的第一个图片文件。
+pub fn read_image() -> Result<Vec<u8>, ClipboardError> {
+    if !cfg!(target_os = "linux") {
+        return Err(ClipboardError::NoBackend);
+    }
+    read_image_with(Backend::candidates(), &run_command)
+}
+
+fn read_image_with(backends: [Backend; 2], run: Runner) -> Result<Vec<u8>, ClipboardError> {
+    let (backend, types) = backends
+        .into_iter()
+        .find_map(|backend| backend.list_types(run).map(|types| (backend, types)))
+        .ok_or(ClipboardError::NoBackend)?;
+
+    for mime_type in IMAGE_MIME_TYPES {
+        if types.iter().any(|t| t == mime_type) {
+            if let Some(data) = backend.read(mime_type, run) {
+                return Ok(data);
+            }
+        }
+    }
+
+    if types.iter().any(|t| t == URI_LIST_MIME_TYPE) {
+        if let Some(uris) = backend.read(URI_LIST_MIME_TYPE, run) {
+            let uris = String::from_utf8_lossy(&uris);
+            let path = uris
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(input::file_uri_to_path)
+                .find(|path| input::detect_file_format(path).is_ok());
+            if let Some(path) = path {
+                return std::fs::read(&path).map_err(|e| {
+                    ClipboardError::Input(InputError::Io {
+                        path,
+                        message: e.to_string(),
+                    })
+                });
+            }
+        }
+    }
+
+    Err(ClipboardError::NoImage)
+}
+
+/// 读取剪贴板图片并保存到临时文件（与 stdin 输入相同的存放方式）
+pub fn save_image_to_temp() -> Result<PathBuf, ClipboardError> {
+    let data = read_image()?;
+    Ok(input::save_temp_image(&data, "clipboard", "剪贴板")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn ok(stdout: &[u8]) -> Option<CommandOutput> {
+        Some(CommandOutput {
+            success: true,
+            stdout: stdout.to_vec(),
+            stderr: Vec::new(),
+        })
+    }
+
+    fn failed(stderr: &str) -> Option<CommandOutput> {
+        Some(CommandOutput {
+            success: false,
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        })
+    }
+
+    /// 按「程序 最后一个参数」应答的模拟命令，未列出的命令视为无法启动
+    fn fake(
+        replies: Vec<(&'static str, Option<CommandOutput>)>,
+    ) -> impl Fn(&str, &[&str]) -> Option<CommandOutput> {
+        let replies: HashMap<_, _> = replies.into_iter().collect();
+        move |program, args| {
+            let key = format!("{} {}", program, args.last().unwrap_or(&""));
+            replies.get(key.as_str()).cloned().flatten()
+        }
+    }
+
+    const WAYLAND_FIRST: [Backend; 2] = [Backend::WlPaste, Backend::Xclip];
+
+    #[test]
+    fn prefers_png_over_jpeg() {
+        let run = fake(vec![
+            (
+                "wl-paste --list-types",
+                ok(b"image/jpeg\nimage/png\ntext/uri-list\n"),
+            ),
+            ("wl-paste image/png", ok(b"png")),
+            ("wl-paste image/jpeg", ok(b"jpeg")),
+        ]);
+        assert_eq!(read_image_with(WAYLAND_FIRST, &run).unwrap(), b"png");
+    }
+
+    #[test]
+    fn falls_back_to_jpeg_when_png_is_empty() {
+        let run = fake(vec![
+            ("wl-paste --list-types", ok(b"image/png\nimage/jpeg\n")),
+            ("wl-paste image/png", ok(b"")),
+            ("wl-paste image/jpeg", ok(b"jpeg")),
+        ]);
+        assert_eq!(read_image_with(WAYLAND_FIRST, &run).unwrap(), b"jpeg");
+    }
+
+    #[test]
+    fn reads_first_image_from_uri_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "not an image").unwrap();
+        let image = dir.path().join("shot.png");
+        std::fs::write(&image, PNG_HEADER).unwrap();
+        let uris = format!(
+            "# copied files\r\nfile://{}\r\nfile://{}\r\n",
+            text.display(),
+            image.display()
+        );
+        let run = fake(vec![
+            ("wl-paste --list-types", ok(b"text/uri-list\n")),
+            ("wl-paste text/uri-list", ok(uris.as_bytes())),
+        ]);
+        assert_eq!(read_image_with(WAYLAND_FIRST, &run).unwrap(), PNG_HEADER);
+    }
+
+    #[test]
+    fn uri_list_without_images_is_no_image() {
+        let run = fake(vec![
+            ("wl-paste --list-types", ok(b"text/uri-list\n")),
+            ("wl-paste text/uri-list", ok(b"file:///nonexistent/a.png\n")),
+        ]);
+        assert!(matches!(
+            read_image_with(WAYLAND_FIRST, &run),
+            Err(ClipboardError::NoImage)
+        ));
+    }
+
+    #[test]
+    fn backend_failure_falls_through_to_next_backend() {
+        let run = fake(vec![
+            (
+                "wl-paste --list-types",
+                failed("Failed to connect to a Wayland server"),
+            ),
+            ("xclip TARGETS", ok(b"TARGETS\nimage/png\n")),
+            ("xclip image/png", ok(b"png")),
+        ]);
+        assert_eq!(read_image_with(WAYLAND_FIRST, &run).unwrap(), b"png");
+    }
+
+    #[test]
+    fn empty_clipboard_does_not_try_next_backend() {
+        let run = fake(vec![
+            ("wl-paste --list-types", failed("Nothing is copied\n")),
+            ("xclip TARGETS", ok(b"image/png\n")),
+            ("xclip image/png", ok(b"stale")),
+        ]);
+        assert!(matches!(
+            read_image_with(WAYLAND_FIRST, &run),
+            Err(ClipboardError::NoImage)
+        ));
+    }
+
+    #[test]
+    fn missing_tools_are_no_backend() {
+        let run = fake(vec![("xclip TARGETS", failed("Error: Can't open display"))]);
+        assert!(matches!(
+            read_image_with(WAYLAND_FIRST, &run),
+            Err(ClipboardError::NoBackend)
+        ));
+    }
+}
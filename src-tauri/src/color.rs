@@ -0,0 +1,137 @@
+// MarkPix - 图片标注工具
+// CSS 颜色解析：支持前端使用的 #hex、rgb()/rgba() 与常见颜色名
+
+/// RGBA 颜色（每通道 0-255）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Rgba = Rgba {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    /// 是否完全透明
+    pub fn is_transparent(&self) -> bool {
+        self.a == 0
+    }
+}
+
+/// 解析 CSS 颜色字符串，空字符串视为透明
+pub fn parse_css_color(value: &str) -> Result<Rgba, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(Rgba::TRANSPARENT);
+    }
+    let lower = value.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| format!("无效的颜色值: {}", value));
+    }
+
+    if let Some(args) = lower
+        .strip_prefix("rgba(")
+        .or_else(|| lower.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        return parse_rgb_function(args).ok_or_else(|| format!("无效的颜色值: {}", value));
+    }
+
+    named_color(&lower).ok_or_else(|| format!("无效的颜色值: {}", value))
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba> {
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    if !hex.is_ascii() {
+        return None;
+    }
+    match hex.len() {
+        3 => Some(Rgba::rgb(digit(0)?, digit(1)?, digit(2)?)),
+        4 => Some(Rgba {
+            r: digit(0)?,
+            g: digit(1)?,
+            b: digit(2)?,
+            a: digit(3)?,
+        }),
+        6 => Some(Rgba::rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Rgba {
+            r: byte(0)?,
+            g: byte(2)?,
+            b: byte(4)?,
+            a: byte(6)?,
+        }),
+        _ => None,
+    }
+}
+
+fn parse_rgb_function(args: &str) -> Option<Rgba> {
+    let parts: Vec<&str> = args
+        .split([',', ' ', '/'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if parts.len() != 3 && parts.len() != 4 {
+        return None;
+    }
+
+    let channel = |s: &str| -> Option<u8> {
+        if let Some(pct) = s.strip_suffix('%') {
+            let v: f32 = pct.parse().ok()?;
+            Some((v.clamp(0.0, 100.0) * 2.55).round() as u8)
+        } else {
+            let v: f32 = s.parse().ok()?;
+            Some(v.clamp(0.0, 255.0).round() as u8)
+        }
+    };
+    let alpha = match parts.get(3) {
+        Some(s) => {
+            let v: f32 = match s.strip_suffix('%') {
+                Some(pct) => pct.parse::<f32>().ok()? / 100.0,
+                None => s.parse().ok()?,
+            };
+            (v.clamp(0.0, 1.0) * 255.0).round() as u8
+        }
+        None => 255,
+    };
+
+    Some(Rgba {
+        r: channel(parts[0])?,
+        g: channel(parts[1])?,
+        b: channel(parts[2])?,
+        a: alpha,
+    })
+}
+
+fn named_color(name: &str) -> Option<Rgba> {
+    let color = match name {
+        "transparent" | "none" => Rgba::TRANSPARENT,
+        "black" => Rgba::rgb(0, 0, 0),
+        "white" => Rgba::rgb(255, 255, 255),
+        "red" => Rgba::rgb(255, 0, 0),
+        "green" => Rgba::rgb(0, 128, 0),
+        "lime" => Rgba::rgb(0, 255, 0),
+        "blue" => Rgba::rgb(0, 0, 255),
+        "yellow" => Rgba::rgb(255, 255, 0),
+        "orange" => Rgba::rgb(255, 165, 0),
+        "purple" => Rgba::rgb(128, 0, 128),
+        "pink" => Rgba::rgb(255, 192, 203),
+        "cyan" | "aqua" => Rgba::rgb(0, 255, 255),
+        "magenta" | "fuchsia" => Rgba::rgb(255, 0, 255),
+        "gray" | "grey" => Rgba::rgb(128, 128, 128),
+        _ => return None,
+    };
+    Some(color)
+}
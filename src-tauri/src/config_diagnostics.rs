@@ -0,0 +1,281 @@
+// MarkPix - 图片标注工具
+// 配置诊断：定位配置文件中出错的行列，备份无法使用的配置文件
+
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 配置问题的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticKind {
+    /// 无法读取文件
+    Read,
+    /// TOML 语法或类型错误
+    Parse,
+    /// 取值无效（如 theme、export.quality 超出范围）
+    Invalid,
+}
+
+/// 加载配置时发现的问题（供设置对话框展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDiagnostic {
+    /// 配置文件路径
+    pub path: String,
+    pub kind: DiagnosticKind,
+    /// 错误说明（不含位置）
+    pub message: String,
+    /// 出错的行号（从 1 开始），无法定位时为 None
+    pub line: Option<usize>,
+    /// 出错的列号（从 1 开始，按字符计）
+    pub column: Option<usize>,
+    /// 原文件的备份路径
+    pub backup: Option<String>,
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl ConfigDiagnostic {
+    fn new(path: &Path, kind: DiagnosticKind, message: String) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            message,
+            line: None,
+            column: None,
+            backup: None,
+        }
+    }
+
+    /// 读取文件失败
+    pub fn read(path: &Path, err: &std::io::Error) -> Self {
+        Self::new(
+            path,
+            DiagnosticKind::Read,
+            format!("读取配置文件失败: {}", err),
+        )
+    }
+
+    /// TOML 解析失败，按错误的字节位置换算行列
+    pub fn parse(path: &Path, content: &str, err: &toml::de::Error) -> Self {
+        // toml 的错误说明可能有多行，合并为一行便于在终端与对话框中显示
+        let message = err.message().trim().lines().collect::<Vec<_>>().join("; ");
+        let mut diagnostic = Self::new(path, DiagnosticKind::Parse, message);
+        if let Some(span) = err.span() {
+            let (line, column) = line_column(content, span.start);
+            diagnostic.line = Some(line);
+            diagnostic.column = Some(column);
+        }
+        diagnostic
+    }
+
+    /// 取值校验失败，尽量定位到错误信息开头的配置键（如 `export.quality 超出范围`）
+    pub fn invalid(path: &Path, content: &str, message: String) -> Self {
+        let location = message
+            .split_whitespace()
+            .next()
+            .and_then(|key| locate_key(content, key));
+        let mut diagnostic = Self::new(path, DiagnosticKind::Invalid, message);
+        if let Some((line, column)) = location {
+            diagnostic.line = Some(line);
+            diagnostic.column = Some(column);
+        }
+        diagnostic
+    }
+
+    /// 把原文件复制为 `<文件名>.broken-<时间戳>`，记录备份路径
+    ///
+    /// 最近一次备份与当前内容相同时直接沿用，避免每次启动都产生新的备份。
+    pub fn backup(&mut self) -> Result<PathBuf, String> {
+        let path = PathBuf::from(&self.path);
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "config.toml".to_string());
+        let content =
+            fs::read(&path).map_err(|e| format!("读取配置文件失败: {} ({})", path.display(), e))?;
+        let backup = match latest_backup(&path, &file_name) {
+            Some(latest) if fs::read(&latest).is_ok_and(|old| old == content) => latest,
+            _ => {
+                let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                let backup = path.with_file_name(format!("{}.broken-{}", file_name, timestamp));
+                fs::write(&backup, &content)
+                    .map_err(|e| format!("备份配置文件失败: {} ({})", backup.display(), e))?;
+                backup
+            }
+        };
+        self.backup = Some(backup.to_string_lossy().to_string());
+        Ok(backup)
+    }
+}
+
+/// 同目录下最近的 `<文件名>.broken-<时间戳>` 备份（时间戳定长，按文件名排序即按时间排序）
+fn latest_backup(path: &Path, file_name: &str) -> Option<PathBuf> {
+    let prefix = format!("{}.broken-", file_name);
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .max()
+}
+
+/// 字节偏移换算为行列（均从 1 开始）
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(content.len());
+    while !content.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// 在 TOML 文本中查找配置键所在的行列
+///
+/// 键为点分隔路径，数组表用下标表示，如 `theme`、`export.quality`、`custom_actions[0]`。
+/// 只识别常见写法（`[表]`、`[[数组表]]` 与 `键 = 值`），找不到时返回 None。
+fn locate_key(content: &str, key: &str) -> Option<(usize, usize)> {
+    let key = key.trim_end_matches([':', '：']);
+    let mut table = String::new();
+    let mut array_counts: std::collections::HashMap<String, usize> = Default::default();
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim_start();
+        let column = raw.chars().count() - line.chars().count() + 1;
+        if let Some(header) = line.strip_prefix("[[") {
+            let name = header
+                .split("]]")
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let count = array_counts.entry(name.clone()).or_insert(0);
+            table = format!("{}[{}]", name, count);
+            *count += 1;
+            if table == key {
+                return Some((index + 1, column));
+            }
+        } else if let Some(header) = line.strip_prefix('[') {
+            table = header
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if table == key {
+                return Some((index + 1, column));
+            }
+        } else if let Some((name, _)) = line.split_once('=') {
+            let name = name.trim().trim_matches('"');
+            let full = if table.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", table, name)
+            };
+            if full == key {
+                return Some((index + 1, column));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_column_counts_characters_not_bytes() {
+        let content = "theme = \"dark\"\n# 注释\nname = \"标注\" x\n";
+        let offset = content.find(" x").unwrap() + 1;
+        assert_eq!(line_column(content, offset), (3, 13));
+    }
+
+    #[test]
+    fn line_column_clamps_offset_inside_character() {
+        let content = "a = \"中\"";
+        // 落在「中」的第二个字节上时回退到字符开头
+        let offset = content.find('中').unwrap() + 1;
+        assert_eq!(line_column(content, offset), (1, 6));
+        assert_eq!(line_column(content, usize::MAX), (1, 8));
+    }
+
+    #[test]
+    fn locate_key_finds_tables_and_nested_keys() {
+        let content = "theme = \"dark\"\n\n[export]\n  quality = 120\n";
+        assert_eq!(locate_key(content, "theme"), Some((1, 1)));
+        assert_eq!(locate_key(content, "export"), Some((3, 1)));
+        assert_eq!(locate_key(content, "export.quality"), Some((4, 3)));
+        assert_eq!(locate_key(content, "export.format"), None);
+    }
+
+    #[test]
+    fn locate_key_indexes_array_tables() {
+        let content = "[[custom_actions]]\nname = \"a\"\n\n[[custom_actions]]\nname = \"b\"\n";
+        assert_eq!(locate_key(content, "custom_actions[0]"), Some((1, 1)));
+        assert_eq!(locate_key(content, "custom_actions[1]"), Some((4, 1)));
+        assert_eq!(locate_key(content, "custom_actions[1].name"), Some((5, 1)));
+        assert_eq!(locate_key(content, "custom_actions[2]"), None);
+    }
+
+    #[test]
+    fn invalid_locates_key_from_message() {
+        let content = "[export]\nquality = 120\n";
+        let diagnostic = ConfigDiagnostic::invalid(
+            Path::new("config.toml"),
+            content,
+            "export.quality: 超出范围".to_string(),
+        );
+        assert_eq!((diagnostic.line, diagnostic.column), (Some(2), Some(1)));
+        assert_eq!(
+            diagnostic.to_string(),
+            "config.toml:2:1: export.quality: 超出范围"
+        );
+    }
+
+    #[test]
+    fn backup_reuses_unchanged_latest_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "theme = 1\n").unwrap();
+        let stale = dir.path().join("config.toml.broken-20000101-000000");
+        fs::write(&stale, "theme = 0\n").unwrap();
+
+        let mut diagnostic = ConfigDiagnostic::invalid(&path, "", "theme 无效".to_string());
+        let first = diagnostic.backup().unwrap();
+        assert_ne!(first, stale);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "theme = 1\n");
+        assert_eq!(diagnostic.backup.as_deref(), first.to_str());
+
+        let second = diagnostic.backup().unwrap();
+        assert_eq!(second, first);
+        let backups = fs::read_dir(dir.path()).unwrap().count() - 1;
+        assert_eq!(backups, 2);
+    }
+
+    #[test]
+    fn backup_fails_for_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut diagnostic = ConfigDiagnostic::invalid(&path, "", "theme 无效".to_string());
+        assert!(diagnostic.backup().is_err());
+        assert!(diagnostic.backup.is_none());
+    }
+}
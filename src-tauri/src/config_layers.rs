@@ -0,0 +1,948 @@
+// MarkPix - 图片标注工具
+// 分层配置：系统 → 用户 → 项目（.markpix.toml）→ 环境变量（MARKPIX_*）→ 命令行，逐键合并并记录来源
+
+use crate::config_diagnostics::{ConfigDiagnostic, DiagnosticKind};
+use crate::{AppConfig, ConfigSource};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 项目配置文件名（位于图片所在目录）
+pub const PROJECT_FILE_NAME: &str = ".markpix.toml";
+/// 环境变量前缀：`MARKPIX_THEME`、`MARKPIX_EXPORT__QUALITY`（双下划线分隔嵌套的键）
+pub const ENV_PREFIX: &str = "MARKPIX_";
+/// 按字段逐项合并的数组表（其余数组整体覆盖），如系统配置下发的自定义动作与用户自己的动作共存
+const KEYED_ARRAYS: &[(&str, &str)] = &[("custom_actions", "name")];
+
+/// 配置层，按优先级从低到高排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerKind {
+    /// 内置默认值
+    Default,
+    /// 系统配置（$XDG_CONFIG_DIRS，默认 /etc/xdg）
+    System,
+    /// 用户配置（~/.config/markpix/config.toml 或 -c 指定的文件）
+    User,
+    /// 图片所在目录的 .markpix.toml
+    Project,
+    /// MARKPIX_* 环境变量
+    Env,
+    /// 命令行参数
+    Cli,
+}
+
+impl LayerKind {
+    /// 显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Default => "默认值",
+            Self::System => "系统配置",
+            Self::User => "用户配置",
+            Self::Project => "项目配置",
+            Self::Env => "环境变量",
+            Self::Cli => "命令行",
+        }
+    }
+}
+
+/// 一层配置：只包含该层实际设置的键
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub kind: LayerKind,
+    /// 配置文件路径（环境变量与命令行层为 None）
+    pub path: Option<PathBuf>,
+    pub table: toml::Table,
+}
+
+/// 生效配置项的来源
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigOrigin {
+    /// 点分隔的配置键，如 `export.quality`
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: LayerKind,
+    /// 来源文件（默认值、环境变量与命令行为 None）
+    pub path: Option<String>,
+}
+
+/// 各层配置及其合并结果的来源
+#[derive(Debug, Clone)]
+pub struct ConfigLayers {
+    /// 用户配置文件
+    user_path: PathBuf,
+    /// 项目配置所在目录（第一张图片的目录）
+    project_dir: Option<PathBuf>,
+    /// 环境变量层（进程运行期间不变，创建时读取一次）
+    env: Option<ConfigLayer>,
+    /// 命令行参数对应的配置项
+    cli: toml::Table,
+    /// 已读取的各层（按优先级从低到高）
+    layers: Vec<ConfigLayer>,
+}
+
+impl ConfigLayers {
+    /// 创建各层，读取 MARKPIX_* 环境变量（无效的变量只提示，不影响其他层）
+    pub fn new(user_path: PathBuf, project_dir: Option<PathBuf>, cli: toml::Table) -> Self {
+        // 非 UTF-8 的环境变量不可能是配置项，直接跳过（std::env::vars 遇到会 panic）
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        let (env, warnings) = env_layer(vars);
+        for warning in warnings {
+            eprintln!("警告: 已忽略环境变量 {}", warning);
+        }
+        Self {
+            user_path,
+            project_dir,
+            env,
+            cli,
+            layers: Vec::new(),
+        }
+    }
+
+    /// 启动时加载配置
+    ///
+    /// 用户配置文件不存在时创建示例配置（有系统配置时不创建，避免示例覆盖系统配置）；
+    /// 用户配置文件无法解析或校验失败时备份原文件，本次跳过该层且不写入配置文件。
+    pub fn load(
+        source: &ConfigSource,
+        project_dir: Option<PathBuf>,
+        cli: toml::Table,
+    ) -> (Self, AppConfig, Vec<ConfigDiagnostic>) {
+        let mut layers = Self::new(source.path.clone(), project_dir, cli);
+        if !source.path.exists()
+            && !source.read_only
+            && !system_paths().iter().any(|path| path.exists())
+        {
+            let _ = AppConfig::default_with_examples().save_to(&source.path);
+        }
+
+        let mut diagnostics = layers.read();
+        let (config, invalid) = layers.resolve();
+        diagnostics.extend(invalid);
+        config.print_warnings();
+        for diagnostic in &mut diagnostics {
+            eprintln!("配置错误: {}", diagnostic);
+            if Path::new(&diagnostic.path) == source.path && diagnostic.kind != DiagnosticKind::Read
+            {
+                match diagnostic.backup() {
+                    Ok(backup) => eprintln!("已备份原配置文件到 {}", backup.display()),
+                    Err(err) => eprintln!("警告: {}", err),
+                }
+                eprintln!("本次忽略该文件，不会写入配置文件");
+            }
+        }
+        (layers, config, diagnostics)
+    }
+
+    /// 读取所有层，任一层有错误时返回错误（config 子命令使用，不创建或备份文件）
+    pub fn load_strict(
+        user_path: PathBuf,
+        project_dir: Option<PathBuf>,
+    ) -> Result<(Self, AppConfig), String> {
+        let mut layers = Self::new(user_path, project_dir, toml::Table::new());
+        let mut diagnostics = layers.read();
+        let (config, invalid) = layers.resolve();
+        diagnostics.extend(invalid);
+        match diagnostics.first() {
+            Some(diagnostic) => Err(format!("配置无效: {}", diagnostic)),
+            None => Ok((layers, config)),
+        }
+    }
+
+    /// 可能存在的配置文件（系统、用户、项目），用于监视外部修改
+    pub fn file_paths(&self) -> Vec<PathBuf> {
+        let mut paths = system_paths();
+        paths.push(self.user_path.clone());
+        paths.extend(self.project_path());
+        paths
+    }
+
+    fn project_path(&self) -> Option<PathBuf> {
+        self.project_dir
+            .as_ref()
+            .map(|dir| dir.join(PROJECT_FILE_NAME))
+    }
+
+    /// 重新读取配置文件（环境变量与命令行层保持不变），返回无法读取或解析的文件
+    pub fn read(&mut self) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut layers = Vec::new();
+        let files = system_paths()
+            .into_iter()
+            .map(|path| (LayerKind::System, path))
+            .chain([(LayerKind::User, self.user_path.clone())])
+            .chain(self.project_path().map(|path| (LayerKind::Project, path)));
+        for (kind, path) in files {
+            match read_file_layer(kind, &path) {
+                Ok(Some(layer)) => layers.push(layer),
+                Ok(None) => {}
+                Err(diagnostic) => diagnostics.push(diagnostic),
+            }
+        }
+
+        layers.extend(self.env.clone());
+        if !self.cli.is_empty() {
+            layers.push(ConfigLayer {
+                kind: LayerKind::Cli,
+                path: None,
+                table: self.cli.clone(),
+            });
+        }
+        self.layers = layers;
+        diagnostics
+    }
+
+    /// 逐键合并各层得到生效配置
+    ///
+    /// 合并结果校验失败时，按错误信息中的键找到设置它的最高层并跳过该层，直到配置有效；
+    /// 错误信息中没有任何层设置过的键时，跳过单独套用也无效的最高层。
+    pub fn resolve(&mut self) -> (AppConfig, Vec<ConfigDiagnostic>) {
+        let mut diagnostics = Vec::new();
+        loop {
+            let merged = self.merged();
+            let result = toml::Value::Table(merged)
+                .try_into::<AppConfig>()
+                .map_err(|e| e.message().trim().to_string())
+                .and_then(|config| config.validate().map(|_| config));
+            let message = match result {
+                Ok(config) => return (config, diagnostics),
+                Err(message) => message,
+            };
+            let index = error_key(&message)
+                .and_then(|key| self.setting_layer_index(&key))
+                .or_else(|| self.layers.iter().rposition(|layer| !layer_is_valid(layer)));
+            let Some(index) = index else {
+                // 各层单独有效但合并后无效且无法定位：不跳过无关的层，改用默认配置
+                diagnostics.push(ConfigDiagnostic::invalid(
+                    Path::new("合并后的配置"),
+                    "",
+                    message,
+                ));
+                return (AppConfig::default(), diagnostics);
+            };
+            let layer = self.layers.remove(index);
+            diagnostics.push(layer_diagnostic(&layer, message));
+        }
+    }
+
+    fn merged(&self) -> toml::Table {
+        let mut merged = toml::Table::new();
+        for layer in &self.layers {
+            merge_table(&mut merged, &layer.table);
+        }
+        merged
+    }
+
+    /// 用户配置之外各层合并的结果（按配置结构规范化），写入用户配置时据此去掉继承来的数组项
+    fn inherited(&self) -> toml::Table {
+        let mut merged = toml::Table::new();
+        for layer in self
+            .layers
+            .iter()
+            .filter(|layer| layer.kind != LayerKind::User)
+        {
+            merge_table(&mut merged, &layer.table);
+        }
+        toml::Value::Table(merged.clone())
+            .try_into::<AppConfig>()
+            .ok()
+            .and_then(|config| toml::Value::try_from(config).ok())
+            .and_then(|value| match value {
+                toml::Value::Table(table) => Some(table),
+                _ => None,
+            })
+            .unwrap_or(merged)
+    }
+
+    /// 设置了该键的最高层
+    fn top_layer_index(&self, key: &[String]) -> Option<usize> {
+        self.layers
+            .iter()
+            .rposition(|layer| lookup(&layer.table, key).is_some())
+    }
+
+    /// 设置了该键或其最长上级键的最高层，如 `keybindings.undo` 无层设置时取设置了 `keybindings` 的层
+    fn setting_layer_index(&self, key: &[String]) -> Option<usize> {
+        (1..=key.len())
+            .rev()
+            .find_map(|len| self.top_layer_index(&key[..len]))
+    }
+
+    /// 列出每个生效配置项的值与来源
+    pub fn origins(&self, config: &AppConfig) -> Vec<ConfigOrigin> {
+        let mut leaves = Vec::new();
+        if let Ok(value) = serde_json::to_value(config) {
+            collect_leaves(&value, &mut Vec::new(), &mut leaves);
+        }
+        leaves
+            .into_iter()
+            .map(|(key, value)| {
+                let layer = self.top_layer_index(&key).map(|index| &self.layers[index]);
+                ConfigOrigin {
+                    key: key.join("."),
+                    value,
+                    source: layer.map_or(LayerKind::Default, |layer| layer.kind),
+                    path: layer
+                        .and_then(|layer| layer.path.as_ref())
+                        .map(|path| path.to_string_lossy().to_string()),
+                }
+            })
+            .collect()
+    }
+
+    /// 把 old 与 new 之间变化的配置项写入用户配置文件，其余层不受影响
+    pub fn save_user(
+        &mut self,
+        old: &AppConfig,
+        new: &AppConfig,
+        source: &ConfigSource,
+    ) -> Result<(), String> {
+        source.ensure_writable()?;
+        let (Ok(old_value), Ok(new_value)) = (serde_json::to_value(old), serde_json::to_value(new))
+        else {
+            return Err("序列化配置失败".to_string());
+        };
+        let mut keys = Vec::new();
+        collect_changes(&old_value, &new_value, &mut Vec::new(), &mut keys);
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let table = write_keys(&source.path, new, &keys, &self.inherited())?;
+
+        let user = ConfigLayer {
+            kind: LayerKind::User,
+            path: Some(source.path.clone()),
+            table,
+        };
+        match self
+            .layers
+            .iter()
+            .position(|layer| layer.kind == LayerKind::User)
+        {
+            Some(index) => self.layers[index] = user,
+            None => {
+                let index = self
+                    .layers
+                    .partition_point(|layer| layer.kind < LayerKind::User);
+                self.layers.insert(index, user);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 把配置中的指定键写入配置文件，保留文件中的其他键，返回写入后的内容
+///
+/// 数组中的元素（如 `custom_actions.0.name`）按整个数组写入；按字段合并的数组
+/// 只写入与 inherited（其他层合并的结果）中不同的项，避免把系统配置的动作复制进来。
+pub fn write_keys(
+    path: &Path,
+    config: &AppConfig,
+    keys: &[Vec<String>],
+    inherited: &toml::Table,
+) -> Result<toml::Table, String> {
+    let mut table = match fs::read_to_string(path) {
+        Ok(content) => toml::from_str::<toml::Table>(&content).map_err(|e| {
+            format!(
+                "解析配置文件失败: {} ({})",
+                path.display(),
+                e.message().trim()
+            )
+        })?,
+        Err(_) => toml::Table::new(),
+    };
+    let toml::Value::Table(values) = toml::Value::try_from(config).map_err(|e| e.to_string())?
+    else {
+        return Err("序列化配置失败".to_string());
+    };
+    for key in keys {
+        let end = key
+            .iter()
+            .position(|part| part.parse::<usize>().is_ok())
+            .unwrap_or(key.len());
+        let key = &key[..end];
+        let value = match (lookup(&values, key), key) {
+            (Some(toml::Value::Array(items)), [name]) if keyed_field(name).is_some() => {
+                let inherited = lookup(inherited, key).and_then(toml::Value::as_array);
+                let own: Vec<_> = items
+                    .iter()
+                    .filter(|item| !inherited.is_some_and(|inherited| inherited.contains(item)))
+                    .cloned()
+                    .collect();
+                (!own.is_empty()).then_some(toml::Value::Array(own))
+            }
+            (value, _) => value.cloned(),
+        };
+        match value {
+            Some(value) => set_path(&mut table, key, value),
+            // 取值为空的可选项不会出现在 TOML 中
+            None => remove_path(&mut table, key),
+        }
+    }
+    let content = toml::to_string_pretty(&table).map_err(|e| e.to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, content)
+        .map_err(|e| format!("保存配置文件失败: {} ({})", path.display(), e))?;
+    Ok(table)
+}
+
+/// 系统配置文件，按优先级从低到高排列
+///
+/// $XDG_CONFIG_DIRS 中靠前的目录优先级更高，未设置时使用 /etc/xdg。
+pub fn system_paths() -> Vec<PathBuf> {
+    if !cfg!(unix) {
+        return vec![];
+    }
+    let dirs = std::env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_string());
+    dirs.split(':')
+        .filter(|dir| !dir.is_empty())
+        .rev()
+        .map(|dir| Path::new(dir).join("markpix").join("config.toml"))
+        .collect()
+}
+
+/// 读取一个配置文件层，文件不存在时返回 None
+fn read_file_layer(kind: LayerKind, path: &Path) -> Result<Option<ConfigLayer>, ConfigDiagnostic> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| ConfigDiagnostic::read(path, &e))?;
+    // 先按完整配置结构解析一次，类型错误可以定位到行列
+    toml::from_str::<AppConfig>(&content)
+        .map_err(|e| ConfigDiagnostic::parse(path, &content, &e))?;
+    let table = toml::from_str::<toml::Table>(&content)
+        .map_err(|e| ConfigDiagnostic::parse(path, &content, &e))?;
+    Ok(Some(ConfigLayer {
+        kind,
+        path: Some(path.to_path_buf()),
+        table,
+    }))
+}
+
+/// 从 MARKPIX_* 环境变量生成配置层
+///
+/// 字符串配置项直接使用原值，其余按 TOML 值解析（如 `true`、`90`、`["~/Pictures"]`）。
+/// 未知的键或类型不符的值会被忽略并返回说明。
+fn env_layer(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> (Option<ConfigLayer>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let known = serde_json::to_value(AppConfig::default()).unwrap_or_default();
+    let mut table = toml::Table::new();
+    for (name, raw) in vars {
+        let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let key: Vec<String> = rest
+            .to_lowercase()
+            .split("__")
+            .map(str::to_string)
+            .collect();
+        let pointer: String = key.iter().map(|part| format!("/{}", part)).collect();
+        let Some(default) = known.pointer(&pointer) else {
+            warnings.push(format!("{}: 未知配置项 {}", name, key.join(".")));
+            continue;
+        };
+        let value = if default.is_string() {
+            toml::Value::String(raw.clone())
+        } else {
+            parse_value(&raw).unwrap_or_else(|| toml::Value::String(raw.clone()))
+        };
+
+        let mut single = toml::Table::new();
+        set_path(&mut single, &key, value.clone());
+        // 单独套用该变量检查类型与取值（其余项为默认值）
+        let checked = toml::Value::Table(single)
+            .try_into::<AppConfig>()
+            .map_err(|e| e.message().trim().to_string())
+            .and_then(|config| config.validate());
+        if let Err(err) = checked {
+            warnings.push(format!("{}: {} 的值无效 ({})", name, key.join("."), err));
+            continue;
+        }
+        set_path(&mut table, &key, value);
+    }
+    let layer = (!table.is_empty()).then_some(ConfigLayer {
+        kind: LayerKind::Env,
+        path: None,
+        table,
+    });
+    (layer, warnings)
+}
+
+/// 把文本解析为 TOML 值
+fn parse_value(raw: &str) -> Option<toml::Value> {
+    format!("value = {}", raw)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+}
+
+/// 校验错误对应层的问题，文件层定位到出错的行列
+fn layer_diagnostic(layer: &ConfigLayer, message: String) -> ConfigDiagnostic {
+    match &layer.path {
+        Some(path) => {
+            let content = fs::read_to_string(path).unwrap_or_default();
+            ConfigDiagnostic::invalid(path, &content, message)
+        }
+        None => ConfigDiagnostic::invalid(Path::new(layer.kind.label()), "", message),
+    }
+}
+
+/// 单独套用该层（其余项为默认值）时配置是否有效
+fn layer_is_valid(layer: &ConfigLayer) -> bool {
+    toml::Value::Table(layer.table.clone())
+        .try_into::<AppConfig>()
+        .is_ok_and(|config| config.validate().is_ok())
+}
+
+/// 校验错误信息开头的配置键，如 `export.quality 超出范围` → [export, quality]，
+/// `custom_actions[0] 的 name` → [custom_actions]，`keybindings.undo 的快捷键` → [keybindings, undo]
+///
+/// 不对照默认配置检查：presets、palettes、keybindings 默认为空表，其下的键只存在于配置层中。
+fn error_key(message: &str) -> Option<Vec<String>> {
+    let token = message.split_whitespace().next()?;
+    let token = token.split('[').next().unwrap_or(token);
+    let token = token.trim_end_matches([':', '：']);
+    (!token.is_empty()).then(|| token.split('.').map(str::to_string).collect())
+}
+
+/// 逐键合并：两边都是表时递归合并，KEYED_ARRAYS 中的数组按字段合并，否则上层的值覆盖下层
+fn merge_table(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value, keyed_field(key)) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay), _) => {
+                merge_table(base, overlay)
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(overlay), Some(field)) => {
+                merge_keyed(base, overlay, field)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// 数组表用于合并的字段（如 custom_actions 按 name），其余数组返回 None
+fn keyed_field(key: &str) -> Option<&'static str> {
+    KEYED_ARRAYS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, field)| *field)
+}
+
+/// 上层中与下层同名的项替换下层的项（保持原位置），其余项追加在后
+fn merge_keyed(base: &mut Vec<toml::Value>, overlay: &[toml::Value], field: &str) {
+    for item in overlay {
+        let name = item.get(field);
+        match base
+            .iter_mut()
+            .find(|existing| name.is_some() && existing.get(field) == name)
+        {
+            Some(existing) => *existing = item.clone(),
+            None => base.push(item.clone()),
+        }
+    }
+}
+
+fn lookup<'a>(table: &'a toml::Table, key: &[String]) -> Option<&'a toml::Value> {
+    let (first, rest) = key.split_first()?;
+    let value = table.get(first)?;
+    match (rest.is_empty(), value) {
+        (true, value) => Some(value),
+        (false, toml::Value::Table(nested)) => lookup(nested, rest),
+        _ => None,
+    }
+}
+
+fn set_path(table: &mut toml::Table, key: &[String], value: toml::Value) {
+    let Some((first, rest)) = key.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        table.insert(first.clone(), value);
+        return;
+    }
+    let entry = table
+        .entry(first.clone())
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if !entry.is_table() {
+        *entry = toml::Value::Table(toml::Table::new());
+    }
+    if let toml::Value::Table(nested) = entry {
+        set_path(nested, rest, value);
+    }
+}
+
+fn remove_path(table: &mut toml::Table, key: &[String]) {
+    match key.split_first() {
+        Some((first, [])) => {
+            table.remove(first);
+        }
+        Some((first, rest)) => {
+            if let Some(toml::Value::Table(nested)) = table.get_mut(first) {
+                remove_path(nested, rest);
+            }
+        }
+        None => {}
+    }
+}
+
+/// 收集配置中的叶子项（对象递归展开，数组作为整体）
+fn collect_leaves(
+    value: &serde_json::Value,
+    key: &mut Vec<String>,
+    leaves: &mut Vec<(Vec<String>, serde_json::Value)>,
+) {
+    match value {
+        serde_json::Value::Object(fields) if !fields.is_empty() => {
+            for (name, field) in fields {
+                key.push(name.clone());
+                collect_leaves(field, key, leaves);
+                key.pop();
+            }
+        }
+        _ => leaves.push((key.clone(), value.clone())),
+    }
+}
+
+/// 收集两份配置之间取值不同的叶子项
+fn collect_changes(
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    key: &mut Vec<String>,
+    changes: &mut Vec<Vec<String>>,
+) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            for (name, field) in new {
+                key.push(name.clone());
+                collect_changes(
+                    old.get(name).unwrap_or(&serde_json::Value::Null),
+                    field,
+                    key,
+                    changes,
+                );
+                key.pop();
+            }
+            // 删除的键（如删除的预设）写入时从文件中移除
+            for name in old.keys().filter(|name| !new.contains_key(*name)) {
+                key.push(name.clone());
+                changes.push(key.clone());
+                key.pop();
+            }
+        }
+        _ if old != new => changes.push(key.clone()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CustomAction;
+
+    fn table(content: &str) -> toml::Table {
+        toml::from_str(content).unwrap()
+    }
+
+    fn layer(kind: LayerKind, content: &str) -> ConfigLayer {
+        ConfigLayer {
+            kind,
+            path: None,
+            table: table(content),
+        }
+    }
+
+    fn with_layers(layers: Vec<ConfigLayer>) -> ConfigLayers {
+        ConfigLayers {
+            user_path: PathBuf::from("config.toml"),
+            project_dir: None,
+            env: None,
+            cli: toml::Table::new(),
+            layers,
+        }
+    }
+
+    fn action(name: &str, command: &str) -> CustomAction {
+        CustomAction {
+            name: name.to_string(),
+            command: command.to_string(),
+            icon: None,
+        }
+    }
+
+    fn names(value: Option<&toml::Value>) -> Vec<(String, String)> {
+        value
+            .and_then(toml::Value::as_array)
+            .unwrap()
+            .iter()
+            .map(|item| {
+                (
+                    item["name"].as_str().unwrap().to_string(),
+                    item["command"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn key(path: &str) -> Vec<String> {
+        path.split('.').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn merge_table_merges_nested_tables_and_replaces_other_values() {
+        let mut merged = table(
+            r##"
+            theme = "dark"
+            watch_dirs = ["~/a"]
+            [export]
+            quality = 80
+            background_color = "#000000"
+            "##,
+        );
+        merge_table(
+            &mut merged,
+            &table(
+                r#"
+                watch_dirs = ["~/b"]
+                [export]
+                quality = 50
+                "#,
+            ),
+        );
+        assert_eq!(merged["theme"].as_str(), Some("dark"));
+        assert_eq!(merged["export"]["quality"].as_integer(), Some(50));
+        assert_eq!(
+            merged["export"]["background_color"].as_str(),
+            Some("#000000")
+        );
+        // 普通数组整体覆盖
+        assert_eq!(merged["watch_dirs"], toml::Value::Array(vec!["~/b".into()]));
+    }
+
+    #[test]
+    fn merge_table_merges_custom_actions_by_name() {
+        let mut merged = table(
+            r#"
+            [[custom_actions]]
+            name = "OCR"
+            command = "system-ocr {file}"
+            [[custom_actions]]
+            name = "Upload"
+            command = "upload {file}"
+            "#,
+        );
+        merge_table(
+            &mut merged,
+            &table(
+                r#"
+                [[custom_actions]]
+                name = "Share"
+                command = "share {file}"
+                [[custom_actions]]
+                name = "OCR"
+                command = "my-ocr {file}"
+                "#,
+            ),
+        );
+        assert_eq!(
+            names(merged.get("custom_actions")),
+            [
+                ("OCR".to_string(), "my-ocr {file}".to_string()),
+                ("Upload".to_string(), "upload {file}".to_string()),
+                ("Share".to_string(), "share {file}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_keyed_appends_items_without_the_field() {
+        let mut base = vec![toml::Value::Table(table("name = \"OCR\""))];
+        let unnamed = toml::Value::Table(table("command = \"x\""));
+        merge_keyed(&mut base, &[unnamed.clone(), unnamed.clone()], "name");
+        assert_eq!(base.len(), 3);
+        assert_eq!(base[1], unnamed);
+    }
+
+    #[test]
+    fn env_layer_parses_values_and_skips_invalid_variables() {
+        let vars = [
+            ("MARKPIX_THEME", "dark"),
+            ("MARKPIX_EXPORT__QUALITY", "75"),
+            ("MARKPIX_EARLY_EXIT", "true"),
+            ("MARKPIX_WATCH_DIRS", r#"["~/Pictures"]"#),
+            ("MARKPIX_EXPORT__QUALITI", "75"),
+            ("MARKPIX_COPY_ON_EXIT", "yes"),
+            ("MARKPIX_THEME_X", "dark"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let (layer, warnings) = env_layer(vars);
+        let layer = layer.unwrap();
+        assert_eq!(layer.kind, LayerKind::Env);
+        assert_eq!(
+            layer.table,
+            table(
+                r#"
+                theme = "dark"
+                early_exit = true
+                watch_dirs = ["~/Pictures"]
+                [export]
+                quality = 75
+                "#
+            )
+        );
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].starts_with("MARKPIX_EXPORT__QUALITI: 未知配置项 export.qualiti"));
+        assert!(warnings[1].starts_with("MARKPIX_COPY_ON_EXIT: copy_on_exit 的值无效"));
+        assert!(warnings[2].starts_with("MARKPIX_THEME_X: 未知配置项"));
+    }
+
+    #[test]
+    fn env_layer_rejects_out_of_range_values() {
+        let (layer, warnings) = env_layer([
+            ("MARKPIX_EXPORT__QUALITY".to_string(), "500".to_string()),
+            ("MARKPIX_THEME".to_string(), "blue".to_string()),
+        ]);
+        assert!(layer.is_none());
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    }
+
+    #[test]
+    fn write_keys_keeps_other_keys_and_inherited_actions_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "theme = \"dark\"\nsave_on_exit = true\n\n[export]\nquality = 80\n",
+        )
+        .unwrap();
+
+        let system = AppConfig {
+            custom_actions: vec![action("OCR", "system-ocr {file}")],
+            ..AppConfig::default()
+        };
+        let toml::Value::Table(inherited) = toml::Value::try_from(&system).unwrap() else {
+            panic!("配置应序列化为表");
+        };
+        let mut config = AppConfig {
+            theme: "dark".to_string(),
+            custom_actions: vec![
+                action("OCR", "system-ocr {file}"),
+                action("Upload", "upload {file}"),
+            ],
+            ..AppConfig::default()
+        };
+        config.export.quality = 60;
+        let keys = [key("export.quality"), key("custom_actions.1.name")];
+
+        let written = write_keys(&path, &config, &keys, &inherited).unwrap();
+        let on_disk = table(&fs::read_to_string(&path).unwrap());
+        assert_eq!(written, on_disk);
+        assert_eq!(on_disk["theme"].as_str(), Some("dark"));
+        assert_eq!(on_disk["save_on_exit"].as_bool(), Some(true));
+        assert_eq!(on_disk["export"]["quality"].as_integer(), Some(60));
+        assert!(on_disk["export"].get("background_color").is_none());
+        assert_eq!(
+            names(on_disk.get("custom_actions")),
+            [("Upload".to_string(), "upload {file}".to_string())]
+        );
+
+        // 只剩继承来的动作时从文件中移除
+        config.custom_actions.truncate(1);
+        let written = write_keys(&path, &config, &[key("custom_actions.1")], &inherited).unwrap();
+        assert!(written.get("custom_actions").is_none());
+    }
+
+    #[test]
+    fn write_keys_creates_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("markpix").join("config.toml");
+        let config = AppConfig {
+            early_exit: true,
+            ..AppConfig::default()
+        };
+        let written =
+            write_keys(&path, &config, &[key("early_exit")], &toml::Table::new()).unwrap();
+        assert_eq!(written, table("early_exit = true"));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn invalid_keybinding_drops_only_the_layer_that_set_it() {
+        let mut layers = with_layers(vec![
+            layer(LayerKind::System, "[keybindings]\nundo = \"Hyper+Z\""),
+            layer(LayerKind::User, "theme = \"dark\""),
+            layer(LayerKind::Cli, "early_exit = true"),
+        ]);
+        let (config, diagnostics) = layers.resolve();
+        assert_eq!(config.theme, "dark");
+        assert!(config.early_exit);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].path, LayerKind::System.label());
+        assert!(diagnostics[0].message.starts_with("keybindings.undo"));
+    }
+
+    #[test]
+    fn keybinding_conflict_blames_the_layer_of_the_conflicting_action() {
+        let mut layers = with_layers(vec![
+            layer(LayerKind::User, "[keybindings]\nredo = \"Ctrl+Q\""),
+            layer(LayerKind::Project, "[keybindings]\nundo = \"ctrl+q\""),
+            layer(LayerKind::Env, "theme = \"light\""),
+        ]);
+        let (config, diagnostics) = layers.resolve();
+        assert_eq!(config.theme, "light");
+        assert_eq!(config.keybindings.len(), 1);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].path, LayerKind::Project.label());
+    }
+
+    #[test]
+    fn invalid_preset_and_palette_drop_their_own_layers() {
+        let mut layers = with_layers(vec![
+            layer(
+                LayerKind::System,
+                "[palettes]\nbrand = [\"#123456\", \"nope\"]",
+            ),
+            layer(
+                LayerKind::User,
+                "theme = \"dark\"\n[palettes]\nmine = [\"#ffffff\"]",
+            ),
+            layer(LayerKind::Project, "[presets.big]\nstroke_width = 99"),
+            layer(LayerKind::Cli, "early_exit = true"),
+        ]);
+        let (config, diagnostics) = layers.resolve();
+        assert_eq!(config.theme, "dark");
+        assert!(config.early_exit);
+        assert!(config.palettes.contains_key("mine"));
+        assert!(config.presets.is_empty());
+        let sources: Vec<_> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            sources,
+            [LayerKind::Project.label(), LayerKind::System.label()]
+        );
+    }
+
+    #[test]
+    fn error_without_known_key_drops_the_layer_invalid_on_its_own() {
+        let mut layers = with_layers(vec![
+            layer(LayerKind::User, "theme = \"dark\""),
+            layer(LayerKind::Project, "export = 5"),
+            layer(LayerKind::Cli, "early_exit = true"),
+        ]);
+        let (config, diagnostics) = layers.resolve();
+        assert_eq!(config.theme, "dark");
+        assert!(config.early_exit);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].path, LayerKind::Project.label());
+    }
+}
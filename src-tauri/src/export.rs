@@ -0,0 +1,346 @@
+// MarkPix - 图片标注工具
+// 图片导出：按目标格式重新编码（PNG/JPEG/WebP/AVIF/BMP/QOI）
+
+use crate::color::{parse_css_color, Rgba};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, ImageEncoder, ImageFormat, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 支持的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    #[value(alias = "jpg")]
+    Jpeg,
+    Webp,
+    Avif,
+    Bmp,
+    Qoi,
+}
+
+impl OutputFormat {
+    /// 支持的扩展名列表（用于错误提示）
+    pub const EXTENSIONS: &'static str = "png, jpg, jpeg, webp, avif, bmp, qoi";
+
+    /// 按扩展名识别格式（不区分大小写）
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::Webp),
+            "avif" => Some(Self::Avif),
+            "bmp" => Some(Self::Bmp),
+            "qoi" => Some(Self::Qoi),
+            _ => None,
+        }
+    }
+
+    /// 按名称识别格式（配置或前端传入）
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::from_extension(name.trim().trim_start_matches('.'))
+            .ok_or_else(|| format!("不支持的输出格式: {}（支持 {}）", name, Self::EXTENSIONS))
+    }
+
+    /// 根据文件路径确定格式：无扩展名时默认 PNG，未知扩展名报错
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|e| e.to_str()) {
+            None => Ok(Self::Png),
+            Some(ext) => Self::from_extension(ext)
+                .ok_or_else(|| format!("不支持的输出格式: .{}（支持 {}）", ext, Self::EXTENSIONS)),
+        }
+    }
+
+    /// 该格式是否能保存透明通道
+    pub fn supports_alpha(self) -> bool {
+        !matches!(self, Self::Jpeg)
+    }
+
+    /// 对应的 MIME 类型
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+            Self::Avif => "image/avif",
+            Self::Bmp => "image/bmp",
+            Self::Qoi => "image/qoi",
+        }
+    }
+}
+
+/// 导出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// JPEG/WebP/AVIF 质量（1-100），WebP 为 100 时使用无损编码
+    pub quality: u8,
+    /// 不支持透明的格式（JPEG）用来填充透明区域的背景色
+    pub background_color: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            background_color: "#ffffff".to_string(),
+        }
+    }
+}
+
+/// 单次导出的参数
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub format: OutputFormat,
+    pub quality: u8,
+    pub background: Rgba,
+}
+
+impl ExportOptions {
+    /// 由配置生成导出参数，quality 为 None 时使用配置值
+    pub fn new(
+        format: OutputFormat,
+        config: &ExportConfig,
+        quality: Option<u8>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            format,
+            quality: quality.unwrap_or(config.quality).clamp(1, 100),
+            background: parse_css_color(&config.background_color)?,
+        })
+    }
+}
+
+/// 将图片编码为目标格式
+pub fn encode_image(image: &RgbaImage, options: &ExportOptions) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let mut bytes = Vec::new();
+    let encode_err = |e: image::ImageError| format!("编码 {:?} 失败: {}", options.format, e);
+
+    match options.format {
+        OutputFormat::Jpeg => {
+            let flattened = flatten_alpha(image, options.background);
+            JpegEncoder::new_with_quality(&mut bytes, options.quality)
+                .write_image(flattened.as_raw(), width, height, ExtendedColorType::Rgb8)
+                .map_err(encode_err)?;
+        }
+        OutputFormat::Webp => {
+            // image 的 WebPEncoder 只有无损模式，按质量有损编码使用 libwebp
+            let encoder = webp::Encoder::from_rgba(image.as_raw(), width, height);
+            let encoded = if options.quality >= 100 {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(options.quality as f32)
+            };
+            bytes = encoded.to_vec();
+        }
+        OutputFormat::Avif => {
+            AvifEncoder::new_with_speed_quality(&mut bytes, 6, options.quality)
+                .write_image(image.as_raw(), width, height, ExtendedColorType::Rgba8)
+                .map_err(encode_err)?;
+        }
+        OutputFormat::Png | OutputFormat::Bmp | OutputFormat::Qoi => {
+            let format = match options.format {
+                OutputFormat::Png => ImageFormat::Png,
+                OutputFormat::Bmp => ImageFormat::Bmp,
+                _ => ImageFormat::Qoi,
+            };
+            image
+                .write_to(&mut std::io::Cursor::new(&mut bytes), format)
+                .map_err(encode_err)?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// 将前端导出的图片数据（PNG）转码为目标格式
+///
+/// 目标为 PNG 时直接返回原始数据，避免重复编码。
+pub fn transcode(data: &[u8], options: &ExportOptions) -> Result<Vec<u8>, String> {
+    let source_format = image::guess_format(data).ok();
+    if options.format == OutputFormat::Png && source_format == Some(ImageFormat::Png) {
+        return Ok(data.to_vec());
+    }
+    let image = image::load_from_memory(data)
+        .map_err(|e| format!("解码图片数据失败: {}", e))?
+        .to_rgba8();
+    encode_image(&image, options)
+}
+
+/// 编码并写入文件
+pub fn save_image(image: &RgbaImage, path: &Path, options: &ExportOptions) -> Result<(), String> {
+    let bytes = encode_image(image, options)?;
+    fs::write(path, bytes).map_err(|e| format!("保存文件失败: {} ({})", path.display(), e))
+}
+
+/// 将透明区域合成到背景色上
+fn flatten_alpha(image: &RgbaImage, background: Rgba) -> RgbImage {
+    let blend = |fg: u8, bg: u8, alpha: u32| -> u8 {
+        ((fg as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8
+    };
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let alpha = a as u32;
+        image::Rgb([
+            blend(r, background.r, alpha),
+            blend(g, background.g, alpha),
+            blend(b, background.b, alpha),
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 带半透明像素与噪点的测试图片（噪点让有损压缩的质量差异体现在文件大小上）
+    fn sample_image() -> RgbaImage {
+        RgbaImage::from_fn(32, 32, |x, y| {
+            let noise = ((x * 37 + y * 91) % 256) as u8;
+            image::Rgba([
+                noise,
+                255 - noise,
+                (x * 8) as u8,
+                if x < 16 { 255 } else { 128 },
+            ])
+        })
+    }
+
+    fn options(format: OutputFormat, quality: u8) -> ExportOptions {
+        ExportOptions {
+            format,
+            quality,
+            background: Rgba::rgb(255, 255, 255),
+        }
+    }
+
+    #[test]
+    fn encodes_each_format_with_its_magic_bytes() {
+        let image = sample_image();
+        for (format, expected) in [
+            (OutputFormat::Png, ImageFormat::Png),
+            (OutputFormat::Jpeg, ImageFormat::Jpeg),
+            (OutputFormat::Webp, ImageFormat::WebP),
+            (OutputFormat::Avif, ImageFormat::Avif),
+            (OutputFormat::Bmp, ImageFormat::Bmp),
+            (OutputFormat::Qoi, ImageFormat::Qoi),
+        ] {
+            let bytes = encode_image(&image, &options(format, 80)).unwrap();
+            let magic_ok = match format {
+                OutputFormat::Png => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+                OutputFormat::Jpeg => bytes.starts_with(&[0xff, 0xd8, 0xff]),
+                OutputFormat::Webp => bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP",
+                OutputFormat::Avif => &bytes[4..12] == b"ftypavif",
+                OutputFormat::Bmp => bytes.starts_with(b"BM"),
+                OutputFormat::Qoi => bytes.starts_with(b"qoif"),
+            };
+            assert!(magic_ok, "{:?}: {:02x?}", format, &bytes[..12]);
+            assert_eq!(image::guess_format(&bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn jpeg_flattens_alpha_onto_background() {
+        let transparent = RgbaImage::from_pixel(16, 16, image::Rgba([0, 0, 255, 0]));
+        let options = ExportOptions {
+            background: Rgba::rgb(255, 0, 0),
+            ..options(OutputFormat::Jpeg, 95)
+        };
+        let bytes = encode_image(&transparent, &options).unwrap();
+        let decoded = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        let [r, g, b] = decoded.get_pixel(8, 8).0;
+        assert!(r > 240 && g < 16 && b < 16, "{:?}", (r, g, b));
+    }
+
+    #[test]
+    fn flatten_alpha_blends_by_coverage() {
+        let image =
+            RgbaImage::from_vec(3, 1, vec![0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 128]).unwrap();
+        let flattened = flatten_alpha(&image, Rgba::rgb(200, 100, 50));
+        assert_eq!(flattened.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(flattened.get_pixel(1, 0).0, [200, 100, 50]);
+        assert_eq!(flattened.get_pixel(2, 0).0, [100, 50, 25]);
+    }
+
+    #[test]
+    fn quality_is_clamped_and_defaults_to_config() {
+        let config = ExportConfig {
+            quality: 70,
+            background_color: "#000".to_string(),
+        };
+        let new = |quality| ExportOptions::new(OutputFormat::Jpeg, &config, quality).unwrap();
+        assert_eq!(new(None).quality, 70);
+        assert_eq!(new(Some(0)).quality, 1);
+        assert_eq!(new(Some(150)).quality, 100);
+        assert_eq!(new(Some(40)).quality, 40);
+        assert_eq!(new(None).background, Rgba::rgb(0, 0, 0));
+
+        let invalid = ExportConfig {
+            background_color: "nope".to_string(),
+            ..config
+        };
+        assert!(ExportOptions::new(OutputFormat::Jpeg, &invalid, None).is_err());
+    }
+
+    #[test]
+    fn quality_is_honoured_by_lossy_encoders() {
+        let image = sample_image();
+        for format in [OutputFormat::Jpeg, OutputFormat::Webp] {
+            let low = encode_image(&image, &options(format, 10)).unwrap();
+            let high = encode_image(&image, &options(format, 95)).unwrap();
+            assert!(
+                low.len() < high.len(),
+                "{:?}: {} >= {}",
+                format,
+                low.len(),
+                high.len()
+            );
+        }
+        // WebP 质量 100 为无损编码，像素保持不变
+        let lossless = encode_image(&image, &options(OutputFormat::Webp, 100)).unwrap();
+        assert_eq!(
+            image::load_from_memory(&lossless).unwrap().to_rgba8(),
+            image
+        );
+    }
+
+    #[test]
+    fn transcode_keeps_png_and_converts_other_formats() {
+        let image = sample_image();
+        let png = encode_image(&image, &options(OutputFormat::Png, 90)).unwrap();
+        assert_eq!(
+            transcode(&png, &options(OutputFormat::Png, 90)).unwrap(),
+            png
+        );
+
+        let bmp = transcode(&png, &options(OutputFormat::Bmp, 90)).unwrap();
+        assert_eq!(image::guess_format(&bmp).unwrap(), ImageFormat::Bmp);
+        assert_eq!(image::load_from_memory(&bmp).unwrap().to_rgba8(), image);
+
+        assert!(transcode(b"not an image", &options(OutputFormat::Jpeg, 90)).is_err());
+    }
+
+    #[test]
+    fn format_is_chosen_from_path_and_name() {
+        assert_eq!(
+            OutputFormat::from_path(Path::new("a/shot.JPG")).unwrap(),
+            OutputFormat::Jpeg
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("shot")).unwrap(),
+            OutputFormat::Png
+        );
+        let err = OutputFormat::from_path(Path::new("shot.gif")).unwrap_err();
+        assert!(err.contains(".gif"), "{}", err);
+        assert!(OutputFormat::from_path(Path::new("shot.txt")).is_err());
+
+        assert_eq!(
+            OutputFormat::from_name(" .webp").unwrap(),
+            OutputFormat::Webp
+        );
+        assert!(OutputFormat::from_name("tiff").is_err());
+    }
+}
@@ -0,0 +1,434 @@
+// MarkPix - 图片标注工具
+// 输入图片识别：按文件内容（魔数）判断格式，而不是依赖扩展名
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// 识别时读取的文件头长度，足以覆盖所有支持格式的魔数
+const SNIFF_LEN: u64 = 64;
+
+/// 错误信息中 URI 的最大显示长度（data: URI 可能非常长）
+const URI_DISPLAY_LEN: usize = 64;
+
+/// 读取输入图片时的错误
+#[derive(Debug)]
+pub enum InputError {
+    /// 文件不存在
+    NotFound(PathBuf),
+    /// 读写文件失败
+    Io { path: PathBuf, message: String },
+    /// 输入数据为空
+    Empty,
+    /// 内容不是可识别的图片格式
+    NotAnImage { source: String },
+    /// URI 格式无效或不受支持
+    InvalidUri { uri: String, reason: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "文件不存在: {}", path.display()),
+            Self::Io { path, message } => {
+                write!(f, "读取文件失败: {} ({})", path.display(), message)
+            }
+            Self::Empty => write!(f, "输入数据为空"),
+            Self::NotAnImage { source } => write!(f, "不是可识别的图片: {}", source),
+            Self::InvalidUri { uri, reason } => {
+                let shown: String = uri.chars().take(URI_DISPLAY_LEN).collect();
+                let ellipsis = if shown.len() < uri.len() { "..." } else { "" };
+                write!(f, "无效的 URI: {} ({}{})", reason, shown, ellipsis)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<InputError> for String {
+    fn from(err: InputError) -> Self {
+        err.to_string()
+    }
+}
+
+/// 按内容识别图片格式
+pub fn detect_format(data: &[u8], source: &str) -> Result<ImageFormat, InputError> {
+    if data.is_empty() {
+        return Err(InputError::Empty);
+    }
+    image::guess_format(data).map_err(|_| InputError::NotAnImage {
+        source: source.to_string(),
+    })
+}
+
+/// 只读取文件头识别图片格式
+pub fn detect_file_format(path: &Path) -> Result<ImageFormat, InputError> {
+    if !path.exists() {
+        return Err(InputError::NotFound(path.to_path_buf()));
+    }
+    let io_error = |e: std::io::Error| InputError::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    };
+    let mut header = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_LEN).read_to_end(&mut header))
+        .map_err(io_error)?;
+    detect_format(&header, &path.display().to_string())
+}
+
+/// 读取图片文件并返回 data URL，MIME 类型由文件内容决定
+pub fn read_data_url(path: &Path) -> Result<String, InputError> {
+    if !path.exists() {
+        return Err(InputError::NotFound(path.to_path_buf()));
+    }
+    let data = fs::read(path).map_err(|e| InputError::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+    let format = detect_format(&data, &path.display().to_string())?;
+    Ok(format!(
+        "data:{};base64,{}",
+        format.to_mime_type(),
+        STANDARD.encode(&data)
+    ))
+}
+
+/// 以 URI 形式传入的图片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UriInput {
+    /// 本地文件（`file://` URI 或 `markpix://open?path=` 链接）
+    Path(PathBuf),
+    /// `data:` URI 中内嵌的图片数据
+    Data(Vec<u8>),
+}
+
+/// 参数是否为支持的 URI（`file:`、`data:`、`markpix:`），其余参数按路径处理
+pub fn is_uri(arg: &str) -> bool {
+    ["file:", "data:", "markpix:"].iter().any(|scheme| {
+        arg.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// 解析 URI 形式的输入参数
+///
+/// - `file:///path/a%20b.png`：百分号解码为本地路径
+/// - `data:image/png;base64,...`：解码为图片数据
+/// - `markpix://open?path=/path/a.png`：其他桌面应用通过 URL scheme 传入的图片
+pub fn parse_uri(uri: &str) -> Result<UriInput, InputError> {
+    let invalid = |reason: &str| InputError::InvalidUri {
+        uri: uri.to_string(),
+        reason: reason.to_string(),
+    };
+    let (scheme, rest) = uri.split_once(':').ok_or_else(|| invalid("缺少 scheme"))?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "file" => file_uri_to_path(&format!("file:{}", rest))
+            .map(UriInput::Path)
+            .ok_or_else(|| invalid("仅支持本机 file:// 路径，且百分号编码必须完整")),
+        "data" => parse_data_uri(rest).map(UriInput::Data).map_err(invalid),
+        "markpix" => parse_markpix_link(rest)
+            .map(UriInput::Path)
+            .map_err(invalid),
+        _ => Err(invalid("不支持的 scheme")),
+    }
+}
+
+/// 解析 `data:` 之后的部分，只接受 base64 编码的图片
+fn parse_data_uri(rest: &str) -> Result<Vec<u8>, &'static str> {
+    let (media_type, payload) = rest.split_once(',').ok_or("缺少逗号分隔的数据部分")?;
+    let mut params = media_type.split(';');
+    let mime_type = params.next().unwrap_or_default().trim();
+    if !mime_type.to_ascii_lowercase().starts_with("image/") {
+        return Err("data: URI 的类型必须是 image/*");
+    }
+    if !params.any(|param| param.trim().eq_ignore_ascii_case("base64")) {
+        return Err("data: URI 仅支持 base64 编码");
+    }
+    let payload: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD.decode(payload).map_err(|_| "base64 数据无效")
+}
+
+/// 解析 `markpix:` 之后的部分，目前只支持 `//open?path=<绝对路径>`
+fn parse_markpix_link(rest: &str) -> Result<PathBuf, &'static str> {
+    let link = rest
+        .strip_prefix("//")
+        .ok_or("应为 markpix://open?path=...")?;
+    let (action, query) = link.split_once('?').unwrap_or((link, ""));
+    if action.trim_end_matches('/') != "open" {
+        return Err("不支持的操作，仅支持 markpix://open");
+    }
+    let value = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("path="))
+        .ok_or("缺少 path 参数")?;
+    // 查询参数中的 + 表示空格（字面量 + 应编码为 %2B）
+    let decoded = percent_decode(&value.replace('+', " ")).ok_or("path 参数的百分号编码不完整")?;
+    let value = String::from_utf8(decoded).map_err(|_| "path 参数不是有效的 UTF-8")?;
+    let path = match file_uri_to_path(&value) {
+        Some(path) => path,
+        None => PathBuf::from(value),
+    };
+    if !path.is_absolute() {
+        return Err("path 参数必须是绝对路径");
+    }
+    Ok(path)
+}
+
+/// 将 `file://` URI 转换为本地路径（百分号编码会被解码）
+///
+/// 只接受本机文件：主机部分必须为空或 `localhost`。
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(index) if &rest[..index] == "localhost" => &rest[index..],
+        _ => return None,
+    };
+    let decoded = percent_decode(path)?;
+    Some(PathBuf::from(String::from_utf8(decoded).ok()?))
+}
+
+/// 解码 URI 中的 `%XX` 序列，编码不完整时返回 None
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            // from_str_radix 接受 “+1” 这样的写法，需先确认是两位十六进制数字
+            let hex = value
+                .get(index + 1..index + 3)
+                .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// 展开输入参数：含通配符的参数按 glob 匹配，其余视为普通路径
+///
+/// 结果去重并保持参数顺序，匹配不到任何文件的通配符会返回错误。
+pub fn expand_paths(patterns: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            inputs.push(PathBuf::from(pattern));
+            continue;
+        }
+        let mut matched: Vec<PathBuf> = glob::glob(pattern)
+            .map_err(|e| format!("无效的通配符: {} ({})", pattern, e))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("没有匹配的文件: {}", pattern));
+        }
+        matched.sort();
+        inputs.extend(matched);
+    }
+
+    let mut seen = HashSet::new();
+    inputs.retain(|path| seen.insert(path.clone()));
+    Ok(inputs)
+}
+
+/// 生成临时文件路径（位于系统临时目录下的 markpix 目录，按需创建）
+///
+/// `prefix` 用于区分来源（如 stdin），文件名附加毫秒时间戳、进程号与序号避免冲突。
+pub fn temp_file_path(prefix: &str, extension: &str) -> Result<PathBuf, InputError> {
+    static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+    let temp_dir = std::env::temp_dir().join("markpix");
+    fs::create_dir_all(&temp_dir).map_err(|e| InputError::Io {
+        path: temp_dir.clone(),
+        message: e.to_string(),
+    })?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    Ok(temp_dir.join(format!(
+        "{}-{}-{}-{}.{}",
+        prefix,
+        timestamp,
+        std::process::id(),
+        sequence,
+        extension
+    )))
+}
+
+/// 将内存中的图片数据写入临时文件，扩展名与实际格式一致
+pub fn save_temp_image(data: &[u8], prefix: &str, source: &str) -> Result<PathBuf, InputError> {
+    let format = detect_format(data, source)?;
+    let extension = format.extensions_str().first().copied().unwrap_or("img");
+    let temp_path = temp_file_path(prefix, extension)?;
+    fs::write(&temp_path, data).map_err(|e| InputError::Io {
+        path: temp_path.clone(),
+        message: e.to_string(),
+    })?;
+    Ok(temp_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(uri: &str) -> String {
+        match parse_uri(uri) {
+            Err(InputError::InvalidUri { reason, .. }) => reason,
+            other => panic!("{}: {:?}", uri, other),
+        }
+    }
+
+    #[test]
+    fn recognises_supported_schemes() {
+        for arg in [
+            "file:///a.png",
+            "FILE:///a.png",
+            "data:image/png;base64,",
+            "markpix://open",
+        ] {
+            assert!(is_uri(arg), "{}", arg);
+        }
+        for arg in [
+            "/tmp/a.png",
+            "shot.png",
+            "C:\\a.png",
+            "http://example.com/a.png",
+            "fil",
+        ] {
+            assert!(!is_uri(arg), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn file_uri_is_percent_decoded() {
+        assert_eq!(
+            file_uri_to_path("file:///home/me/a%20b%E5%9B%BE.png"),
+            Some(PathBuf::from("/home/me/a b图.png"))
+        );
+        assert_eq!(
+            file_uri_to_path("file://localhost/tmp/x.png"),
+            Some(PathBuf::from("/tmp/x.png"))
+        );
+        assert_eq!(
+            parse_uri("File:///tmp/%41.png").unwrap(),
+            UriInput::Path(PathBuf::from("/tmp/A.png"))
+        );
+    }
+
+    #[test]
+    fn file_uri_must_be_local() {
+        assert_eq!(file_uri_to_path("file://server/share/x.png"), None);
+        assert_eq!(file_uri_to_path("file:/tmp/x.png"), None);
+        assert_eq!(file_uri_to_path("file://"), None);
+        assert!(reason("file://server/x.png").starts_with("仅支持本机 file:// 路径"));
+    }
+
+    #[test]
+    fn malformed_percent_escapes_are_rejected() {
+        for value in ["%", "a%2", "%zz", "%+1", "%2G", "%-1"] {
+            assert_eq!(percent_decode(value), None, "{}", value);
+        }
+        assert_eq!(percent_decode("a%2fb%2F"), Some(b"a/b/".to_vec()));
+        assert_eq!(file_uri_to_path("file:///tmp/%E5%9B.png"), None);
+        assert!(reason("file:///tmp/50%.png").contains("百分号编码必须完整"));
+        assert_eq!(
+            reason("markpix://open?path=%2Ftmp%2"),
+            "path 参数的百分号编码不完整"
+        );
+    }
+
+    #[test]
+    fn data_uri_is_base64_decoded() {
+        let encoded = STANDARD.encode(b"\x89PNG data");
+        let uri = format!("data:image/png;base64,{}", encoded);
+        assert_eq!(
+            parse_uri(&uri).unwrap(),
+            UriInput::Data(b"\x89PNG data".to_vec())
+        );
+        // 参数不区分大小写，数据中的换行会被忽略
+        let (head, tail) = encoded.split_at(4);
+        let uri = format!("DATA:Image/PNG;charset=x;BASE64,{}\n{}", head, tail);
+        assert_eq!(
+            parse_uri(&uri).unwrap(),
+            UriInput::Data(b"\x89PNG data".to_vec())
+        );
+    }
+
+    #[test]
+    fn invalid_data_uris_are_rejected() {
+        assert_eq!(reason("data:image/png;base64"), "缺少逗号分隔的数据部分");
+        assert_eq!(
+            reason("data:text/plain;base64,aGk="),
+            "data: URI 的类型必须是 image/*"
+        );
+        assert_eq!(reason("data:image/png,abc"), "data: URI 仅支持 base64 编码");
+        assert_eq!(reason("data:image/png;base64,@@@"), "base64 数据无效");
+    }
+
+    #[test]
+    fn markpix_link_opens_absolute_path() {
+        for (uri, expected) in [
+            (
+                "markpix://open?path=%2Fhome%2Fme%2Fa+b.png",
+                "/home/me/a b.png",
+            ),
+            ("markpix://open/?x=1&path=/tmp/a%2Bb.png", "/tmp/a+b.png"),
+            (
+                "MARKPIX://open?path=file%3A%2F%2F%2Ftmp%2Fx.png",
+                "/tmp/x.png",
+            ),
+        ] {
+            assert_eq!(
+                parse_uri(uri).unwrap(),
+                UriInput::Path(PathBuf::from(expected)),
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_markpix_links_are_rejected() {
+        assert_eq!(
+            reason("markpix:open?path=/a.png"),
+            "应为 markpix://open?path=..."
+        );
+        assert_eq!(
+            reason("markpix://edit?path=/a.png"),
+            "不支持的操作，仅支持 markpix://open"
+        );
+        assert_eq!(reason("markpix://open?file=/a.png"), "缺少 path 参数");
+        assert_eq!(
+            reason("markpix://open?path=a.png"),
+            "path 参数必须是绝对路径"
+        );
+        assert_eq!(
+            reason("markpix://open?path=%FF"),
+            "path 参数不是有效的 UTF-8"
+        );
+    }
+
+    #[test]
+    fn unknown_schemes_are_rejected() {
+        assert_eq!(reason("http://example.com/a.png"), "不支持的 scheme");
+        assert_eq!(reason("shot.png"), "缺少 scheme");
+        let long = format!("data:text/plain;base64,{}", "A".repeat(200));
+        let message = parse_uri(&long).unwrap_err().to_string();
+        assert!(message.ends_with("...)"), "{}", message);
+    }
+}
@@ -0,0 +1,214 @@
+// MarkPix - 图片标注工具
+// 单实例模式：后续启动通过 Unix 套接字把图片转交给已运行的窗口或后台进程
+
+use crate::presets::StartupOptions;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 单实例窗口的套接字文件名（位于 $XDG_RUNTIME_DIR）
+const SOCKET_NAME: &str = "markpix.sock";
+/// 后台进程的套接字文件名
+const DAEMON_SOCKET_NAME: &str = "markpix-daemon.sock";
+/// 收发请求的超时时间，避免卡住的连接阻塞另一端
+#[cfg(unix)]
+const REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 后续实例转交的启动参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstanceRequest {
+    /// 要打开的图片（绝对路径）
+    pub images: Vec<String>,
+    /// 输出文件名模式（-o，不含管道模式）
+    pub output_pattern: Option<String>,
+    /// 工具、预设与样式（--tool/--preset/--stroke-color/--stroke-width）
+    pub startup: StartupOptions,
+}
+
+/// 接收转交请求的实例类型，各自使用独立的套接字
+///
+/// 后台进程总是接收转交，单实例窗口只在后续启动也开启 single_instance 时接收。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceKind {
+    /// 开启 single_instance 的窗口
+    Window,
+    /// --daemon 后台进程
+    Daemon,
+}
+
+impl InstanceKind {
+    /// 按是否为后台模式确定实例类型
+    pub fn new(daemon: bool) -> Self {
+        if daemon {
+            Self::Daemon
+        } else {
+            Self::Window
+        }
+    }
+
+    fn socket_name(self) -> &'static str {
+        match self {
+            Self::Window => SOCKET_NAME,
+            Self::Daemon => DAEMON_SOCKET_NAME,
+        }
+    }
+}
+
+/// 套接字路径，未设置 $XDG_RUNTIME_DIR 时不启用单实例模式
+pub fn socket_path(kind: InstanceKind) -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join(kind.socket_name()))
+}
+
+/// 尝试把请求转交给已运行的实例
+///
+/// 转交成功返回 true；没有正在运行的实例时返回 false，由调用方自行启动窗口。
+#[cfg(unix)]
+pub fn forward(kind: InstanceKind, request: &InstanceRequest) -> Result<bool, String> {
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::net::UnixStream;
+
+    let Some(path) = socket_path(kind) else {
+        return Ok(false);
+    };
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        // 套接字不存在或残留（上次异常退出），视为没有运行中的实例
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(false)
+        }
+        Err(e) => return Err(format!("连接已运行的实例失败: {} ({})", path.display(), e)),
+    };
+    let message = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    stream
+        .set_read_timeout(Some(REPLY_TIMEOUT))
+        .and_then(|_| writeln!(stream, "{}", message))
+        .and_then(|_| BufReader::new(&stream).read_line(&mut reply))
+        .map_err(|e| format!("与已运行的实例通信失败: {}", e))?;
+    match reply.trim_end().strip_prefix("error: ") {
+        Some(err) => Err(format!("已运行的实例无法打开图片: {}", err)),
+        None if reply.trim_end() == "ok" => Ok(true),
+        None => Err("已运行的实例没有应答".to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(_kind: InstanceKind, _request: &InstanceRequest) -> Result<bool, String> {
+    Ok(false)
+}
+
+/// 在后台线程监听后续实例的请求，每个请求调用一次 handler
+///
+/// 每个连接在单独的线程中处理，卡住的客户端不会阻塞后续请求。
+#[cfg(unix)]
+pub fn listen<F>(kind: InstanceKind, handler: F) -> Result<(), String>
+where
+    F: Fn(InstanceRequest) -> Result<(), String> + Send + Sync + 'static,
+{
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
+
+    let path = socket_path(kind).ok_or("未设置 XDG_RUNTIME_DIR")?;
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(format!("已有实例在监听 {}", path.display()));
+        }
+        // 清理上次异常退出残留的套接字
+        let _ = std::fs::remove_file(&path);
+    }
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| format!("创建套接字失败: {} ({})", path.display(), e))?;
+
+    let handler = Arc::new(handler);
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let handler = Arc::clone(&handler);
+            std::thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+                let mut line = String::new();
+                // 探测连接（如另一个实例检查套接字是否存活）不发送数据，直接忽略
+                if !matches!(BufReader::new(&stream).read_line(&mut line), Ok(n) if n > 0) {
+                    return;
+                }
+                let result = serde_json::from_str::<InstanceRequest>(&line)
+                    .map_err(|e| format!("无效的请求: {}", e))
+                    .and_then(|request| handler(request));
+                let reply = match result {
+                    Ok(()) => "ok".to_string(),
+                    Err(err) => format!("error: {}", err.replace('\n', " ")),
+                };
+                let _ = writeln!(stream, "{}", reply);
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen<F>(_kind: InstanceKind, _handler: F) -> Result<(), String>
+where
+    F: Fn(InstanceRequest) -> Result<(), String> + Send + Sync + 'static,
+{
+    Err("单实例模式仅支持 Unix 系统".to_string())
+}
+
+/// 退出时删除本实例创建的套接字
+pub fn remove_socket(kind: InstanceKind) {
+    if let Some(path) = socket_path(kind) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    /// 套接字路径取自进程级环境变量，各场景放在同一个测试中依次执行
+    #[test]
+    fn forward_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("XDG_RUNTIME_DIR", dir.path());
+        let request = InstanceRequest {
+            images: vec!["/tmp/a.png".to_string()],
+            ..Default::default()
+        };
+
+        // 没有实例在监听
+        assert_eq!(forward(InstanceKind::Window, &request), Ok(false));
+
+        // 残留的套接字：转交视为没有实例，监听时清理后重新创建
+        let stale = socket_path(InstanceKind::Window).unwrap();
+        drop(UnixListener::bind(&stale).unwrap());
+        assert!(stale.exists());
+        assert_eq!(forward(InstanceKind::Window, &request), Ok(false));
+        listen(InstanceKind::Window, |request| {
+            match request.images.as_slice() {
+                [image] if image.ends_with(".png") => Ok(()),
+                _ => Err("无法打开\n图片".to_string()),
+            }
+        })
+        .unwrap();
+
+        // 只连接不发送数据的探测不影响后续请求
+        drop(UnixStream::connect(&stale).unwrap());
+        // 卡住不发送数据的客户端不阻塞其他请求
+        let _stuck = UnixStream::connect(&stale).unwrap();
+
+        assert_eq!(forward(InstanceKind::Window, &request), Ok(true));
+        let bad = InstanceRequest::default();
+        assert_eq!(
+            forward(InstanceKind::Window, &bad),
+            Err("已运行的实例无法打开图片: 无法打开 图片".to_string())
+        );
+
+        // 已有实例在监听时不能重复监听，另一类实例的套接字互不影响
+        assert!(listen(InstanceKind::Window, |_| Ok(())).is_err());
+        assert_eq!(forward(InstanceKind::Daemon, &request), Ok(false));
+
+        remove_socket(InstanceKind::Window);
+        assert!(!stale.exists());
+    }
+}
@@ -0,0 +1,426 @@
+// MarkPix - 图片标注工具
+// 快捷键：解析并规范化按键组合，合并配置文件 [keybindings] 与默认快捷键并检查冲突
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// 自定义动作的动作 ID 前缀，后接动作名称，如 `custom:OCR`
+pub const CUSTOM_ACTION_PREFIX: &str = "custom:";
+
+/// 默认快捷键（动作 ID 与前端一致：工具名称及编辑命令）
+const DEFAULT_BINDINGS: &[(&str, &[&str])] = &[
+    ("select", &["V"]),
+    ("pan", &["H"]),
+    ("rectangle", &["R"]),
+    ("ellipse", &["E"]),
+    ("arrow", &["A"]),
+    ("line", &["L"]),
+    ("text", &["T"]),
+    ("brush", &["B"]),
+    ("marker", &["M"]),
+    ("blur", &["U"]),
+    ("crop", &["C"]),
+    ("magnifier", &["Z"]),
+    ("save", &["Ctrl+S"]),
+    ("copy", &["Ctrl+C"]),
+    ("paste", &["Ctrl+V"]),
+    ("undo", &["Ctrl+Z"]),
+    ("redo", &["Ctrl+Y", "Ctrl+Shift+Z"]),
+    ("delete", &["Delete", "Backspace"]),
+    ("next_image", &["PageDown"]),
+    ("previous_image", &["PageUp"]),
+];
+
+/// 具名按键：可接受的写法（小写）与规范名称（与浏览器 KeyboardEvent.key 一致）
+const NAMED_KEYS: &[(&[&str], &str)] = &[
+    (&["enter", "return"], "Enter"),
+    (&["escape", "esc"], "Escape"),
+    (&["tab"], "Tab"),
+    (&["space"], "Space"),
+    (&["backspace"], "Backspace"),
+    (&["delete", "del"], "Delete"),
+    (&["insert", "ins"], "Insert"),
+    (&["home"], "Home"),
+    (&["end"], "End"),
+    (&["pageup", "pgup"], "PageUp"),
+    (&["pagedown", "pgdn"], "PageDown"),
+    (&["arrowup", "up"], "ArrowUp"),
+    (&["arrowdown", "down"], "ArrowDown"),
+    (&["arrowleft", "left"], "ArrowLeft"),
+    (&["arrowright", "right"], "ArrowRight"),
+    (&["plus", "+"], "Plus"),
+];
+
+/// 配置文件中一个动作的快捷键：单个按键组合或列表，空字符串或空列表表示不绑定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl KeyBinding {
+    fn chords(&self) -> Vec<&str> {
+        match self {
+            Self::Single(chord) => vec![chord.as_str()],
+            Self::Multiple(chords) => chords.iter().map(String::as_str).collect(),
+        }
+        .into_iter()
+        .filter(|chord| !chord.trim().is_empty())
+        .collect()
+    }
+}
+
+/// 规范化的按键组合，显示为 `Ctrl+Alt+Shift+Meta+键`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    /// 字母为大写，具名按键为规范名称（如 PageDown、F5）
+    pub key: String,
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pressed, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.meta, "Meta"),
+        ] {
+            if pressed {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    /// 解析 `ctrl+shift+z`、`Ctrl+PageDown`、`F5`、`Ctrl++` 等写法（不区分大小写）
+    ///
+    /// 单个符号键（如 `?`、`/`、`+`）本身已体现 Shift，忽略 Shift 修饰键，与前端按键事件一致。
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        // 末尾的 “+” 是按键本身（`+`、`Ctrl++`），其余 “+” 分隔修饰键
+        let (modifiers, key) = match value.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if value == "+" => ("", "+"),
+            None => value.rsplit_once('+').unwrap_or(("", value)),
+        };
+        let key = key.trim();
+        let modifiers: Vec<&str> = match modifiers {
+            "" => Vec::new(),
+            modifiers => modifiers.split('+').map(str::trim).collect(),
+        };
+        let mut chord = Chord {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: parse_key(key)?,
+        };
+        for modifier in &modifiers {
+            let flag = match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => &mut chord.ctrl,
+                "alt" | "option" => &mut chord.alt,
+                "shift" => &mut chord.shift,
+                "meta" | "super" | "cmd" | "command" | "win" => &mut chord.meta,
+                "" => return Err("修饰键之间缺少按键".to_string()),
+                other => return Err(format!("未知的修饰键 {}", other)),
+            };
+            if *flag {
+                return Err(format!("修饰键 {} 重复", modifier));
+            }
+            *flag = true;
+        }
+        let mut chars = chord.key.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if !c.is_alphanumeric() {
+                chord.shift = false;
+            }
+        }
+        if chord.key == "Plus" {
+            chord.shift = false;
+        }
+        Ok(chord)
+    }
+}
+
+/// 解析按键部分：具名按键、F1-F24 或单个字符
+fn parse_key(key: &str) -> Result<String, String> {
+    if key.is_empty() {
+        return Err("按键为空".to_string());
+    }
+    let lower = key.to_lowercase();
+    if let Some((_, name)) = NAMED_KEYS
+        .iter()
+        .find(|(aliases, _)| aliases.contains(&lower.as_str()))
+    {
+        return Ok(name.to_string());
+    }
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        if (1..=24).contains(&number) {
+            return Ok(format!("F{}", number));
+        }
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() => Ok(c.to_uppercase().collect()),
+        _ => Err(format!("未知的按键 {}", key)),
+    }
+}
+
+/// 所有内置动作 ID
+pub fn builtin_actions() -> impl Iterator<Item = &'static str> {
+    DEFAULT_BINDINGS.iter().map(|(action, _)| *action)
+}
+
+/// 动作 ID 到规范化按键组合的映射
+pub type ResolvedKeybindings = BTreeMap<String, Vec<String>>;
+
+/// 合并默认快捷键与配置中的覆盖项，返回动作 ID 到规范化按键组合的映射及警告
+///
+/// 未知的动作、无法解析的按键组合以及多个动作使用同一按键组合时返回错误，
+/// 错误信息以出错的配置键开头（如 `keybindings.undo`），便于定位到配置文件中的行。
+/// 自定义动作被重命名或删除后留下的绑定只忽略该项并给出警告，不影响其他配置。
+pub fn resolve(
+    overrides: &BTreeMap<String, KeyBinding>,
+    custom_actions: &[String],
+) -> Result<(ResolvedKeybindings, Vec<String>), String> {
+    let mut warnings = Vec::new();
+    let mut stale = Vec::new();
+    for action in overrides.keys() {
+        if let Some(name) = action.strip_prefix(CUSTOM_ACTION_PREFIX) {
+            if !custom_actions.iter().any(|custom| custom == name) {
+                warnings.push(format!(
+                    "keybindings.{} 没有名为 {} 的自定义动作，已忽略该快捷键",
+                    action, name
+                ));
+                stale.push(action.as_str());
+            }
+        } else if !builtin_actions().any(|builtin| builtin == action) {
+            let actions: Vec<_> = builtin_actions().collect();
+            return Err(format!(
+                "keybindings.{} 不是可绑定的动作（可选 {}，或 {}<自定义动作名称>）",
+                action,
+                actions.join(", "),
+                CUSTOM_ACTION_PREFIX
+            ));
+        }
+    }
+
+    // 先放入未被覆盖的默认快捷键，冲突时报告配置文件中设置的动作（默认快捷键之间没有冲突）
+    let defaults = DEFAULT_BINDINGS
+        .iter()
+        .filter(|(action, _)| !overrides.contains_key(*action))
+        .map(|(action, chords)| (action.to_string(), chords.to_vec()));
+    let configured = overrides
+        .iter()
+        .filter(|(action, _)| !stale.contains(&action.as_str()))
+        .map(|(action, binding)| (action.clone(), binding.chords()));
+
+    let mut owners: BTreeMap<Chord, String> = BTreeMap::new();
+    let mut resolved = BTreeMap::new();
+    for (action, chords) in defaults.chain(configured) {
+        let mut normalized: Vec<String> = Vec::new();
+        for raw in chords {
+            let chord: Chord = raw.parse().map_err(|err| {
+                format!("keybindings.{} 的快捷键 \"{}\" 无效: {}", action, raw, err)
+            })?;
+            if let Some(owner) = owners.get(&chord) {
+                if owner == &action {
+                    continue;
+                }
+                return Err(format!(
+                    "keybindings.{} 的快捷键 {} 与 {} 冲突（可将 {} 设为 \"\" 解除绑定）",
+                    action, chord, owner, owner
+                ));
+            }
+            owners.insert(chord.clone(), action.clone());
+            normalized.push(chord.to_string());
+        }
+        if !normalized.is_empty() {
+            resolved.insert(action, normalized);
+        }
+    }
+    Ok((resolved, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(value: &str) -> String {
+        value
+            .parse::<Chord>()
+            .unwrap_or_else(|err| panic!("{}: {}", value, err))
+            .to_string()
+    }
+
+    fn overrides(entries: &[(&str, KeyBinding)]) -> BTreeMap<String, KeyBinding> {
+        entries
+            .iter()
+            .map(|(action, binding)| (action.to_string(), binding.clone()))
+            .collect()
+    }
+
+    fn single(chord: &str) -> KeyBinding {
+        KeyBinding::Single(chord.to_string())
+    }
+
+    #[test]
+    fn normalises_aliases_and_modifier_order() {
+        for (raw, expected) in [
+            ("ctrl+s", "Ctrl+S"),
+            ("control+s", "Ctrl+S"),
+            ("shift+ctrl+z", "Ctrl+Shift+Z"),
+            ("cmd+option+k", "Alt+Meta+K"),
+            ("esc", "Escape"),
+            ("pgdn", "PageDown"),
+            ("ctrl+pgup", "Ctrl+PageUp"),
+            (" Ctrl + Del ", "Ctrl+Delete"),
+            ("space", "Space"),
+            ("f5", "F5"),
+            ("+", "Plus"),
+            ("ctrl++", "Ctrl+Plus"),
+            ("Ctrl+plus", "Ctrl+Plus"),
+        ] {
+            assert_eq!(chord(raw), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn shift_is_dropped_on_symbol_keys_only() {
+        for (raw, expected) in [
+            ("shift+/", "/"),
+            ("shift+?", "?"),
+            ("ctrl+shift+=", "Ctrl+="),
+            ("shift++", "Plus"),
+            ("shift+plus", "Plus"),
+            ("shift+r", "Shift+R"),
+            ("shift+1", "Shift+1"),
+            ("shift+f5", "Shift+F5"),
+            ("ctrl+shift+pagedown", "Ctrl+Shift+PageDown"),
+        ] {
+            assert_eq!(chord(raw), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn matches_keyboard_event_chords() {
+        // 前端 chordFromEvent 由 KeyboardEvent 生成的组合，解析后应保持不变
+        for event in [
+            "Ctrl+Shift+Z",
+            "Alt+Meta+K",
+            "Ctrl+Plus",
+            "?",
+            "Shift+R",
+            "Space",
+            "Escape",
+            "ArrowLeft",
+            "F12",
+            "Ctrl+Alt+Shift+Meta+Delete",
+        ] {
+            assert_eq!(chord(event), event);
+        }
+    }
+
+    #[test]
+    fn invalid_chords_are_rejected() {
+        for (raw, message) in [
+            ("", "按键为空"),
+            ("ctrl+", "按键为空"),
+            ("hyper+z", "未知的修饰键 hyper"),
+            ("ctrl+ctrl+z", "修饰键 ctrl 重复"),
+            ("ctrl++z", "修饰键之间缺少按键"),
+            ("ctrl+ab", "未知的按键 ab"),
+            ("f25", "未知的按键 f25"),
+        ] {
+            let err = raw.parse::<Chord>().unwrap_err();
+            assert!(err.contains(message), "{}: {}", raw, err);
+        }
+    }
+
+    #[test]
+    fn defaults_apply_without_overrides() {
+        let (resolved, warnings) = resolve(&BTreeMap::new(), &[]).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(resolved.len(), DEFAULT_BINDINGS.len());
+        assert_eq!(resolved["redo"], ["Ctrl+Y", "Ctrl+Shift+Z"]);
+        assert_eq!(resolved["next_image"], ["PageDown"]);
+    }
+
+    #[test]
+    fn overrides_are_normalised_and_deduplicated() {
+        let overrides = overrides(&[
+            ("undo", single("ctrl+u")),
+            (
+                "redo",
+                KeyBinding::Multiple(vec!["ctrl+shift+u".to_string(), "CTRL+SHIFT+U".to_string()]),
+            ),
+        ]);
+        let (resolved, _) = resolve(&overrides, &[]).unwrap();
+        assert_eq!(resolved["undo"], ["Ctrl+U"]);
+        assert_eq!(resolved["redo"], ["Ctrl+Shift+U"]);
+    }
+
+    #[test]
+    fn conflict_with_default_binding_is_reported() {
+        let err = resolve(&overrides(&[("rectangle", single("a"))]), &[]).unwrap_err();
+        assert!(err.starts_with("keybindings.rectangle "), "{}", err);
+        assert!(err.contains("与 arrow 冲突"), "{}", err);
+    }
+
+    #[test]
+    fn conflict_between_configured_actions_is_reported() {
+        let overrides = overrides(&[("redo", single("Ctrl+Q")), ("undo", single("ctrl+q"))]);
+        let err = resolve(&overrides, &[]).unwrap_err();
+        assert!(err.starts_with("keybindings.undo "), "{}", err);
+        assert!(err.contains("Ctrl+Q 与 redo 冲突"), "{}", err);
+    }
+
+    #[test]
+    fn empty_binding_unbinds_the_action() {
+        let overrides = overrides(&[
+            ("arrow", single("")),
+            ("delete", KeyBinding::Multiple(vec![])),
+            ("rectangle", single("A")),
+        ]);
+        let (resolved, _) = resolve(&overrides, &[]).unwrap();
+        assert!(!resolved.contains_key("arrow"));
+        assert!(!resolved.contains_key("delete"));
+        assert_eq!(resolved["rectangle"], ["A"]);
+    }
+
+    #[test]
+    fn unknown_action_is_rejected() {
+        let err = resolve(&overrides(&[("zoom", single("Ctrl+K"))]), &[]).unwrap_err();
+        assert!(err.starts_with("keybindings.zoom "), "{}", err);
+    }
+
+    #[test]
+    fn binding_to_missing_custom_action_is_skipped_with_warning() {
+        let overrides = overrides(&[
+            ("custom:OCR", single("ctrl+o")),
+            // 与 save 的快捷键相同，但该动作已不存在，不应报告冲突
+            ("custom:Upload", single("Ctrl+S")),
+        ]);
+        let (resolved, warnings) = resolve(&overrides, &["OCR".to_string()]).unwrap();
+        assert_eq!(resolved["custom:OCR"], ["Ctrl+O"]);
+        assert!(!resolved.contains_key("custom:Upload"));
+        assert_eq!(resolved["save"], ["Ctrl+S"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("keybindings.custom:Upload "),
+            "{}",
+            warnings[0]
+        );
+    }
+}
@@ -1,21 +1,243 @@
 // MarkPix - 图片标注工具
 // Rust 后端核心模块
 
+pub mod annotation;
+pub mod batch;
+pub mod capture;
+pub mod clipboard;
+pub mod color;
+pub mod config_diagnostics;
+pub mod config_layers;
+pub mod export;
+pub mod input;
+pub mod instance;
+pub mod keybindings;
+pub mod outcome;
+pub mod output_pattern;
+pub mod presets;
+pub mod queue;
+pub mod render;
+pub mod tool_defaults;
+pub mod tools;
+mod tray;
+pub mod watch;
+
 use base64::{engine::general_purpose::STANDARD, Engine};
+use capture::CaptureConfig;
+use config_diagnostics::ConfigDiagnostic;
+use config_layers::{ConfigLayers, ConfigOrigin};
+use export::{ExportConfig, ExportOptions, OutputFormat};
+use instance::{InstanceKind, InstanceRequest};
+use keybindings::KeyBinding;
+use outcome::{ExitStatus, ResultFormat, SessionOutcome};
+use output_pattern::{OutputPattern, PatternContext};
+use presets::{PresetInfo, StartupOptions, StylePreset};
+use queue::{ImageQueue, QueueEntry, QueueInfo};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Mutex;
-use tauri::{Manager, State};
+use tauri::{Emitter, Manager, State};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tool_defaults::ToolDefaults;
+
+/// 托盘菜单中保留的最近打开图片数量
+const MAX_RECENT_IMAGES: usize = 10;
 
 /// 应用状态：存储 CLI 传入的参数
 pub struct AppState {
-    pub initial_image_path: Mutex<Option<String>>,
+    /// CLI 传入的图片队列（单张图片时队列长度为 1）
+    pub image_queue: Mutex<ImageQueue>,
     pub config: Mutex<AppConfig>,
-    pub cli_config_path: Mutex<Option<String>>,
+    /// 用户配置文件（设置界面的修改只写入该文件）
+    pub config_source: Mutex<ConfigSource>,
+    /// 各层配置（系统、用户、项目、环境变量、命令行）
+    pub config_layers: Mutex<ConfigLayers>,
     pub cli_output_pattern: Mutex<Option<String>>,
+    /// 本次会话已保存、复制、执行的内容（决定退出码）
+    pub outcome: Mutex<SessionOutcome>,
+    /// 退出时打印结果的格式（--print-result）
+    pub print_result: Mutex<Option<ResultFormat>>,
+    /// CLI 指定的输出格式（覆盖扩展名）
+    pub cli_output_format: Mutex<Option<OutputFormat>>,
+    /// CLI 指定的编码质量（覆盖配置）
+    pub cli_quality: Mutex<Option<u8>>,
+    /// 启动时的工具与样式（已套用预设）
+    pub startup_options: Mutex<StartupOptions>,
+    /// 是否在监听后续实例的请求（单实例模式）
+    pub single_instance: Mutex<bool>,
+    /// 本次会话保存过的文件（监视目录时忽略，规范路径）
+    pub own_saves: Mutex<HashSet<PathBuf>>,
+    /// 正在运行的目录监视（--watch 与配置 watch_dirs）
+    pub folder_watcher: Mutex<Option<watch::FolderWatcher>>,
+    /// 是否为后台模式（--daemon）：关闭窗口时重置会话并隐藏，不退出
+    pub daemon: Mutex<bool>,
+    /// 最近打开的图片（托盘菜单，最新在前）
+    pub recent_images: Mutex<Vec<String>>,
+    /// 加载配置时发现的问题（设置对话框展示）
+    pub config_diagnostics: Mutex<Vec<ConfigDiagnostic>>,
+    /// 监视配置文件的外部修改
+    pub config_watcher: Mutex<Vec<watch::FolderWatcher>>,
+}
+
+/// CLI 传入的启动参数
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    /// 初始图片路径，多张时按顺序组成队列
+    pub images: Vec<String>,
+    /// 配置文件路径
+    pub config_path: Option<String>,
+    /// 输出文件名模式
+    pub output_pattern: Option<String>,
+    /// 启动时最大化窗口
+    pub fullscreen: bool,
+    /// 复制/保存后的退出行为
+    pub exit_behavior: ExitBehavior,
+    /// 输出格式（覆盖扩展名）
+    pub output_format: Option<OutputFormat>,
+    /// 编码质量（覆盖配置）
+    pub quality: Option<u8>,
+    /// 退出时打印结果的格式
+    pub print_result: Option<ResultFormat>,
+    /// 启动时的工具、预设与样式
+    pub startup: StartupOptions,
+    /// 忽略单实例配置，总是打开新窗口
+    pub new_instance: bool,
+    /// 监视的目录（与配置 watch_dirs 合并）
+    pub watch_dirs: Vec<PathBuf>,
+    /// 后台模式：隐藏启动并显示托盘图标
+    pub daemon: bool,
+}
+
+/// 复制、保存后的退出行为
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ExitBehavior {
+    /// 复制或保存成功后立即退出
+    pub early_exit: bool,
+    /// 退出时自动复制结果到剪贴板
+    pub copy_on_exit: bool,
+    /// 退出时按输出模式自动保存结果
+    pub save_on_exit: bool,
+}
+
+impl AppState {
+    /// 复制/保存/退出行为：CLI 的 --early-exit 等作为命令行层参与配置合并，
+    /// 重新加载或保存配置后随之更新
+    fn exit_behavior(&self) -> ExitBehavior {
+        self.config.lock().unwrap().exit_behavior()
+    }
+
+    /// 是否为管道模式（-o -）：结果图片写入 stdout
+    fn is_stdout_output(&self) -> bool {
+        self.cli_output_pattern.lock().unwrap().as_deref() == Some("-")
+    }
+
+    /// 确定输出格式：显式指定 > CLI --output-format > 文件扩展名
+    fn output_format(
+        &self,
+        path: Option<&std::path::Path>,
+        explicit: Option<&str>,
+    ) -> Result<OutputFormat, String> {
+        if let Some(name) = explicit.filter(|f| !f.is_empty()) {
+            return OutputFormat::from_name(name);
+        }
+        if let Some(format) = *self.cli_output_format.lock().unwrap() {
+            return Ok(format);
+        }
+        path.map_or(Ok(OutputFormat::Png), OutputFormat::from_path)
+    }
+
+    /// 生成导出参数（质量：CLI > 配置）
+    fn export_options(&self, format: OutputFormat) -> Result<ExportOptions, String> {
+        let quality = *self.cli_quality.lock().unwrap();
+        ExportOptions::new(format, &self.config.lock().unwrap().export, quality)
+    }
+
+    /// 展开输出路径（模式：CLI > 显式传入 > 配置），需要时解码图片以填充尺寸与哈希
+    ///
+    /// keep_relative 为 true 时，非 CLI 模式的相对路径保持相对（保存对话框据此选择目录）。
+    fn expand_output_path(
+        &self,
+        pattern: Option<String>,
+        data: Option<&[u8]>,
+        input_path: Option<&str>,
+        keep_relative: bool,
+    ) -> Result<PathBuf, String> {
+        let cli_pattern = self
+            .cli_output_pattern
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|p| p != "-");
+        // 命令行 -o 的相对路径始终基于启动时的工作目录
+        let keep_relative = keep_relative && cli_pattern.is_none();
+        let pattern = cli_pattern
+            .or(pattern)
+            .unwrap_or_else(|| self.config.lock().unwrap().output_pattern.clone());
+        let pattern = OutputPattern::parse(&pattern)?;
+        let image = match data {
+            Some(data) if pattern.needs_image() => Some(
+                image::load_from_memory(data)
+                    .map_err(|e| format!("解码图片数据失败: {}", e))?
+                    .to_rgba8(),
+            ),
+            _ => None,
+        };
+        let context = PatternContext::new(input_path.map(Path::new), image.as_ref());
+        let context = if keep_relative {
+            context.keep_relative()
+        } else {
+            context
+        };
+        Ok(pattern.expand(&context))
+    }
+
+    /// 当前退出时应使用的退出码
+    fn exit_code(&self) -> i32 {
+        self.outcome.lock().unwrap().status().code()
+    }
+
+    /// 记录操作结果
+    fn record(&self, update: impl FnOnce(&mut SessionOutcome)) {
+        update(&mut self.outcome.lock().unwrap());
+    }
+
+    /// 记录已保存的文件（同时避免监视目录把它当作新截图打开）
+    fn record_saved(&self, path: &Path) {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.own_saves.lock().unwrap().insert(canonical);
+        self.record(|outcome| outcome.saved.push(path.to_string_lossy().to_string()));
+    }
+
+    /// 记录最近打开的图片（不含剪贴板、stdin 等临时文件），返回更新后的列表
+    fn record_recent(&self, images: &[String]) -> Vec<String> {
+        let temp_dir = std::env::temp_dir().join("markpix");
+        let mut recent = self.recent_images.lock().unwrap();
+        for image in images {
+            if Path::new(image).starts_with(&temp_dir) {
+                continue;
+            }
+            recent.retain(|path| path != image);
+            recent.insert(0, image.clone());
+        }
+        recent.truncate(MAX_RECENT_IMAGES);
+        recent.clone()
+    }
+
+    /// 退出时按 --print-result 打印结果（管道模式下 stdout 被图片占用，改为 stderr）
+    fn print_result(&self) {
+        let Some(format) = *self.print_result.lock().unwrap() else {
+            return;
+        };
+        let report = self.outcome.lock().unwrap().render(format);
+        if self.is_stdout_output() {
+            eprintln!("{}", report);
+        } else {
+            println!("{}", report);
+        }
+    }
 }
 
 /// 自定义动作配置
@@ -78,50 +300,199 @@ pub struct AppConfig {
     pub custom_actions: Vec<CustomAction>,
     /// 文本工具配置
     pub text_tool_config: TextToolConfig,
+    /// 其他绘图工具的默认样式
+    pub tool_defaults: ToolDefaults,
+    /// 命名的样式预设（与内置预设一起供 --preset 与工具栏选择）
+    pub presets: BTreeMap<String, StylePreset>,
+    /// 命名的调色板（颜色列表）
+    pub palettes: BTreeMap<String, Vec<String>>,
+    /// 快捷键：动作 ID（工具名称、save、undo、custom:<自定义动作名称> 等）到按键组合，只需写出要修改的动作
+    pub keybindings: BTreeMap<String, KeyBinding>,
+    /// 复制或保存成功后立即退出
+    pub early_exit: bool,
+    /// 退出时自动复制结果到剪贴板
+    pub copy_on_exit: bool,
+    /// 退出时按输出模式自动保存结果
+    pub save_on_exit: bool,
+    /// 导出设置（编码质量、透明背景填充色）
+    pub export: ExportConfig,
+    /// 截图命令（markpix capture）
+    pub capture: CaptureConfig,
+    /// 单实例模式：后续启动把图片转交给已打开的窗口
+    pub single_instance: bool,
+    /// 监视的目录：出现新图片时自动打开
+    pub watch_dirs: Vec<String>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             theme: "auto".to_string(),
-            output_pattern: "{input_file_base}_{YYYY_MM_DD-hh-mm-ss}_markpix.png".to_string(),
+            output_pattern: output_pattern::DEFAULT_OUTPUT_PATTERN.to_string(),
             custom_actions: vec![],
             text_tool_config: TextToolConfig::default(),
+            tool_defaults: ToolDefaults::default(),
+            presets: BTreeMap::new(),
+            palettes: BTreeMap::new(),
+            keybindings: BTreeMap::new(),
+            early_exit: false,
+            copy_on_exit: false,
+            save_on_exit: false,
+            export: ExportConfig::default(),
+            capture: CaptureConfig::default(),
+            single_instance: false,
+            watch_dirs: vec![],
+        }
+    }
+}
+
+/// 配置文件来源：实际加载的文件及能否写入
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSource {
+    /// 配置文件路径（CLI -c 指定的文件或默认位置）
+    pub path: PathBuf,
+    /// 是否由 CLI -c 指定
+    pub explicit: bool,
+    /// 文件不可写时为只读模式：设置仍在本次运行中生效，但不会保存
+    pub read_only: bool,
+}
+
+impl ConfigSource {
+    /// 按 CLI 参数确定配置文件
+    pub fn new(path: Option<&str>) -> Self {
+        let mut source = Self {
+            path: AppConfig::path_for(path),
+            explicit: path.is_some(),
+            read_only: false,
+        };
+        source.refresh();
+        source
+    }
+
+    /// 重新检查文件能否写入（文件不存在时视为可写，保存时创建）
+    pub fn refresh(&mut self) {
+        self.read_only = self.path.exists()
+            && fs::OpenOptions::new()
+                .append(true)
+                .open(&self.path)
+                .is_err();
+    }
+
+    /// 只读模式下返回错误
+    pub fn ensure_writable(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!("配置文件为只读，无法保存: {}", self.path.display()));
         }
+        Ok(())
     }
 }
 
 impl AppConfig {
-    /// 从默认配置文件加载
-    pub fn load() -> Self {
-        Self::load_from_path(&Self::config_path())
+    /// 加载系统、用户配置与环境变量合并后的配置（优先使用 CLI 指定的用户配置文件）
+    pub fn load_with(path: Option<&str>) -> Self {
+        ConfigLayers::load(&ConfigSource::new(path), None, toml::Table::new()).1
     }
 
-    /// 从指定路径加载配置
-    pub fn load_from(path: &str) -> Self {
-        Self::load_from_path(&PathBuf::from(path))
+    /// 读取、解析并校验单个已存在的配置文件（不合并其他层）
+    fn parse_file(config_path: &Path) -> Result<Self, ConfigDiagnostic> {
+        let content =
+            fs::read_to_string(config_path).map_err(|e| ConfigDiagnostic::read(config_path, &e))?;
+        let config: Self = toml::from_str(&content)
+            .map_err(|e| ConfigDiagnostic::parse(config_path, &content, &e))?;
+        config
+            .validate()
+            .map_err(|e| ConfigDiagnostic::invalid(config_path, &content, e))?;
+        Ok(config)
     }
 
-    /// 从指定路径加载配置（内部方法）
-    fn load_from_path(config_path: &PathBuf) -> Self {
-        if config_path.exists() {
-            if let Ok(content) = fs::read_to_string(config_path) {
-                if let Ok(config) = toml::from_str(&content) {
-                    return config;
-                }
+    /// 严格读取配置文件：文件不存在时返回带示例的默认配置，解析失败时返回错误
+    pub fn read_from(config_path: &Path) -> Result<Self, String> {
+        if !config_path.exists() {
+            return Ok(Self::default_with_examples());
+        }
+        Self::parse_file(config_path).map_err(|e| format!("配置文件无效: {}", e))
+    }
+
+    /// 检查配置取值是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if !matches!(self.theme.as_str(), "light" | "dark" | "auto") {
+            return Err(format!(
+                "theme 无效: {}（可选 light, dark, auto）",
+                self.theme
+            ));
+        }
+        output_pattern::validate_pattern(&self.output_pattern)
+            .map_err(|e| format!("output_pattern {}", e))?;
+        if !(1..=100).contains(&self.export.quality) {
+            return Err(format!(
+                "export.quality 超出范围 1-100: {}",
+                self.export.quality
+            ));
+        }
+        color::parse_css_color(&self.export.background_color)
+            .map_err(|e| format!("export.background_color {}", e))?;
+        for (index, action) in self.custom_actions.iter().enumerate() {
+            if action.name.trim().is_empty() || action.command.trim().is_empty() {
+                return Err(format!(
+                    "custom_actions[{}] 的 name 和 command 不能为空",
+                    index
+                ));
             }
         }
-        // 返回默认配置并创建示例配置文件
-        let default_config = Self::default_with_examples();
-        let _ = default_config.save();
-        default_config
+        self.capture.validate()?;
+        self.tool_defaults.validate()?;
+        for (name, preset) in &self.presets {
+            presets::validate_preset(name, preset)?;
+        }
+        for (name, colors) in &self.palettes {
+            presets::validate_palette(name, colors)?;
+        }
+        self.resolved_keybindings()?;
+        Ok(())
+    }
+
+    /// 列出配置文件中无法识别的键（拼写错误的键会被静默忽略，需单独提示）
+    pub fn unknown_keys(content: &str) -> Result<Vec<String>, String> {
+        let table: toml::Table =
+            toml::from_str(content).map_err(|e| format!("解析配置文件失败: {}", e))?;
+        let known = serde_json::to_value(Self::default()).map_err(|e| e.to_string())?;
+        let mut unknown = Vec::new();
+        collect_unknown_keys(&table, &known, "", &mut unknown);
+        Ok(unknown)
+    }
+
+    /// 按点分隔的键读取配置值，如 `export.quality`、`custom_actions.0.name`
+    pub fn get_value(&self, key: &str) -> Result<serde_json::Value, String> {
+        let root = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        root.pointer(&key_pointer(key))
+            .cloned()
+            .ok_or_else(|| format!("未知配置项: {}", key))
+    }
+
+    /// 按点分隔的键设置配置值，返回修改后的配置
+    ///
+    /// 字符串字段直接使用原始文本，其余字段按 JSON 解析（如 `true`、`80`、`[...]`）。
+    pub fn with_value(&self, key: &str, raw: &str) -> Result<Self, String> {
+        let mut root = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        let slot = root
+            .pointer_mut(&key_pointer(key))
+            .ok_or_else(|| format!("未知配置项: {}", key))?;
+        *slot = match slot {
+            serde_json::Value::String(_) => serde_json::Value::String(raw.to_string()),
+            serde_json::Value::Null => serde_json::from_str(raw)
+                .unwrap_or_else(|_| serde_json::Value::String(raw.to_string())),
+            _ => serde_json::from_str(raw).map_err(|e| format!("无效的值 {}: {}", raw, e))?,
+        };
+        let config: Self =
+            serde_json::from_value(root).map_err(|e| format!("{} 的值无效: {}", key, e))?;
+        config.validate()?;
+        Ok(config)
     }
 
     /// 创建带示例的默认配置
-    fn default_with_examples() -> Self {
-        let mut config = Self::default();
-        config.custom_actions = vec![
-            CustomAction {
+    pub fn default_with_examples() -> Self {
+        Self {
+            custom_actions: vec![CustomAction {
                 name: "打开所在文件夹".to_string(),
                 command: if cfg!(target_os = "windows") {
                     "explorer /select, \"{file}\"".to_string()
@@ -131,22 +502,99 @@ impl AppConfig {
                     "xdg-open \"$(dirname \"{file}\")\"".to_string()
                 },
                 icon: Some("folder".to_string()),
-            },
-        ];
-        config
+            }],
+            ..Self::default()
+        }
+    }
+
+    /// 导入另一份配置中的预设与调色板（同名的覆盖），返回导入后的配置与导入的键
+    pub fn with_imported_presets(&self, other: &Self) -> (Self, Vec<Vec<String>>) {
+        let mut config = self.clone();
+        let mut keys = Vec::new();
+        for (name, preset) in &other.presets {
+            config.presets.insert(name.clone(), preset.clone());
+            keys.push(vec!["presets".to_string(), name.clone()]);
+        }
+        for (name, colors) in &other.palettes {
+            config.palettes.insert(name.clone(), colors.clone());
+            keys.push(vec!["palettes".to_string(), name.clone()]);
+        }
+        (config, keys)
+    }
+
+    /// 合并默认快捷键后的快捷键映射（动作 ID → 规范化的按键组合），以及被忽略的绑定
+    pub fn resolved_keybindings(
+        &self,
+    ) -> Result<(keybindings::ResolvedKeybindings, Vec<String>), String> {
+        let custom_actions: Vec<String> = self
+            .custom_actions
+            .iter()
+            .map(|action| action.name.clone())
+            .collect();
+        keybindings::resolve(&self.keybindings, &custom_actions)
+    }
+
+    /// 打印不影响加载的配置问题（如指向已删除自定义动作的快捷键）
+    pub fn print_warnings(&self) {
+        if let Ok((_, warnings)) = self.resolved_keybindings() {
+            for warning in warnings {
+                eprintln!("警告: {}", warning);
+            }
+        }
     }
 
-    /// 保存配置到文件
-    pub fn save(&self) -> Result<(), String> {
-        let config_path = Self::config_path();
+    /// 与另一份配置相比取值不同的顶层配置项
+    pub fn changed_sections(&self, other: &Self) -> Vec<String> {
+        let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return vec![];
+        };
+        new.iter()
+            .filter(|(key, value)| old.get(*key) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// 配置中的退出行为
+    pub fn exit_behavior(&self) -> ExitBehavior {
+        ExitBehavior {
+            early_exit: self.early_exit,
+            copy_on_exit: self.copy_on_exit,
+            save_on_exit: self.save_on_exit,
+        }
+    }
+
+    /// 用前端传入的部分配置覆盖当前配置，未传入的字段保持不变
+    pub fn merged_with(&self, patch: serde_json::Value) -> Result<Self, String> {
+        let mut current = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let (Some(target), serde_json::Value::Object(fields)) = (current.as_object_mut(), patch)
+        {
+            target.extend(fields);
+        }
+        serde_json::from_value(current).map_err(|e| format!("配置格式无效: {}", e))
+    }
+
+    /// 保存配置到指定文件
+    pub fn save_to(&self, config_path: &Path) -> Result<(), String> {
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent).map_err(|e| e.to_string())?;
         }
-        let content = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(&config_path, content).map_err(|e| e.to_string())?;
+        let content = self.to_toml()?;
+        fs::write(config_path, content).map_err(|e| e.to_string())?;
         Ok(())
     }
 
+    /// 序列化为 TOML 文本
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// 配置文件路径：优先使用 CLI 指定的路径
+    pub fn path_for(path: Option<&str>) -> PathBuf {
+        path.map(PathBuf::from).unwrap_or_else(Self::config_path)
+    }
+
     /// 获取配置文件路径
     fn config_path() -> PathBuf {
         dirs::config_dir()
@@ -156,53 +604,258 @@ impl AppConfig {
     }
 }
 
+/// 将点分隔的配置键转换为 JSON Pointer
+fn key_pointer(key: &str) -> String {
+    key.split('.')
+        .filter(|part| !part.is_empty())
+        .fold(String::new(), |pointer, part| pointer + "/" + part)
+}
+
+/// 递归比较 TOML 表与已知配置结构，收集未知的键
+fn collect_unknown_keys(
+    table: &toml::Table,
+    known: &serde_json::Value,
+    prefix: &str,
+    unknown: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (known.get(key), value) {
+            (None, _) => unknown.push(path),
+            // 默认为空的表按名称索引（presets、palettes），其中的名称不做检查
+            (Some(serde_json::Value::Object(fields)), _) if fields.is_empty() => {}
+            (Some(known @ serde_json::Value::Object(_)), toml::Value::Table(nested)) => {
+                collect_unknown_keys(nested, known, &path, unknown)
+            }
+            _ => {}
+        }
+    }
+}
+
 /// 获取 CLI 传入的初始图片路径
 #[tauri::command]
 fn get_initial_image(state: State<AppState>) -> Option<String> {
-    state.initial_image_path.lock().unwrap().clone()
+    state
+        .image_queue
+        .lock()
+        .unwrap()
+        .current_path()
+        .map(str::to_string)
 }
 
-/// 读取图片文件并返回 Base64 编码
+/// 获取图片队列概览
 #[tauri::command]
-fn read_image_file(path: String) -> Result<String, String> {
-    let path = PathBuf::from(&path);
-    if !path.exists() {
-        return Err(format!("文件不存在: {}", path.display()));
-    }
+fn list_queue(state: State<AppState>) -> QueueInfo {
+    state.image_queue.lock().unwrap().info()
+}
 
-    let data = fs::read(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+/// 保存当前图片的标注快照并切换到下一张，已是最后一张时返回 None
+#[tauri::command]
+fn next_image(state: State<AppState>, snapshot: Option<serde_json::Value>) -> Option<QueueEntry> {
+    state.image_queue.lock().unwrap().step(1, snapshot)
+}
 
-    // 检测图片格式
-    let mime_type = match path.extension().and_then(|e| e.to_str()) {
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("webp") => "image/webp",
-        Some("bmp") => "image/bmp",
-        _ => "image/png",
-    };
+/// 保存当前图片的标注快照并切换到上一张，已是第一张时返回 None
+#[tauri::command]
+fn previous_image(
+    state: State<AppState>,
+    snapshot: Option<serde_json::Value>,
+) -> Option<QueueEntry> {
+    state.image_queue.lock().unwrap().step(-1, snapshot)
+}
 
-    let base64_data = STANDARD.encode(&data);
-    Ok(format!("data:{};base64,{}", mime_type, base64_data))
+/// 保存当前图片的标注快照并切换到指定图片
+#[tauri::command]
+fn select_image(
+    state: State<AppState>,
+    index: usize,
+    snapshot: Option<serde_json::Value>,
+) -> Result<QueueEntry, String> {
+    state
+        .image_queue
+        .lock()
+        .unwrap()
+        .select(index, snapshot)
+        .ok_or_else(|| format!("队列中没有第 {} 张图片", index + 1))
 }
 
-/// 保存图片到文件
+/// 读取图片文件并返回 Base64 编码（MIME 类型按文件内容识别）
 #[tauri::command]
-fn save_image_file(path: String, data: String) -> Result<(), String> {
+fn read_image_file(path: String) -> Result<String, String> {
+    Ok(input::read_data_url(&PathBuf::from(path))?)
+}
+
+/// 解码前端传入的图片数据
+fn decode_image_data(data: &str) -> Result<Vec<u8>, String> {
     // 支持两种格式：完整的 data URL 或纯 base64 数据
     let base64_data = if data.contains(',') {
         // 完整的 data URL 格式: data:image/png;base64,xxxxx
-        data.split(',').nth(1).unwrap_or(&data)
+        data.split(',').nth(1).unwrap_or(data)
     } else {
         // 纯 base64 数据
-        &data
+        data
     };
 
-    let bytes = STANDARD
+    STANDARD
         .decode(base64_data)
-        .map_err(|e| format!("Base64 解码失败: {}", e))?;
+        .map_err(|e| format!("Base64 解码失败: {}", e))
+}
 
+/// 保存图片到文件（按扩展名或显式格式重新编码）
+#[tauri::command]
+fn save_image_file(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    path: String,
+    data: String,
+    format: Option<String>,
+) -> Result<(), String> {
+    let format = state.output_format(Some(std::path::Path::new(&path)), format.as_deref())?;
+    let options = state.export_options(format)?;
+    let bytes = export::transcode(&decode_image_data(&data)?, &options)?;
+    output_pattern::ensure_parent_dir(std::path::Path::new(&path))?;
     fs::write(&path, bytes).map_err(|e| format!("保存文件失败: {}", e))?;
+    state.record_saved(Path::new(&path));
+    exit_if_early(&app, &state);
+    Ok(())
+}
+
+/// 生成保存对话框的默认路径（CLI -o 优先，否则使用传入或配置中的模式，相对路径保持相对）
+#[tauri::command]
+fn expand_output_pattern(
+    state: State<AppState>,
+    pattern: Option<String>,
+    data: Option<String>,
+    input_path: Option<String>,
+) -> Result<String, String> {
+    let bytes = data.as_deref().map(decode_image_data).transpose()?;
+    let path = state.expand_output_path(pattern, bytes.as_deref(), input_path.as_deref(), true)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 开启 early_exit 时，在复制或保存成功后退出
+fn exit_if_early(app: &tauri::AppHandle, state: &AppState) {
+    if state.exit_behavior().early_exit {
+        end_session(app, state);
+    }
+}
+
+/// 结束当前会话：后台模式下重置并隐藏窗口，否则按实际输出退出
+fn end_session(app: &tauri::AppHandle, state: &AppState) {
+    if *state.daemon.lock().unwrap() {
+        reset_session(app, state);
+    } else {
+        app.exit(state.exit_code());
+    }
+}
+
+/// 后台模式：清空队列、结果、输出模式与启动样式，通知前端清空画布并隐藏窗口
+fn reset_session(app: &tauri::AppHandle, state: &AppState) {
+    *state.image_queue.lock().unwrap() = ImageQueue::default();
+    *state.outcome.lock().unwrap() = SessionOutcome::default();
+    *state.cli_output_pattern.lock().unwrap() = None;
+    *state.startup_options.lock().unwrap() = StartupOptions::default();
+    if let Err(err) = app.emit("session-reset", ()) {
+        eprintln!("通知窗口失败: {}", err);
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+/// 获取退出行为（前端据此决定关闭前是否需要导出画布）
+#[tauri::command]
+fn get_exit_behavior(state: State<AppState>) -> ExitBehavior {
+    state.exit_behavior()
+}
+
+/// 获取启动时的工具与样式（CLI --tool/--preset/--stroke-color/--stroke-width）
+#[tauri::command]
+fn get_startup_options(state: State<AppState>) -> StartupOptions {
+    state.startup_options.lock().unwrap().clone()
+}
+
+/// 按退出行为处理最终图片（复制 → 保存），全部成功后退出
+///
+/// 任一步骤失败时返回错误且不退出，避免静默丢失结果。
+#[tauri::command]
+fn finish_and_exit(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    data: Option<String>,
+    input_path: Option<String>,
+) -> Result<Option<String>, String> {
+    let behavior = state.exit_behavior();
+    let mut saved_path = None;
+
+    if behavior.copy_on_exit || behavior.save_on_exit {
+        let bytes = decode_image_data(data.as_deref().ok_or("缺少图片数据")?)?;
+        let mut errors = Vec::new();
+
+        if behavior.copy_on_exit {
+            match copy_raw_image_to_clipboard(&bytes) {
+                Ok(()) => state.record(|outcome| outcome.copied = true),
+                Err(err) => errors.push(format!("复制到剪贴板失败: {}", err)),
+            }
+        }
+
+        if behavior.save_on_exit {
+            let result = state
+                .expand_output_path(None, Some(&bytes), input_path.as_deref(), false)
+                .and_then(|path| {
+                    let encoded = state
+                        .output_format(Some(&path), None)
+                        .and_then(|format| state.export_options(format))
+                        .and_then(|options| export::transcode(&bytes, &options))?;
+                    output_pattern::ensure_parent_dir(&path)?;
+                    fs::write(&path, encoded).map_err(|e| format!("{} ({})", path.display(), e))?;
+                    Ok(path)
+                });
+            match result {
+                Ok(path) => {
+                    state.record_saved(&path);
+                    saved_path = Some(path.to_string_lossy().to_string());
+                }
+                Err(err) => errors.push(format!("保存文件失败: {}", err)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+    }
+
+    end_session(&app, &state);
+    Ok(saved_path)
+}
+
+/// 管道模式：将最终图片写入 stdout 后退出
+#[tauri::command]
+fn write_image_to_stdout(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    data: String,
+) -> Result<(), String> {
+    use std::io::Write;
+
+    if !state.is_stdout_output() {
+        return Err("未启用 stdout 输出（需要 -o -）".to_string());
+    }
+    let options = state.export_options(state.output_format(None, None)?)?;
+    let bytes = export::transcode(&decode_image_data(&data)?, &options)?;
+
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(&bytes)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| format!("写入 stdout 失败: {}", e))?;
+    state.record(|outcome| outcome.stdout = true);
+
+    app.exit(state.exit_code());
     Ok(())
 }
 
@@ -242,22 +895,19 @@ fn execute_custom_action(
         // 从 base64 数据创建临时文件
         let temp_dir = std::env::temp_dir().join("markpix");
         fs::create_dir_all(&temp_dir).map_err(|e| format!("创建临时目录失败: {}", e))?;
-        
+
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis();
         let temp_path = temp_dir.join(format!("markpix-{}.png", timestamp));
 
-        let base64_data = data
-            .split(',')
-            .nth(1)
-            .ok_or("无效的图片数据格式")?;
+        let base64_data = data.split(',').nth(1).ok_or("无效的图片数据格式")?;
         let bytes = STANDARD
             .decode(base64_data)
             .map_err(|e| format!("Base64 解码失败: {}", e))?;
         fs::write(&temp_path, &bytes).map_err(|e| format!("保存临时文件失败: {}", e))?;
-        
+
         temp_path.to_string_lossy().to_string()
     } else {
         return Err("需要提供图片路径或图片数据".to_string());
@@ -281,7 +931,10 @@ fn execute_custom_action(
     #[cfg(not(target_os = "windows"))]
     {
         // 使用 nohup 和 & 在后台运行，脱离父进程
-        let bg_command = format!("nohup sh -c '{}' >/dev/null 2>&1 &", command.replace("'", "'\"'\"'"));
+        let bg_command = format!(
+            "nohup sh -c '{}' >/dev/null 2>&1 &",
+            command.replace("'", "'\"'\"'")
+        );
         Command::new("sh")
             .args(["-c", &bg_command])
             .stdin(std::process::Stdio::null())
@@ -291,41 +944,147 @@ fn execute_custom_action(
             .map_err(|e| format!("执行命令失败: {}", e))?;
     }
 
+    state.record(|outcome| outcome.actions.push(action.name.clone()));
     Ok(format!("已启动: {}", action.name))
 }
 
+/// 配置文件重新加载后变化的配置节
+#[derive(Debug, Clone, Serialize)]
+struct ConfigChanged {
+    /// 变化的顶层配置项，如 theme、custom_actions、text_tool_config
+    sections: Vec<String>,
+    config: AppConfig,
+}
+
 /// 重新加载配置
 #[tauri::command]
-fn reload_config(state: State<AppState>) -> Result<(), String> {
-    let new_config = AppConfig::load();
-    *state.config.lock().unwrap() = new_config;
-    Ok(())
+fn reload_config(app: tauri::AppHandle) -> Result<(), String> {
+    reload_config_file(&app)
+}
+
+/// 重新读取配置文件：有效时更新配置并通知前端哪些配置节变化了，
+/// 无效时保留当前配置并记录问题
+fn reload_config_file(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    state.config_source.lock().unwrap().refresh();
+    let mut layers = state.config_layers.lock().unwrap().clone();
+    let mut diagnostics = layers.read();
+    let (new_config, invalid) = layers.resolve();
+    diagnostics.extend(invalid);
+    if let Some(diagnostic) = diagnostics.first() {
+        let message = diagnostic.to_string();
+        *state.config_diagnostics.lock().unwrap() = diagnostics;
+        return Err(message);
+    }
+    new_config.print_warnings();
+    *state.config_layers.lock().unwrap() = layers;
+    state.config_diagnostics.lock().unwrap().clear();
+
+    let sections = {
+        let mut config = state.config.lock().unwrap();
+        let sections = config.changed_sections(&new_config);
+        *config = new_config.clone();
+        sections
+    };
+    if sections.is_empty() {
+        return Ok(());
+    }
+    app.emit(
+        "config-changed",
+        ConfigChanged {
+            sections,
+            config: new_config,
+        },
+    )
+    .map_err(|e| format!("通知窗口失败: {}", e))
 }
 
-/// 获取配置文件路径
+/// 监视各层配置文件，外部编辑保存后自动重新加载
+fn start_config_watcher(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let paths = state.config_layers.lock().unwrap().file_paths();
+    let user_path = state.config_source.lock().unwrap().path.clone();
+    let mut watchers = Vec::new();
+    for path in paths {
+        let handle = app.clone();
+        let watcher = watch::watch_file(&path, move || {
+            if let Err(err) = reload_config_file(&handle) {
+                eprintln!("重新加载配置失败: {}", err);
+            }
+        });
+        match watcher {
+            Ok(watcher) => watchers.push(watcher),
+            // 系统与项目配置所在目录通常不存在，只提示用户配置文件
+            Err(err) if path == user_path => eprintln!("警告: 无法监视配置文件: {}", err),
+            Err(_) => {}
+        }
+    }
+    *state.config_watcher.lock().unwrap() = watchers;
+}
+
+/// 获取每个生效配置项的值及来源（系统、用户、项目、环境变量、命令行或默认值）
 #[tauri::command]
-fn get_config_path() -> String {
-    AppConfig::config_path().to_string_lossy().to_string()
+fn get_config_origins(state: State<AppState>) -> Vec<ConfigOrigin> {
+    let config = state.config.lock().unwrap().clone();
+    state.config_layers.lock().unwrap().origins(&config)
+}
+
+/// 获取加载配置时发现的问题（解析失败的行列、备份路径）
+#[tauri::command]
+fn get_config_diagnostics(state: State<AppState>) -> Vec<ConfigDiagnostic> {
+    state.config_diagnostics.lock().unwrap().clone()
+}
+
+/// 获取实际使用的配置文件路径
+#[tauri::command]
+fn get_config_path(state: State<AppState>) -> String {
+    state
+        .config_source
+        .lock()
+        .unwrap()
+        .path
+        .to_string_lossy()
+        .to_string()
+}
+
+/// 获取配置文件来源（路径、是否由 -c 指定、是否只读）
+#[tauri::command]
+fn get_config_source(state: State<AppState>) -> ConfigSource {
+    state.config_source.lock().unwrap().clone()
 }
 
 /// 直接从 base64 数据复制图片到剪贴板（更快，无需临时文件）
 #[tauri::command]
-fn copy_image_data_to_clipboard(data: String) -> Result<(), String> {
-    use base64::{Engine as _, engine::general_purpose::STANDARD};
-    
+fn copy_image_data_to_clipboard(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    data: String,
+) -> Result<(), String> {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
     // 解码 base64 数据
-    let image_data = STANDARD.decode(&data)
+    let image_data = STANDARD
+        .decode(&data)
         .map_err(|e| format!("解码图片数据失败: {}", e))?;
-    
-    copy_raw_image_to_clipboard(&image_data)
+
+    copy_raw_image_to_clipboard(&image_data)?;
+    state.record(|outcome| outcome.copied = true);
+    exit_if_early(&app, &state);
+    Ok(())
 }
 
 /// 复制图片到剪贴板（Wayland 使用 wl-copy）
 #[tauri::command]
-fn copy_image_to_clipboard(path: String) -> Result<(), String> {
-    let image_data = std::fs::read(&path)
-        .map_err(|e| format!("读取图片文件失败: {}", e))?;
-    copy_raw_image_to_clipboard(&image_data)
+fn copy_image_to_clipboard(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    path: String,
+) -> Result<(), String> {
+    let image_data = std::fs::read(&path).map_err(|e| format!("读取图片文件失败: {}", e))?;
+    copy_raw_image_to_clipboard(&image_data)?;
+    state.record(|outcome| outcome.copied = true);
+    exit_if_early(&app, &state);
+    Ok(())
 }
 
 /// 内部函数：将原始图片数据复制到剪贴板
@@ -333,7 +1092,7 @@ fn copy_raw_image_to_clipboard(image_data: &[u8]) -> Result<(), String> {
     #[cfg(target_os = "linux")]
     {
         use std::io::Write;
-        
+
         // 优先尝试 wl-copy (Wayland)
         let wl_result = Command::new("wl-copy")
             .args(["--type", "image/png"])
@@ -345,26 +1104,33 @@ fn copy_raw_image_to_clipboard(image_data: &[u8]) -> Result<(), String> {
                 }
                 child.wait()
             });
-        
+
         if wl_result.is_ok() {
             return Ok(());
         }
-        
+
         // 回退到 xclip (X11) - 需要通过 stdin 传递数据
         let mut child = Command::new("xclip")
             .args(["-selection", "clipboard", "-t", "image/png"])
             .stdin(std::process::Stdio::piped())
             .spawn()
-            .map_err(|e| format!("执行剪贴板命令失败: {}。请确保已安装 wl-copy (wl-clipboard) 或 xclip", e))?;
-        
+            .map_err(|e| {
+                format!(
+                    "执行剪贴板命令失败: {}。请确保已安装 wl-copy (wl-clipboard) 或 xclip",
+                    e
+                )
+            })?;
+
         if let Some(stdin) = child.stdin.as_mut() {
-            stdin.write_all(image_data)
+            stdin
+                .write_all(image_data)
                 .map_err(|e| format!("写入剪贴板数据失败: {}", e))?;
         }
-        
-        let status = child.wait()
+
+        let status = child
+            .wait()
             .map_err(|e| format!("等待剪贴板命令完成失败: {}", e))?;
-        
+
         if !status.success() {
             return Err("剪贴板命令执行失败".to_string());
         }
@@ -373,9 +1139,8 @@ fn copy_raw_image_to_clipboard(image_data: &[u8]) -> Result<(), String> {
     {
         // macOS: 保存到临时文件后使用 osascript 复制
         let temp_path = std::env::temp_dir().join("markpix_clipboard.png");
-        std::fs::write(&temp_path, image_data)
-            .map_err(|e| format!("保存临时文件失败: {}", e))?;
-        
+        std::fs::write(&temp_path, image_data).map_err(|e| format!("保存临时文件失败: {}", e))?;
+
         let script = format!(
             "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
             temp_path.display()
@@ -389,10 +1154,12 @@ fn copy_raw_image_to_clipboard(image_data: &[u8]) -> Result<(), String> {
     {
         // Windows: 需要保存到临时文件
         let temp_path = std::env::temp_dir().join("markpix_clipboard.png");
-        std::fs::write(&temp_path, image_data)
-            .map_err(|e| format!("保存临时文件失败: {}", e))?;
+        std::fs::write(&temp_path, image_data).map_err(|e| format!("保存临时文件失败: {}", e))?;
         Command::new("powershell")
-            .args(["-Command", &format!("Set-Clipboard -Path '{}'", temp_path.display())])
+            .args([
+                "-Command",
+                &format!("Set-Clipboard -Path '{}'", temp_path.display()),
+            ])
             .output()
             .map_err(|e| format!("复制到剪贴板失败: {}", e))?;
     }
@@ -427,10 +1194,44 @@ fn open_directory(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn save_config(app_state: State<AppState>, config: AppConfig) -> Result<(), String> {
+fn save_config(app_state: State<AppState>, config: serde_json::Value) -> Result<(), String> {
     let mut state_config = app_state.config.lock().map_err(|e| e.to_string())?;
-    *state_config = config;
-    state_config.save()
+    let merged = state_config.merged_with(config)?;
+    merged.validate()?;
+    persist_config(&app_state, &mut state_config, merged)
+}
+
+/// 保存修改后的配置：写入用户配置文件并按各层重新合并，写入失败时修改仍在本次运行中生效
+fn persist_config(
+    app_state: &AppState,
+    state_config: &mut AppConfig,
+    merged: AppConfig,
+) -> Result<(), String> {
+    let source = app_state.config_source.lock().unwrap().clone();
+    // 用户配置文件有错误时本次没有使用它，写入会覆盖用户的配置
+    let user_file_broken = app_state
+        .config_diagnostics
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|diagnostic| Path::new(&diagnostic.path) == source.path);
+    if user_file_broken {
+        *state_config = merged;
+        return Err("配置文件有错误，修复并重新加载前不会写入".to_string());
+    }
+
+    // 只把变化的配置项写入用户配置文件，再按各层重新合并（项目、环境变量、命令行仍然优先）
+    let mut layers = app_state.config_layers.lock().unwrap();
+    if let Err(err) = layers.save_user(state_config, &merged, &source) {
+        *state_config = merged;
+        return Err(err);
+    }
+    let (effective, diagnostics) = layers.resolve();
+    *state_config = effective;
+    if !diagnostics.is_empty() {
+        *app_state.config_diagnostics.lock().unwrap() = diagnostics;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -438,6 +1239,99 @@ fn get_config(app_state: State<AppState>) -> Result<AppConfig, String> {
     Ok(app_state.config.lock().map_err(|e| e.to_string())?.clone())
 }
 
+/// 获取生效的快捷键（默认快捷键与配置 keybindings 合并后）
+#[tauri::command]
+fn get_keybindings(app_state: State<AppState>) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let (keybindings, _) = app_state.config.lock().unwrap().resolved_keybindings()?;
+    Ok(keybindings)
+}
+
+/// 列出内置预设与配置文件中的预设
+#[tauri::command]
+fn list_presets(app_state: State<AppState>) -> Vec<PresetInfo> {
+    presets::list_presets(&app_state.config.lock().unwrap().presets)
+}
+
+/// 按名称查找预设，返回其工具与样式（由前端套用）
+#[tauri::command]
+fn apply_preset(app_state: State<AppState>, name: String) -> Result<StylePreset, String> {
+    presets::find_preset(&name, &app_state.config.lock().unwrap().presets)
+}
+
+/// 把当前工具与样式保存为预设（同名预设会被覆盖）
+#[tauri::command]
+fn save_current_as_preset(
+    app_state: State<AppState>,
+    name: String,
+    preset: StylePreset,
+) -> Result<(), String> {
+    let name = name.trim().to_string();
+    presets::validate_preset(&name, &preset)?;
+    let mut state_config = app_state.config.lock().map_err(|e| e.to_string())?;
+    let mut merged = state_config.clone();
+    merged.presets.insert(name, preset);
+    persist_config(&app_state, &mut state_config, merged)
+}
+
+/// 删除配置文件中的预设（内置预设不能删除）
+#[tauri::command]
+fn delete_preset(app_state: State<AppState>, name: String) -> Result<(), String> {
+    let mut state_config = app_state.config.lock().map_err(|e| e.to_string())?;
+    if !state_config.presets.contains_key(&name) {
+        return Err(format!("配置文件中没有预设: {}", name));
+    }
+    let mut merged = state_config.clone();
+    merged.presets.remove(&name);
+    persist_config(&app_state, &mut state_config, merged)?;
+    // 系统或项目配置中的预设无法从用户配置文件中删除
+    if state_config.presets.contains_key(&name) {
+        return Err(format!("预设 {} 定义在系统或项目配置中，无法删除", name));
+    }
+    Ok(())
+}
+
+/// 从另一个配置文件导入预设与调色板，返回导入的配置项（如 presets.redaction）
+#[tauri::command]
+fn import_presets(app_state: State<AppState>, path: String) -> Result<Vec<String>, String> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(format!("文件不存在: {}", path.display()));
+    }
+    let other = AppConfig::read_from(&path)?;
+    let mut state_config = app_state.config.lock().map_err(|e| e.to_string())?;
+    let (merged, keys) = state_config.with_imported_presets(&other);
+    if keys.is_empty() {
+        return Err(format!("{} 中没有预设或调色板", path.display()));
+    }
+    persist_config(&app_state, &mut state_config, merged)?;
+    Ok(keys.into_iter().map(|key| key.join(".")).collect())
+}
+
+/// 获取单个工具的默认样式
+#[tauri::command]
+fn get_tool_defaults(
+    app_state: State<AppState>,
+    tool: tools::ToolType,
+) -> Result<serde_json::Value, String> {
+    app_state.config.lock().unwrap().tool_defaults.get(tool)
+}
+
+/// 更新单个工具的默认样式（只需传入变化的字段），返回该工具更新后的默认样式
+#[tauri::command]
+fn update_tool_defaults(
+    app_state: State<AppState>,
+    tool: tools::ToolType,
+    defaults: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let mut state_config = app_state.config.lock().map_err(|e| e.to_string())?;
+    let merged = AppConfig {
+        tool_defaults: state_config.tool_defaults.with_tool(tool, defaults)?,
+        ..state_config.clone()
+    };
+    persist_config(&app_state, &mut state_config, merged)?;
+    state_config.tool_defaults.get(tool)
+}
+
 /// 获取系统字体列表
 #[tauri::command]
 fn list_system_fonts() -> Vec<String> {
@@ -474,7 +1368,7 @@ fn normalize_fonts(fonts: Vec<String>) -> Vec<String> {
         }
     }
 
-    result.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    result.sort_by_key(|a| a.to_lowercase());
     result
 }
 
@@ -588,10 +1482,10 @@ fn fetch_system_fonts() -> Result<Vec<String>, String> {
     Ok(vec![])
 }
 
-/// 退出应用程序
+/// 退出应用程序（未保存或复制任何内容视为取消）
 #[tauri::command]
-fn exit_app(app: tauri::AppHandle) {
-    app.exit(0);
+fn exit_app(app: tauri::AppHandle, state: State<AppState>) {
+    end_session(&app, &state);
 }
 
 /// 打开开发者工具
@@ -611,38 +1505,261 @@ fn open_devtools(webview: tauri::WebviewWindow) {
     }
 }
 
+/// 显示并激活主窗口（有新图片送达时调用）
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// 转交给前端的后续实例请求
+#[derive(Debug, Clone, Serialize)]
+struct InstanceOpen {
+    /// 要切换到的队列位置，未传入图片时为 None
+    index: Option<usize>,
+    output_pattern: Option<String>,
+    startup: StartupOptions,
+}
+
+/// 打开后续实例转交或托盘菜单选择的图片：加入队列，更新输出模式与启动样式，并激活窗口
+fn open_request(app: &tauri::AppHandle, request: InstanceRequest) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let presets = state.config.lock().unwrap().presets.clone();
+    let startup = request.startup.resolve(&presets)?;
+    if let Some(pattern) = request.output_pattern.as_deref() {
+        output_pattern::validate_pattern(pattern)?;
+    }
+    // -o 只对本次请求生效，未指定时恢复使用配置中的模式
+    *state.cli_output_pattern.lock().unwrap() = request.output_pattern.clone();
+    *state.startup_options.lock().unwrap() = startup.clone();
+    let recent = state.record_recent(&request.images);
+    if *state.daemon.lock().unwrap() {
+        tray::refresh(app, &recent);
+    }
+    let index = state.image_queue.lock().unwrap().add(request.images);
+
+    show_main_window(app);
+    app.emit(
+        "instance-open",
+        InstanceOpen {
+            index,
+            output_pattern: request.output_pattern,
+            startup,
+        },
+    )
+    .map_err(|e| format!("通知窗口失败: {}", e))
+}
+
+/// 打开 deep link 传入的图片，链接无效时只打印错误
+fn open_deep_link(app: &tauri::AppHandle, url: &str) {
+    let result = input::parse_uri(url)
+        .and_then(|parsed| match parsed {
+            input::UriInput::Path(path) => Ok(path),
+            input::UriInput::Data(data) => input::save_temp_image(&data, "data-uri", "data: URI"),
+        })
+        .map_err(|err| err.to_string())
+        .and_then(|path| {
+            open_request(
+                app,
+                InstanceRequest {
+                    images: vec![path.to_string_lossy().to_string()],
+                    ..InstanceRequest::default()
+                },
+            )
+        });
+    if let Err(err) = result {
+        eprintln!("打开链接失败: {}", err);
+    }
+}
+
+/// 监视目录中出现的新图片
+#[derive(Debug, Clone, Serialize)]
+struct FolderImage {
+    /// 图片在队列中的位置
+    index: usize,
+    path: String,
+}
+
+/// 监视 CLI 与配置中的目录，新图片写入完成后加入队列并通知前端切换
+fn start_folder_watcher(app: &tauri::AppHandle, cli_dirs: Vec<PathBuf>) {
+    let config_dirs = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .unwrap()
+        .watch_dirs
+        .clone();
+    let mut dirs = cli_dirs;
+    for dir in config_dirs {
+        match watch::resolve_dir(&dir) {
+            Ok(dir) if !dirs.contains(&dir) => dirs.push(dir),
+            Ok(_) => {}
+            Err(err) => eprintln!("警告: watch_dirs {}", err),
+        }
+    }
+    if dirs.is_empty() {
+        return;
+    }
+
+    let ignore_handle = app.clone();
+    let open_handle = app.clone();
+    let watcher = watch::watch(
+        &dirs,
+        move |path| {
+            ignore_handle
+                .state::<AppState>()
+                .own_saves
+                .lock()
+                .unwrap()
+                .contains(path)
+        },
+        move |path| {
+            let path = path.to_string_lossy().to_string();
+            let state = open_handle.state::<AppState>();
+            let Some(index) = state.image_queue.lock().unwrap().add(vec![path.clone()]) else {
+                return;
+            };
+            show_main_window(&open_handle);
+            if let Err(err) = open_handle.emit("folder-image", FolderImage { index, path }) {
+                eprintln!("通知窗口失败: {}", err);
+            }
+        },
+    );
+    match watcher {
+        Ok(watcher) => *app.state::<AppState>().folder_watcher.lock().unwrap() = Some(watcher),
+        Err(err) => eprintln!("警告: {}", err),
+    }
+}
+
+/// 命令行参数中与配置项对应的部分（-o、--quality、--early-exit 等），作为最高层参与合并
+fn cli_config_layer(
+    output_pattern: Option<&str>,
+    quality: Option<u8>,
+    exit_behavior: ExitBehavior,
+) -> toml::Table {
+    let mut table = toml::Table::new();
+    if let Some(pattern) = output_pattern.filter(|pattern| *pattern != "-") {
+        table.insert("output_pattern".into(), pattern.into());
+    }
+    if let Some(quality) = quality {
+        let mut export = toml::Table::new();
+        export.insert("quality".into(), i64::from(quality).into());
+        table.insert("export".into(), export.into());
+    }
+    for (key, enabled) in [
+        ("early_exit", exit_behavior.early_exit),
+        ("copy_on_exit", exit_behavior.copy_on_exit),
+        ("save_on_exit", exit_behavior.save_on_exit),
+    ] {
+        if enabled {
+            table.insert(key.into(), true.into());
+        }
+    }
+    table
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    run_with_args(None, None, None, false)
+    run_with_args(LaunchOptions::default())
 }
 
 /// 带参数运行（供 main.rs 调用）
-pub fn run_with_args(
-    initial_image: Option<String>,
-    config_path: Option<String>,
-    output_pattern: Option<String>,
-    fullscreen: bool,
-) {
-    // 加载配置（优先使用 CLI 指定的配置文件）
-    let config = if let Some(ref path) = config_path {
-        AppConfig::load_from(path)
-    } else {
-        AppConfig::load()
-    };
+pub fn run_with_args(options: LaunchOptions) {
+    let LaunchOptions {
+        images,
+        config_path,
+        output_pattern,
+        fullscreen,
+        exit_behavior,
+        output_format,
+        quality,
+        print_result,
+        startup,
+        new_instance,
+        watch_dirs,
+        daemon,
+    } = options;
+
+    // 加载配置：系统 → 用户（CLI 可指定文件）→ 第一张图片目录下的 .markpix.toml → 环境变量 → 命令行
+    let config_source = ConfigSource::new(config_path.as_deref());
+    let project_dir = images.first().map(|image| {
+        Path::new(image)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf()
+    });
+    let cli_config = cli_config_layer(output_pattern.as_deref(), quality, exit_behavior);
+    let (config_layers, config, config_diagnostics) =
+        ConfigLayers::load(&config_source, project_dir, cli_config);
+    if config_source.read_only {
+        eprintln!(
+            "提示: 配置文件为只读，设置修改不会保存: {}",
+            config_source.path.display()
+        );
+    }
+    let startup = startup.resolve(&config.presets).unwrap_or_else(|err| {
+        eprintln!("错误: {}", err);
+        ExitStatus::ConfigError.exit();
+    });
+
+    // 已有后台进程时总是转交；单实例模式（配置 single_instance）下已有窗口时也转交，转交后直接退出
+    // 管道模式与 --print-result 的结果属于当前进程，始终打开新窗口
+    // 后台模式总是监听请求，自身不再转交
+    let forwardable =
+        !new_instance && output_pattern.as_deref() != Some("-") && print_result.is_none();
+    let single_instance = daemon || (config.single_instance && forwardable);
+    if forwardable && !daemon {
+        let request = InstanceRequest {
+            images: images.clone(),
+            output_pattern: output_pattern.clone(),
+            startup: startup.clone(),
+        };
+        let targets = std::iter::once(InstanceKind::Daemon)
+            .chain(config.single_instance.then_some(InstanceKind::Window));
+        for kind in targets {
+            match instance::forward(kind, &request) {
+                Ok(true) => ExitStatus::Forwarded.exit(),
+                Ok(false) => {}
+                Err(err) => {
+                    eprintln!("警告: {}，将打开新窗口", err);
+                    break;
+                }
+            }
+        }
+    }
 
+    let show_window = !daemon || !images.is_empty();
     let app_state = AppState {
-        initial_image_path: Mutex::new(initial_image),
+        image_queue: Mutex::new(ImageQueue::new(images.clone())),
         config: Mutex::new(config),
-        cli_config_path: Mutex::new(config_path),
+        config_source: Mutex::new(config_source),
         cli_output_pattern: Mutex::new(output_pattern),
+        outcome: Mutex::new(SessionOutcome::default()),
+        print_result: Mutex::new(print_result),
+        cli_output_format: Mutex::new(output_format),
+        cli_quality: Mutex::new(quality),
+        startup_options: Mutex::new(startup),
+        single_instance: Mutex::new(false),
+        own_saves: Mutex::new(HashSet::new()),
+        folder_watcher: Mutex::new(None),
+        daemon: Mutex::new(daemon),
+        recent_images: Mutex::new(Vec::new()),
+        config_layers: Mutex::new(config_layers),
+        config_diagnostics: Mutex::new(config_diagnostics),
+        config_watcher: Mutex::new(Vec::new()),
     };
+    let recent = app_state.record_recent(&images);
 
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_deep_link::init())
         .setup(move |app| {
             if let Some(window) = app.get_webview_window("main") {
                 if fullscreen {
@@ -650,30 +1767,119 @@ pub fn run_with_args(
                         eprintln!("设置最大化模式失败: {}", err);
                     }
                 }
+                // 窗口默认隐藏，后台模式在收到图片前保持隐藏
+                if show_window {
+                    let _ = window.show();
+                }
             } else {
                 eprintln!("警告: 未找到主窗口");
             }
+            start_folder_watcher(app.handle(), watch_dirs);
+            start_config_watcher(app.handle());
+            // 运行中收到的 markpix:// 链接（Linux 上链接作为命令行参数传入，由单实例模式转交）
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    open_deep_link(&handle, url.as_str());
+                }
+            });
+            if single_instance {
+                let handle = app.handle().clone();
+                let kind = InstanceKind::new(daemon);
+                match instance::listen(kind, move |request| open_request(&handle, request)) {
+                    Ok(()) => *app.state::<AppState>().single_instance.lock().unwrap() = true,
+                    // 后台模式无法接收请求时没有意义，直接报错退出
+                    Err(err) if daemon => return Err(format!("后台模式启动失败: {}", err).into()),
+                    Err(err) => eprintln!("警告: 单实例模式启动失败: {}", err),
+                }
+            }
+            if daemon {
+                tray::create(app.handle())?;
+                tray::refresh(app.handle(), &recent);
+            }
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // 后台模式：关闭窗口只结束当前会话
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                let state = window.state::<AppState>();
+                if *state.daemon.lock().unwrap() {
+                    api.prevent_close();
+                    reset_session(window.app_handle(), &state);
+                }
+            }
+        })
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             get_initial_image,
+            list_queue,
+            next_image,
+            previous_image,
+            select_image,
             read_image_file,
             save_image_file,
             get_custom_actions,
             get_cli_output_pattern,
             execute_custom_action,
             reload_config,
+            get_config_diagnostics,
             get_config_path,
+            get_config_source,
+            get_config_origins,
             copy_image_to_clipboard,
             copy_image_data_to_clipboard,
+            write_image_to_stdout,
+            get_exit_behavior,
+            get_startup_options,
+            expand_output_pattern,
+            finish_and_exit,
             open_directory,
             exit_app,
             save_config,
             get_config,
+            get_tool_defaults,
+            update_tool_defaults,
+            get_keybindings,
+            list_presets,
+            apply_preset,
+            save_current_as_preset,
+            delete_preset,
+            import_presets,
             list_system_fonts,
             open_devtools,
         ])
-        .run(tauri::generate_context!())
-        .expect("启动 Tauri 应用时发生错误");
+        .build(tauri::generate_context!());
+
+    let app = match app {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("启动 Tauri 应用时发生错误: {}", err);
+            ExitStatus::Failure.exit();
+        }
+    };
+    app.run(|app, event| match event {
+        // 直接关闭窗口时同样按实际输出决定退出码
+        tauri::RunEvent::ExitRequested {
+            code: None, api, ..
+        } => {
+            let code = app.state::<AppState>().exit_code();
+            if code != 0 {
+                api.prevent_exit();
+                app.exit(code);
+            }
+        }
+        tauri::RunEvent::Exit => {
+            let state = app.state::<AppState>();
+            if *state.single_instance.lock().unwrap() {
+                instance::remove_socket(InstanceKind::new(*state.daemon.lock().unwrap()));
+            }
+            if !*state.daemon.lock().unwrap()
+                && state.outcome.lock().unwrap().status() == ExitStatus::Cancelled
+            {
+                eprintln!("已取消: 未保存或复制图片");
+            }
+            state.print_result();
+        }
+        _ => {}
+    });
 }
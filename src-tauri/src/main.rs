@@ -4,64 +4,610 @@
 // 在 Windows Release 模式下隐藏控制台窗口
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand};
+use markpix_lib::annotation::AnnotationDocument;
+use markpix_lib::capture::{self, CaptureError, CaptureMode};
+use markpix_lib::clipboard::{self, ClipboardError};
+use markpix_lib::config_layers::{self, ConfigLayers};
+use markpix_lib::export::{ExportOptions, OutputFormat};
+use markpix_lib::outcome::{ExitStatus, ResultFormat};
+use markpix_lib::presets::{self, StartupOptions, StylePreset};
+use markpix_lib::tools::ToolType;
+use markpix_lib::{batch, color, input, output_pattern, AppConfig};
 use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
 /// MarkPix - 图片标注工具
 #[derive(Parser, Debug)]
 #[command(name = "markpix")]
 #[command(author = "snemc")]
-#[command(version = "0.5.4")]
+#[command(version)]
 #[command(about = "一个现代化的图片标注工具", long_about = None)]
+#[command(after_long_help = help_footer())]
 struct Args {
-    /// 要打开的图片文件路径（位置参数）
+    /// 要打开的图片文件路径、通配符或 URI（file://、data:、markpix://open?path=），多张图片按顺序组成队列
     #[arg(value_name = "IMAGE")]
-    image_path: Option<PathBuf>,
+    images: Vec<String>,
 
     /// 配置文件路径，默认读取 ~/.config/markpix/config.toml
-    #[arg(short = 'c', long = "config")]
+    #[arg(short = 'c', long = "config", global = true)]
     config: Option<PathBuf>,
 
-    /// 输入图片路径，使用 '-' 从 stdin 读取
+    /// 输入图片路径或 URI，使用 '-' 从 stdin 读取
     #[arg(short = 'f', long = "filename")]
     filename: Option<String>,
 
+    /// 启动时打开剪贴板中的图片（Linux，需要 wl-paste 或 xclip）
+    #[arg(long = "from-clipboard", conflicts_with_all = ["images", "filename"])]
+    from_clipboard: bool,
+
     /// 输出文件名模式，使用 '-' 输出到 stdout
-    /// 支持占位符: {input_file_base}, {YYYY_MM_DD-hh-mm-ss}
+    /// 支持的占位符见 --help 末尾说明
     #[arg(short = 'o', long = "output-filename")]
     output_filename: Option<String>,
 
     /// 启动时最大化窗口
     #[arg(long = "fullscreen")]
     fullscreen: bool,
+
+    /// 复制或保存成功后立即退出
+    #[arg(long = "early-exit")]
+    early_exit: bool,
+
+    /// 退出时自动将结果复制到剪贴板
+    #[arg(long = "copy-on-exit")]
+    copy_on_exit: bool,
+
+    /// 退出时按输出文件名模式自动保存结果
+    #[arg(long = "save-on-exit")]
+    save_on_exit: bool,
+
+    /// 输出格式，默认按输出文件扩展名决定
+    #[arg(long = "output-format", value_enum, global = true)]
+    output_format: Option<OutputFormat>,
+
+    /// JPEG/WebP/AVIF 编码质量（1-100），默认读取配置 export.quality
+    #[arg(long = "quality", value_parser = clap::value_parser!(u8).range(1..=100), global = true)]
+    quality: Option<u8>,
+
+    /// 退出时打印结果（保存路径、是否复制、执行的动作），管道模式下输出到 stderr
+    #[arg(long = "print-result", value_enum, value_name = "FORMAT")]
+    print_result: Option<ResultFormat>,
+
+    /// 监视目录，出现新图片（写入完成后）时自动打开，可多次指定；与配置 watch_dirs 合并
+    #[arg(long = "watch", value_name = "DIR")]
+    watch: Vec<String>,
+
+    /// 不转交给已运行的窗口或后台进程，总是打开新窗口
+    #[arg(long = "new-instance")]
+    new_instance: bool,
+
+    /// 后台模式：隐藏启动并显示托盘图标，关闭窗口时重置会话而不退出
+    #[arg(long = "daemon", conflicts_with_all = ["print_result", "new_instance"])]
+    daemon: bool,
+
+    /// 启动时选中的工具
+    #[arg(long = "tool", value_enum)]
+    tool: Option<ToolType>,
+
+    /// 启动时套用的样式预设（内置 redact, highlight, callout 或配置文件 presets 中的名称）
+    #[arg(long = "preset", value_name = "NAME")]
+    preset: Option<String>,
+
+    /// 启动时的描边颜色（CSS 颜色，如 '#ef4444'）
+    #[arg(long = "stroke-color", value_name = "COLOR", value_parser = parse_color_arg)]
+    stroke_color: Option<String>,
+
+    /// 启动时的描边粗细（1-20）
+    #[arg(long = "stroke-width", value_name = "WIDTH", value_parser = clap::value_parser!(u32).range(1..=20))]
+    stroke_width: Option<u32>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// 子命令（除 capture 外均不打开窗口）
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// 截图并在编辑器中打开（命令模板见配置 [capture]，按 WAYLAND_DISPLAY/DISPLAY 选择）
+    Capture {
+        /// 框选区域截图（默认）
+        #[arg(long = "region", group = "capture_mode")]
+        region: bool,
+
+        /// 截取全部屏幕
+        #[arg(long = "full", group = "capture_mode")]
+        full: bool,
+
+        /// 截取指定显示器（如 DP-1，需要配置 capture.<会话>.output）
+        #[arg(long = "output", value_name = "NAME", group = "capture_mode")]
+        output: Option<String>,
+    },
+
+    /// 将标注文档渲染到图片上并输出（无界面）
+    Render {
+        /// 标注文档（JSON，包含 annotations 和可选的 cropMask）
+        #[arg(short = 'a', long = "annotations", value_name = "FILE")]
+        annotations: PathBuf,
+
+        /// 输入图片路径
+        #[arg(short = 'i', long = "input", value_name = "FILE")]
+        input: PathBuf,
+
+        /// 输出图片路径，格式由扩展名或 --output-format 决定
+        #[arg(short = 'o', long = "output", value_name = "FILE")]
+        output: PathBuf,
+    },
+
+    /// 将同一份标注模板批量应用到多张图片（并行渲染）
+    Batch {
+        /// 标注模板（JSON，与 render 子命令格式相同）
+        #[arg(short = 'a', long = "annotations", value_name = "FILE")]
+        annotations: PathBuf,
+
+        /// 输出文件名模式，按每个输入文件展开，默认读取配置 output_pattern
+        #[arg(short = 'o', long = "output-filename", value_name = "PATTERN")]
+        output_pattern: Option<String>,
+
+        /// 并行任务数，默认使用全部 CPU 核心
+        #[arg(short = 'j', long = "jobs")]
+        jobs: Option<usize>,
+
+        /// 输入图片路径或通配符（如 'shots/*.png'）
+        #[arg(value_name = "IMAGES", required = true)]
+        inputs: Vec<String>,
+    },
+
+    /// 生成 shell 补全脚本并输出到 stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// 生成 man 手册页（roff 格式）并输出到 stdout
+    Manpage,
+
+    /// 查看或修改配置文件
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+/// config 子命令的操作
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// 打印用户配置文件路径
+    Path,
+
+    /// 打印当前生效的配置（系统、用户、项目配置与 MARKPIX_* 环境变量合并后）
+    Show {
+        /// 输出格式
+        #[arg(long = "format", value_enum, default_value_t = ConfigFormat::Toml)]
+        format: ConfigFormat,
+
+        /// 同时合并该目录下的 .markpix.toml
+        #[arg(long = "project", value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+
+    /// 读取单个生效的配置项，键以点分隔（如 export.quality）
+    Get {
+        key: String,
+
+        /// 同时合并该目录下的 .markpix.toml
+        #[arg(long = "project", value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+
+    /// 列出每个生效配置项的值及来源（默认值、系统、用户、项目配置或环境变量）
+    Sources {
+        /// 同时合并该目录下的 .markpix.toml
+        #[arg(long = "project", value_name = "DIR")]
+        project: Option<PathBuf>,
+    },
+
+    /// 修改单个配置项并写回用户配置文件（只写入该项，其余内容保持不变）
+    /// 字符串字段直接使用原值，其余字段按 JSON 解析（如 true、80、'[{"name":"..","command":".."}]'）
+    Set { key: String, value: String },
+
+    /// 列出可用的样式预设（内置与配置文件中的预设）及调色板
+    Presets,
+
+    /// 从另一个配置文件导入预设与调色板到用户配置文件（同名的覆盖）
+    ImportPresets { file: PathBuf },
+
+    /// 检查配置文件是否有效
+    Validate { file: PathBuf },
+
+    /// 打印带示例的默认配置
+    Default,
+}
+
+/// 配置输出格式
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum ConfigFormat {
+    Toml,
+    Json,
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    // 除 capture 外的子命令不启动图形界面
+    let mut captured = None;
+    if let Some(command) = args.command.take() {
+        let config_path = args.config.as_deref().and_then(|p| p.to_str());
+        if let Commands::Config { action } = command {
+            exit_on_error(
+                run_config_command(action, config_path),
+                ExitStatus::ConfigError,
+            );
+            return;
+        }
+        exit_on_error(check_config_path(config_path), ExitStatus::ConfigError);
+        let config = AppConfig::load_with(config_path);
+        match command {
+            Commands::Capture { full, output, .. } => {
+                let mode = match output {
+                    Some(name) => CaptureMode::Output(name),
+                    None if full => CaptureMode::Full,
+                    None => CaptureMode::Region,
+                };
+                captured = Some(capture_screen(&config, &mode));
+            }
+            command => {
+                run_command(command, &config, args.output_format, args.quality);
+                return;
+            }
+        }
+    }
 
     // 处理输入图片路径
-    // 优先级: -f/--filename > 位置参数
-    let initial_image = if let Some(ref filename) = args.filename {
+    // 优先级: capture > --from-clipboard > -f/--filename > 位置参数
+    let images = if let Some(path) = captured {
+        vec![path]
+    } else if args.from_clipboard {
+        read_image_from_clipboard().into_iter().collect()
+    } else if let Some(ref filename) = args.filename {
         if filename == "-" {
             // 从 stdin 读取图片数据
-            read_image_from_stdin()
+            read_image_from_stdin().into_iter().collect()
+        } else if input::is_uri(filename) {
+            resolve_uri(filename).into_iter().collect()
         } else {
-            resolve_path(filename)
+            resolve_path(filename).into_iter().collect()
         }
     } else {
-        args.image_path.and_then(|path| {
-            path.to_str().and_then(|s| resolve_path(s))
-        })
+        resolve_image_queue(&args.images)
     };
+    // 指定了图片却一张都无法打开时直接失败，不打开空白编辑器
+    if images.is_empty() && (args.filename.is_some() || !args.images.is_empty()) {
+        eprintln!("错误: 没有可打开的图片");
+        ExitStatus::LoadError.exit();
+    }
 
     // 处理配置文件路径
     let config_path = args.config.and_then(|p| p.to_str().map(|s| s.to_string()));
+    exit_on_error(
+        check_config_path(config_path.as_deref()),
+        ExitStatus::ConfigError,
+    );
 
-    // 处理输出文件名模式
+    // 处理输出文件名模式（'-' 表示输出到 stdout）
     let output_pattern = args.output_filename;
+    if let Some(pattern) = output_pattern.as_deref().filter(|p| *p != "-") {
+        exit_on_error(
+            output_pattern::validate_pattern(pattern),
+            ExitStatus::ConfigError,
+        );
+    }
+    if args.daemon && output_pattern.as_deref() == Some("-") {
+        eprintln!("错误: 后台模式不支持输出到 stdout（-o -）");
+        ExitStatus::ConfigError.exit();
+    }
+
+    // 监视目录必须存在
+    let watch_dirs = args
+        .watch
+        .iter()
+        .map(|dir| markpix_lib::watch::resolve_dir(dir))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|err| {
+            eprintln!("错误: {}", err);
+            ExitStatus::ConfigError.exit();
+        });
+
+    // 退出行为（作为命令行层覆盖配置文件中的同名选项）
+    let exit_behavior = markpix_lib::ExitBehavior {
+        early_exit: args.early_exit,
+        copy_on_exit: args.copy_on_exit,
+        save_on_exit: args.save_on_exit,
+    };
+
+    markpix_lib::run_with_args(markpix_lib::LaunchOptions {
+        images,
+        config_path,
+        output_pattern,
+        fullscreen: args.fullscreen,
+        exit_behavior,
+        output_format: args.output_format,
+        quality: args.quality,
+        print_result: args.print_result,
+        startup: StartupOptions {
+            preset: args.preset,
+            style: StylePreset {
+                tool: args.tool,
+                stroke_color: args.stroke_color,
+                stroke_width: args.stroke_width,
+                ..Default::default()
+            },
+        },
+        new_instance: args.new_instance,
+        watch_dirs,
+        daemon: args.daemon,
+    })
+}
+
+/// 校验 --stroke-color 是否为可识别的颜色
+fn parse_color_arg(value: &str) -> Result<String, String> {
+    color::parse_css_color(value).map(|_| value.to_string())
+}
+
+/// 执行无界面子命令，失败时以非零状态退出
+fn run_command(
+    command: Commands,
+    config: &AppConfig,
+    output_format: Option<OutputFormat>,
+    quality: Option<u8>,
+) {
+    let result = match command {
+        Commands::Render {
+            annotations,
+            input,
+            output,
+        } => output_format
+            .map_or_else(|| OutputFormat::from_path(&output), Ok)
+            .and_then(|format| ExportOptions::new(format, &config.export, quality))
+            .and_then(|options| {
+                markpix_lib::render::render_file(&input, &annotations, &output, &options)
+            }),
+        Commands::Batch {
+            annotations,
+            output_pattern,
+            jobs,
+            inputs,
+        } => run_batch(
+            config,
+            &annotations,
+            output_pattern,
+            jobs,
+            &inputs,
+            output_format,
+            quality,
+        ),
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Args::command(), "markpix", &mut io::stdout());
+            Ok(())
+        }
+        Commands::Manpage => clap_mangen::Man::new(Args::command())
+            .render(&mut io::stdout())
+            .map_err(|e| format!("生成手册页失败: {}", e)),
+        Commands::Config { .. } | Commands::Capture { .. } => {
+            unreachable!("config 与 capture 子命令单独处理")
+        }
+    };
+
+    exit_on_error(result, ExitStatus::Failure);
+}
+
+/// 详细帮助与手册页末尾的补充说明：输出文件名占位符与配置文件位置
+fn help_footer() -> String {
+    let mut footer =
+        String::from("输出文件名占位符（-o/--output-filename 与配置项 output_pattern）:\n");
+    for (name, description) in output_pattern::PLACEHOLDERS {
+        footer.push_str(&format!("  {:<24}{}\n", name, description));
+    }
+    footer.push_str(
+        "\n配置文件（可用 -c/--config 指定其他路径）:\n\
+         \x20 Linux    $XDG_CONFIG_HOME/markpix/config.toml（默认 ~/.config/markpix/config.toml）\n\
+         \x20 macOS    ~/Library/Application Support/markpix/config.toml\n\
+         \x20 Windows  %APPDATA%\\markpix\\config.toml\n\
+         \n使用 `markpix config path` 查看当前生效的路径。",
+    );
+    footer
+}
+
+/// 失败时打印错误并以对应状态退出
+fn exit_on_error(result: Result<(), String>, status: ExitStatus) {
+    if let Err(err) = result {
+        eprintln!("错误: {}", err);
+        status.exit();
+    }
+}
+
+/// 通过 -c 显式指定的配置文件必须存在
+fn check_config_path(config_path: Option<&str>) -> Result<(), String> {
+    match config_path {
+        Some(path) if !Path::new(path).exists() => Err(format!("配置文件不存在: {}", path)),
+        _ => Ok(()),
+    }
+}
 
-    markpix_lib::run_with_args(initial_image, config_path, output_pattern, args.fullscreen)
+/// 执行 config 子命令（不会自动创建配置文件）
+fn run_config_command(action: ConfigAction, config_path: Option<&str>) -> Result<(), String> {
+    let path = AppConfig::path_for(config_path);
+    match action {
+        ConfigAction::Path => println!("{}", path.display()),
+        ConfigAction::Show { format, project } => {
+            let (_, config) = ConfigLayers::load_strict(path, project)?;
+            let output = match format {
+                ConfigFormat::Toml => config.to_toml()?,
+                ConfigFormat::Json => {
+                    serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?
+                }
+            };
+            println!("{}", output.trim_end());
+        }
+        ConfigAction::Get { key, project } => match ConfigLayers::load_strict(path, project)?
+            .1
+            .get_value(&key)?
+        {
+            serde_json::Value::String(value) => println!("{}", value),
+            value => println!(
+                "{}",
+                serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?
+            ),
+        },
+        ConfigAction::Sources { project } => {
+            let (layers, config) = ConfigLayers::load_strict(path, project)?;
+            for origin in layers.origins(&config) {
+                let value = serde_json::to_string(&origin.value).map_err(|e| e.to_string())?;
+                match origin.path {
+                    Some(file) => println!(
+                        "{} = {}  # {} ({})",
+                        origin.key,
+                        value,
+                        origin.source.label(),
+                        file
+                    ),
+                    None => println!("{} = {}  # {}", origin.key, value, origin.source.label()),
+                }
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let config = AppConfig::read_from(&path)?.with_value(&key, &value)?;
+            let parts: Vec<String> = key.split('.').map(str::to_string).collect();
+            config_layers::write_keys(&path, &config, &[parts], &toml::Table::new())?;
+            eprintln!("已更新 {}: {}", path.display(), key);
+        }
+        ConfigAction::Presets => {
+            let (_, config) = ConfigLayers::load_strict(path, None)?;
+            for preset in presets::list_presets(&config.presets) {
+                let style = serde_json::to_string(&preset.style).map_err(|e| e.to_string())?;
+                let origin = if preset.builtin { "内置" } else { "配置" };
+                println!("{}  {}  # {}", preset.name, style, origin);
+            }
+            for (name, colors) in &config.palettes {
+                println!("调色板 {}: {}", name, colors.join(", "));
+            }
+        }
+        ConfigAction::ImportPresets { file } => {
+            if !file.exists() {
+                return Err(format!("文件不存在: {}", file.display()));
+            }
+            let other = AppConfig::read_from(&file)?;
+            let (config, keys) = AppConfig::read_from(&path)?.with_imported_presets(&other);
+            if keys.is_empty() {
+                return Err(format!("{} 中没有预设或调色板", file.display()));
+            }
+            config.validate()?;
+            config_layers::write_keys(&path, &config, &keys, &toml::Table::new())?;
+            for key in &keys {
+                eprintln!("已导入 {}", key.join("."));
+            }
+        }
+        ConfigAction::Validate { file } => {
+            if !file.exists() {
+                return Err(format!("文件不存在: {}", file.display()));
+            }
+            AppConfig::read_from(&file)?;
+            let content = std::fs::read_to_string(&file).map_err(|e| e.to_string())?;
+            for key in AppConfig::unknown_keys(&content)? {
+                eprintln!("警告: 未知配置项 {}", key);
+            }
+            println!("配置有效: {}", file.display());
+        }
+        ConfigAction::Default => {
+            println!(
+                "{}",
+                AppConfig::default_with_examples().to_toml()?.trim_end()
+            )
+        }
+    }
+    Ok(())
+}
+
+/// 执行批量渲染，打印进度与失败列表；任一文件失败时返回错误
+fn run_batch(
+    config: &AppConfig,
+    annotations: &Path,
+    output_pattern: Option<String>,
+    jobs: Option<usize>,
+    inputs: &[String],
+    output_format: Option<OutputFormat>,
+    quality: Option<u8>,
+) -> Result<(), String> {
+    let template = AnnotationDocument::load(annotations)?;
+    let inputs = input::expand_paths(inputs)?;
+    let output_pattern = output_pattern.unwrap_or_else(|| config.output_pattern.clone());
+    if output_pattern == "-" {
+        return Err("批量模式不支持输出到 stdout".to_string());
+    }
+    let output_pattern = output_pattern::OutputPattern::parse(&output_pattern)?;
+    if let Some(jobs) = jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|e| format!("创建线程池失败: {}", e))?;
+    }
+
+    let job = batch::BatchJob {
+        template: &template,
+        inputs,
+        output_pattern: &output_pattern,
+        output_format,
+        export: &config.export,
+        quality,
+    };
+    let report = batch::run_batch(&job, |done, total, item| match &item.result {
+        Ok(output) => eprintln!(
+            "[{}/{}] {} -> {}",
+            done,
+            total,
+            item.input.display(),
+            output.display()
+        ),
+        Err(_) => eprintln!("[{}/{}] {} 失败", done, total, item.input.display()),
+    });
+
+    let failed: Vec<_> = report.failed().collect();
+    eprintln!(
+        "完成: 共 {} 个，成功 {} 个，失败 {} 个",
+        report.items.len(),
+        report.succeeded(),
+        failed.len()
+    );
+    if failed.is_empty() {
+        return Ok(());
+    }
+    for (input, err) in &failed {
+        eprintln!("  {}: {}", input.display(), err);
+    }
+    Err(format!("{} 个文件处理失败", failed.len()))
+}
+
+/// 展开位置参数中的路径、通配符与 URI，得到按顺序排列的图片队列
+fn resolve_image_queue(patterns: &[String]) -> Vec<String> {
+    let mut images: Vec<String> = patterns
+        .iter()
+        .flat_map(|pattern| {
+            if input::is_uri(pattern) {
+                return resolve_uri(pattern).into_iter().collect();
+            }
+            input::expand_paths(std::slice::from_ref(pattern))
+                .unwrap_or_else(|err| {
+                    eprintln!("警告: {}", err);
+                    Vec::new()
+                })
+                .iter()
+                .filter_map(|path| path.to_str().and_then(resolve_path))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    images.retain(|path| seen.insert(path.clone()));
+    images
 }
 
 /// 解析路径为绝对路径
@@ -76,31 +622,75 @@ fn resolve_path(path: &str) -> Option<String> {
             .unwrap_or(path)
     };
 
-    if abs_path.exists() {
-        abs_path.to_str().map(|s| s.to_string())
-    } else {
-        eprintln!("警告: 文件不存在 - {}", abs_path.display());
-        None
+    match input::detect_file_format(&abs_path) {
+        Ok(_) => abs_path.to_str().map(|s| s.to_string()),
+        Err(err) => {
+            eprintln!("警告: {}", err);
+            None
+        }
     }
 }
 
-/// 从 stdin 读取图片数据并保存到临时文件
+/// 解析 URI 参数：file:// 与 markpix:// 转换为本地路径，data: 写入临时文件
+///
+/// URI 本身无效时直接退出，避免把拼写错误的链接当作普通文件名处理。
+fn resolve_uri(uri: &str) -> Option<String> {
+    let result = input::parse_uri(uri).and_then(|parsed| match parsed {
+        input::UriInput::Path(path) => Ok(path),
+        input::UriInput::Data(data) => input::save_temp_image(&data, "data-uri", "data: URI"),
+    });
+    match result {
+        Ok(path) => path.to_str().and_then(resolve_path),
+        Err(err) => {
+            eprintln!("错误: {}", err);
+            ExitStatus::LoadError.exit();
+        }
+    }
+}
+
+/// 调用配置的截图命令，失败时以对应状态退出（取消框选视为取消）
+fn capture_screen(config: &AppConfig, mode: &CaptureMode) -> String {
+    match capture::capture(&config.capture, mode) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(err) => {
+            eprintln!("错误: {}", err);
+            match err {
+                CaptureError::Cancelled => ExitStatus::Cancelled.exit(),
+                CaptureError::NotConfigured { .. } => ExitStatus::ConfigError.exit(),
+                _ => ExitStatus::Failure.exit(),
+            }
+        }
+    }
+}
+
+/// 读取剪贴板图片并保存到临时文件，没有图片时以专用状态码退出
+///
+/// 缺少剪贴板工具属于环境问题，剪贴板内容无法作为图片读取属于输入错误，二者不使用“没有图片”的状态码。
+fn read_image_from_clipboard() -> Option<String> {
+    match clipboard::save_image_to_temp() {
+        Ok(temp_path) => temp_path.to_str().map(|s| s.to_string()),
+        Err(err) => {
+            eprintln!("错误: {}", err);
+            match err {
+                ClipboardError::NoImage => ExitStatus::NoClipboardImage.exit(),
+                ClipboardError::NoBackend => ExitStatus::Failure.exit(),
+                ClipboardError::Input(_) => ExitStatus::LoadError.exit(),
+            }
+        }
+    }
+}
+
+/// 从 stdin 读取图片数据并保存到临时文件（扩展名按内容识别）
 fn read_image_from_stdin() -> Option<String> {
     let mut buffer = Vec::new();
-    if io::stdin().read_to_end(&mut buffer).is_ok() && !buffer.is_empty() {
-        // 保存到临时文件
-        let temp_dir = std::env::temp_dir().join("markpix");
-        std::fs::create_dir_all(&temp_dir).ok()?;
-        
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        let temp_path = temp_dir.join(format!("stdin-{}.png", timestamp));
-        
-        std::fs::write(&temp_path, buffer).ok()?;
-        temp_path.to_str().map(|s| s.to_string())
-    } else {
-        None
+    if io::stdin().read_to_end(&mut buffer).is_err() || buffer.is_empty() {
+        return None;
+    }
+    match input::save_temp_image(&buffer, "stdin", "stdin") {
+        Ok(temp_path) => temp_path.to_str().map(|s| s.to_string()),
+        Err(err) => {
+            eprintln!("错误: {}", err);
+            ExitStatus::LoadError.exit();
+        }
     }
 }
@@ -0,0 +1,204 @@
+// MarkPix - 图片标注工具
+// 会话结果与退出码：供脚本判断用户是保存、复制还是取消
+
+use serde::Serialize;
+
+/// 进程退出状态（退出码 2 保留给命令行参数错误）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    /// 已保存到文件或输出到 stdout（可能同时复制）
+    Saved,
+    /// 已转交给正在运行的实例（单实例模式）
+    Forwarded,
+    /// 关闭时未保存或复制任何内容
+    Cancelled,
+    /// --from-clipboard 时剪贴板中没有图片
+    NoClipboardImage,
+    /// 仅复制到剪贴板（未写入文件）
+    Copied,
+    /// 输入图片无法读取
+    LoadError,
+    /// 配置文件或配置参数无效
+    ConfigError,
+    /// 其他错误（子命令执行失败、应用启动失败等）
+    Failure,
+    /// 仅执行了自定义动作（未保存也未复制）
+    ActionRun,
+}
+
+impl ExitStatus {
+    /// 对应的进程退出码（仅复制单独使用 4，脚本无需解析输出即可区分是否写入了文件）
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Saved | Self::Forwarded => 0,
+            Self::Cancelled => 1,
+            Self::NoClipboardImage => 3,
+            Self::Copied => 4,
+            Self::LoadError => 5,
+            Self::ConfigError => 6,
+            Self::Failure => 7,
+            Self::ActionRun => 8,
+        }
+    }
+
+    /// 以该状态退出进程
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+
+/// --print-result 的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ResultFormat {
+    Json,
+}
+
+/// 本次会话实际产生的输出
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionOutcome {
+    /// 已保存的文件路径（按保存顺序）
+    pub saved: Vec<String>,
+    /// 是否已输出到 stdout（管道模式）
+    pub stdout: bool,
+    /// 是否已复制到剪贴板
+    pub copied: bool,
+    /// 已执行的自定义动作名称
+    pub actions: Vec<String>,
+}
+
+/// 退出时打印的结果
+#[derive(Serialize)]
+struct ResultReport<'a> {
+    status: ExitStatus,
+    exit_code: i32,
+    #[serde(flatten)]
+    outcome: &'a SessionOutcome,
+}
+
+impl SessionOutcome {
+    /// 根据实际输出确定退出状态：保存/stdout 优先于复制，复制优先于自定义动作
+    pub fn status(&self) -> ExitStatus {
+        if !self.saved.is_empty() || self.stdout {
+            ExitStatus::Saved
+        } else if self.copied {
+            ExitStatus::Copied
+        } else if !self.actions.is_empty() {
+            ExitStatus::ActionRun
+        } else {
+            ExitStatus::Cancelled
+        }
+    }
+
+    /// 按指定格式生成结果文本
+    pub fn render(&self, format: ResultFormat) -> String {
+        let status = self.status();
+        let report = ResultReport {
+            status,
+            exit_code: status.code(),
+            outcome: self,
+        };
+        match format {
+            ResultFormat::Json => serde_json::to_string(&report).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_match_documented_table() {
+        let codes = [
+            (ExitStatus::Saved, 0),
+            (ExitStatus::Forwarded, 0),
+            (ExitStatus::Cancelled, 1),
+            (ExitStatus::NoClipboardImage, 3),
+            (ExitStatus::Copied, 4),
+            (ExitStatus::LoadError, 5),
+            (ExitStatus::ConfigError, 6),
+            (ExitStatus::Failure, 7),
+            (ExitStatus::ActionRun, 8),
+        ];
+        for (status, code) in codes {
+            assert_eq!(status.code(), code, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn empty_session_is_cancelled() {
+        assert_eq!(SessionOutcome::default().status(), ExitStatus::Cancelled);
+    }
+
+    #[test]
+    fn saved_or_stdout_beats_copied_and_actions() {
+        let saved = SessionOutcome {
+            saved: vec!["/tmp/a.png".into()],
+            copied: true,
+            actions: vec!["upload".into()],
+            ..Default::default()
+        };
+        assert_eq!(saved.status(), ExitStatus::Saved);
+
+        let stdout = SessionOutcome {
+            stdout: true,
+            copied: true,
+            ..Default::default()
+        };
+        assert_eq!(stdout.status(), ExitStatus::Saved);
+    }
+
+    #[test]
+    fn copied_beats_actions() {
+        let outcome = SessionOutcome {
+            copied: true,
+            actions: vec!["upload".into()],
+            ..Default::default()
+        };
+        assert_eq!(outcome.status(), ExitStatus::Copied);
+    }
+
+    #[test]
+    fn action_only_session_is_not_cancelled() {
+        let outcome = SessionOutcome {
+            actions: vec!["upload".into()],
+            ..Default::default()
+        };
+        assert_eq!(outcome.status(), ExitStatus::ActionRun);
+    }
+
+    #[test]
+    fn json_report_flattens_outcome() {
+        let outcome = SessionOutcome {
+            saved: vec!["/tmp/a.png".into()],
+            copied: true,
+            ..Default::default()
+        };
+        let report: serde_json::Value =
+            serde_json::from_str(&outcome.render(ResultFormat::Json)).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "status": "saved",
+                "exit_code": 0,
+                "saved": ["/tmp/a.png"],
+                "stdout": false,
+                "copied": true,
+                "actions": [],
+            })
+        );
+    }
+
+    #[test]
+    fn json_report_uses_snake_case_status() {
+        let outcome = SessionOutcome {
+            actions: vec!["upload".into()],
+            ..Default::default()
+        };
+        let report: serde_json::Value =
+            serde_json::from_str(&outcome.render(ResultFormat::Json)).unwrap();
+        assert_eq!(report["status"], "action_run");
+        assert_eq!(report["exit_code"], 8);
+    }
+}
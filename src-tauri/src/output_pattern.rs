@@ -0,0 +1,534 @@
+// MarkPix - 图片标注工具
+// 输出文件名模式：解析、校验与展开（GUI 与 CLI 共用）
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use image::RgbaImage;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 默认输出文件名模式
+pub const DEFAULT_OUTPUT_PATTERN: &str = "{input_file_base}_{YYYY_MM_DD-hh-mm-ss}_markpix.png";
+
+/// {counter} 查找可用编号的上限
+const MAX_COUNTER: u32 = 1_000_000;
+
+/// 支持的占位符及说明（用于命令行帮助与手册页）
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    (
+        "{input_file_base}",
+        "输入文件名（不含扩展名），无输入文件时为 image",
+    ),
+    ("{input_file}", "输入文件的完整路径"),
+    ("{ext}", "输入文件的扩展名（小写），无输入文件时为 png"),
+    (
+        "{YYYY_MM_DD-hh-mm-ss}",
+        "当前本地时间，如 2024_01_31-09-05-00",
+    ),
+    ("{date}", "当前日期，如 2024-01-31"),
+    (
+        "{date:FORMAT}",
+        "按 strftime 格式输出当前时间，如 {date:%Y%m%d-%H%M%S}",
+    ),
+    ("{width} {height}", "输出图片的宽度与高度（像素）"),
+    (
+        "{counter} {counter:N}",
+        "从 1 开始的编号，跳过已存在的文件；N 为补零位数",
+    ),
+    ("{hash8}", "图片内容 SHA-256 的前 8 位十六进制"),
+    ("{hostname}", "主机名"),
+    ("{user}", "当前用户名"),
+    ("{{ }}", "字面量花括号"),
+];
+
+/// 模式中的一个片段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    InputFileBase,
+    InputFile,
+    Ext,
+    LegacyTimestamp,
+    Date(String),
+    Width,
+    Height,
+    Counter(usize),
+    Hash8,
+    Hostname,
+    User,
+}
+
+/// 已解析的输出文件名模式
+#[derive(Debug, Clone)]
+pub struct OutputPattern {
+    segments: Vec<Segment>,
+}
+
+/// 展开模式所需的上下文
+pub struct PatternContext<'a> {
+    /// 输入图片路径
+    pub input: Option<&'a Path>,
+    /// 将要保存的图片（用于 {width}/{height}/{hash8}）
+    pub image: Option<&'a RgbaImage>,
+    /// 展开时刻（同一次展开内所有时间占位符保持一致）
+    pub now: DateTime<Local>,
+    /// 相对路径的基准目录，为 None 时保留相对路径
+    pub base_dir: Option<PathBuf>,
+}
+
+impl<'a> PatternContext<'a> {
+    /// 相对路径基于当前工作目录解析（命令行使用）
+    pub fn new(input: Option<&'a Path>, image: Option<&'a RgbaImage>) -> Self {
+        Self {
+            input,
+            image,
+            now: Local::now(),
+            base_dir: std::env::current_dir().ok(),
+        }
+    }
+
+    /// 保留相对路径，由调用方（如保存对话框）决定所在目录
+    pub fn keep_relative(mut self) -> Self {
+        self.base_dir = None;
+        self
+    }
+}
+
+impl OutputPattern {
+    /// 解析并校验模式，错误信息指出出错的位置
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let invalid = |position: usize, reason: String| {
+            format!(
+                "输出模式无效: {}（第 {} 个字符）: {}",
+                reason,
+                position + 1,
+                pattern
+            )
+        };
+        if pattern.trim().is_empty() {
+            return Err("输出模式不能为空".to_string());
+        }
+
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = pattern;
+        while let Some(index) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..index]);
+            let position = pattern[..pattern.len() - rest.len() + index]
+                .chars()
+                .count();
+            let tail = &rest[index..];
+            let consumed = if tail.starts_with("{{") {
+                literal.push('{');
+                2
+            } else if tail.starts_with("}}") {
+                literal.push('}');
+                2
+            } else if tail.starts_with('}') {
+                return Err(invalid(position, "多余的 }".to_string()));
+            } else {
+                let end = tail
+                    .find('}')
+                    .ok_or_else(|| invalid(position, "缺少 } 闭合占位符".to_string()))?;
+                let segment =
+                    parse_token(&tail[1..end]).map_err(|reason| invalid(position, reason))?;
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(segment);
+                end + 1
+            };
+            rest = &tail[consumed..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        let parsed = Self { segments };
+        if parsed.file_name_template().is_empty() {
+            return Err(format!("输出模式缺少文件名: {}", pattern));
+        }
+        Ok(parsed)
+    }
+
+    /// 是否需要图片内容（尺寸或哈希）才能展开
+    pub fn needs_image(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Width | Segment::Height | Segment::Hash8))
+    }
+
+    /// 展开为路径：含 {counter} 时跳过 is_taken 返回 true 的路径
+    ///
+    /// `~/` 开头的路径展开到用户主目录，相对路径基于 `context.base_dir` 解析。
+    pub fn resolve(&self, context: &PatternContext, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+        let has_counter = self
+            .segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Counter(_)));
+        if !has_counter {
+            return absolutize(&self.render(context, 0), context.base_dir.as_deref());
+        }
+        let mut path = PathBuf::new();
+        for counter in 1..=MAX_COUNTER {
+            path = absolutize(&self.render(context, counter), context.base_dir.as_deref());
+            if !is_taken(&path) {
+                break;
+            }
+        }
+        path
+    }
+
+    /// 展开为路径，{counter} 跳过磁盘上已存在的文件
+    pub fn expand(&self, context: &PatternContext) -> PathBuf {
+        self.resolve(context, |path| path.exists())
+    }
+
+    fn render(&self, context: &PatternContext, counter: u32) -> String {
+        let mut output = String::new();
+        let mut hash = None;
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::InputFileBase => output.push_str(&sanitize(
+                    &context
+                        .input
+                        .and_then(|p| p.file_stem())
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "image".to_string()),
+                )),
+                Segment::InputFile => {
+                    if let Some(input) = context.input {
+                        output.push_str(&input.to_string_lossy());
+                    }
+                }
+                Segment::Ext => output.push_str(
+                    &context
+                        .input
+                        .and_then(|p| p.extension())
+                        .map(|s| s.to_string_lossy().to_lowercase())
+                        .unwrap_or_else(|| "png".to_string()),
+                ),
+                Segment::LegacyTimestamp => {
+                    output.push_str(&context.now.format("%Y_%m_%d-%H-%M-%S").to_string())
+                }
+                // 日期格式中的 / 用于按日期分目录，不做替换
+                Segment::Date(format) => output.push_str(&context.now.format(format).to_string()),
+                Segment::Width => {
+                    output.push_str(&context.image.map_or(0, |image| image.width()).to_string())
+                }
+                Segment::Height => {
+                    output.push_str(&context.image.map_or(0, |image| image.height()).to_string())
+                }
+                Segment::Counter(width) => {
+                    output.push_str(&format!("{:0width$}", counter, width = *width))
+                }
+                Segment::Hash8 => {
+                    output.push_str(hash.get_or_insert_with(|| content_hash8(context.image)))
+                }
+                Segment::Hostname => output.push_str(&sanitize(&hostname())),
+                Segment::User => output.push_str(&sanitize(&username())),
+            }
+        }
+        output
+    }
+
+    /// 文件名部分的模板（最后一个路径分隔符之后），用于检查是否为空
+    fn file_name_template(&self) -> String {
+        let mut name = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => match text.rfind(['/', '\\']) {
+                    Some(index) => name = text[index + 1..].to_string(),
+                    None => name.push_str(text),
+                },
+                _ => name.push('*'),
+            }
+        }
+        name
+    }
+}
+
+/// 校验模式（配置加载与命令行参数使用）
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    OutputPattern::parse(pattern).map(|_| ())
+}
+
+/// 按需创建输出文件所在目录
+pub fn ensure_parent_dir(path: &Path) -> Result<(), String> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent)
+            .map_err(|e| format!("创建目录失败: {} ({})", parent.display(), e)),
+        _ => Ok(()),
+    }
+}
+
+fn parse_token(token: &str) -> Result<Segment, String> {
+    let (name, argument) = match token.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (token, None),
+    };
+    let no_argument = |segment: Segment| match argument {
+        Some(_) => Err(format!("占位符 {{{}}} 不接受参数", name)),
+        None => Ok(segment),
+    };
+    match name {
+        "input_file_base" => no_argument(Segment::InputFileBase),
+        "input_file" => no_argument(Segment::InputFile),
+        "ext" => no_argument(Segment::Ext),
+        "YYYY_MM_DD-hh-mm-ss" => no_argument(Segment::LegacyTimestamp),
+        "width" => no_argument(Segment::Width),
+        "height" => no_argument(Segment::Height),
+        "hash8" => no_argument(Segment::Hash8),
+        "hostname" => no_argument(Segment::Hostname),
+        "user" => no_argument(Segment::User),
+        "date" => {
+            let format = argument.unwrap_or("%Y-%m-%d");
+            if format.is_empty()
+                || StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+            {
+                return Err(format!("无效的日期格式: {}", format));
+            }
+            Ok(Segment::Date(format.to_string()))
+        }
+        "counter" => match argument {
+            None => Ok(Segment::Counter(1)),
+            Some(width) => width
+                .parse::<usize>()
+                .ok()
+                .filter(|width| (1..=10).contains(width))
+                .map(Segment::Counter)
+                .ok_or_else(|| format!("{{counter:N}} 的位数应为 1-10: {}", width)),
+        },
+        _ => Err(format!("未知占位符 {{{}}}", token)),
+    }
+}
+
+/// 将占位符值中的路径分隔符替换掉，避免意外产生子目录
+fn sanitize(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+/// 展开 `~/` 并将相对路径基于 base_dir 解析
+fn absolutize(expanded: &str, base_dir: Option<&Path>) -> PathBuf {
+    let path = match expanded.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(expanded)),
+        None => PathBuf::from(expanded),
+    };
+    match base_dir {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    }
+}
+
+/// 图片像素内容的 SHA-256 前 8 位（与输出格式无关）
+fn content_hash8(image: Option<&RgbaImage>) -> String {
+    let Some(image) = image else {
+        return "00000000".to_string();
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(image.width().to_le_bytes());
+    hasher.update(image.height().to_le_bytes());
+    hasher.update(image.as_raw());
+    hasher.finalize()[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "user".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fixed_context<'a>(
+        input: Option<&'a Path>,
+        image: Option<&'a RgbaImage>,
+    ) -> PatternContext<'a> {
+        PatternContext {
+            input,
+            image,
+            now: Local.with_ymd_and_hms(2024, 1, 31, 9, 5, 0).unwrap(),
+            base_dir: Some(PathBuf::from("/out")),
+        }
+    }
+
+    fn expand(pattern: &str, context: &PatternContext) -> PathBuf {
+        OutputPattern::parse(pattern).unwrap().expand(context)
+    }
+
+    fn parse_error(pattern: &str) -> String {
+        OutputPattern::parse(pattern).unwrap_err()
+    }
+
+    #[test]
+    fn parses_tokens_and_escaped_braces() {
+        let pattern = OutputPattern::parse("{{a}}_{input_file_base}.{ext}").unwrap();
+        assert_eq!(
+            pattern.segments,
+            [
+                Segment::Literal("{a}_".to_string()),
+                Segment::InputFileBase,
+                Segment::Literal(".".to_string()),
+                Segment::Ext,
+            ]
+        );
+        assert_eq!(
+            OutputPattern::parse("{counter}{counter:3}{date:%H}")
+                .unwrap()
+                .segments,
+            [
+                Segment::Counter(1),
+                Segment::Counter(3),
+                Segment::Date("%H".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_input_and_time_tokens() {
+        let input = Path::new("/tmp/shots/My shot.PNG");
+        let context = fixed_context(Some(input), None);
+        assert_eq!(
+            expand(DEFAULT_OUTPUT_PATTERN, &context),
+            Path::new("/out/My shot_2024_01_31-09-05-00_markpix.png")
+        );
+        assert_eq!(
+            expand("{date}/{date:%H%M}.{ext}", &context),
+            Path::new("/out/2024-01-31/0905.png")
+        );
+        assert_eq!(
+            expand("{input_file}.bak", &context),
+            Path::new("/tmp/shots/My shot.PNG.bak")
+        );
+        assert_eq!(
+            expand("{input_file_base}.{ext}", &fixed_context(None, None)),
+            Path::new("/out/image.png")
+        );
+    }
+
+    #[test]
+    fn keeps_relative_paths_when_asked() {
+        let context = fixed_context(None, None).keep_relative();
+        assert_eq!(
+            expand("shots/{date}.png", &context),
+            Path::new("shots/2024-01-31.png")
+        );
+    }
+
+    #[test]
+    fn expands_image_size_and_hash() {
+        let image = RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255]));
+        let other = RgbaImage::from_pixel(3, 2, image::Rgba([3, 2, 1, 255]));
+        let pattern = OutputPattern::parse("{width}x{height}_{hash8}.png").unwrap();
+        assert!(pattern.needs_image());
+        assert!(!OutputPattern::parse("{date}.png").unwrap().needs_image());
+
+        let name = pattern.expand(&fixed_context(None, Some(&image)));
+        let name = name.file_name().unwrap().to_string_lossy().to_string();
+        let hash = name
+            .strip_prefix("3x2_")
+            .and_then(|rest| rest.strip_suffix(".png"))
+            .unwrap();
+        assert_eq!(hash.len(), 8);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(
+            pattern.expand(&fixed_context(None, Some(&image))),
+            pattern.expand(&fixed_context(None, Some(&image)))
+        );
+        assert_ne!(
+            pattern.expand(&fixed_context(None, Some(&image))),
+            pattern.expand(&fixed_context(None, Some(&other)))
+        );
+    }
+
+    #[test]
+    fn counter_skips_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["shot_1.png", "shot_2.png", "shot_001.png"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let context = PatternContext {
+            base_dir: Some(dir.path().to_path_buf()),
+            ..fixed_context(None, None)
+        };
+        assert_eq!(
+            expand("shot_{counter}.png", &context),
+            dir.path().join("shot_3.png")
+        );
+        assert_eq!(
+            expand("shot_{counter:3}.png", &context),
+            dir.path().join("shot_002.png")
+        );
+        // 调用方可额外排除已占用的路径（如批处理中其他输入预留的文件）
+        let pattern = OutputPattern::parse("shot_{counter}.png").unwrap();
+        let taken = dir.path().join("shot_3.png");
+        assert_eq!(
+            pattern.resolve(&context, |path| path.exists() || path == taken),
+            dir.path().join("shot_4.png")
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_character() {
+        assert_eq!(
+            parse_error("shot_{nope}.png"),
+            "输出模式无效: 未知占位符 {nope}（第 6 个字符）: shot_{nope}.png"
+        );
+        // 位置按字符计数
+        assert!(parse_error("截图_{nope}.png").contains("（第 4 个字符）"));
+        assert!(parse_error("a}b.png").contains("多余的 }（第 2 个字符）"));
+        assert!(parse_error("a{date.png").contains("缺少 } 闭合占位符（第 2 个字符）"));
+        assert!(parse_error("{{x}}{input_file:x}.png").contains("不接受参数（第 6 个字符）"));
+        for width in ["0", "11", "x"] {
+            let err = parse_error(&format!("{{counter:{}}}.png", width));
+            assert!(err.contains("{counter:N} 的位数应为 1-10"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn date_format_is_validated() {
+        for format in ["", "%Q", "%Y-%"] {
+            let err = parse_error(&format!("{{date:{}}}.png", format));
+            assert!(err.contains("无效的日期格式"), "{}: {}", format, err);
+        }
+        assert!(validate_pattern("{date:%Y/%m/%d}.png").is_ok());
+    }
+
+    #[test]
+    fn empty_file_name_is_rejected() {
+        assert_eq!(parse_error(""), "输出模式不能为空");
+        assert_eq!(parse_error("   "), "输出模式不能为空");
+        for pattern in ["~/Pictures/", "{date}/", "shots\\"] {
+            assert!(
+                parse_error(pattern).starts_with("输出模式缺少文件名"),
+                "{}",
+                pattern
+            );
+        }
+        assert!(validate_pattern("{date}/{counter}").is_ok());
+    }
+}
@@ -0,0 +1,308 @@
+// MarkPix - 图片标注工具
+// 样式预设：工具与样式字段的命名组合，用于启动时直接进入特定工作模式
+// 调色板：命名的颜色列表（配置文件 palettes）
+
+use crate::color;
+use crate::tools::ToolType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 预设中描边粗细的范围（与 --stroke-width、工具栏滑块一致；
+/// tool_defaults 另外容纳滚轮调节到的更大值）
+const STROKE_WIDTH_RANGE: std::ops::RangeInclusive<u32> = 1..=20;
+/// 预设中字号的范围
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<u32> = 8..=200;
+
+/// 样式预设：未设置的字段保持编辑器当前值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StylePreset {
+    /// 选中的工具
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<ToolType>,
+    /// 描边颜色（文字工具为文字颜色）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_color: Option<String>,
+    /// 填充颜色
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fill_color: Option<String>,
+    /// 描边粗细
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<u32>,
+    /// 字号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u32>,
+}
+
+impl StylePreset {
+    /// 逐字段合并：自身已设置的字段优先，其余取 fallback
+    pub fn or(self, fallback: StylePreset) -> Self {
+        Self {
+            tool: self.tool.or(fallback.tool),
+            stroke_color: self.stroke_color.or(fallback.stroke_color),
+            fill_color: self.fill_color.or(fallback.fill_color),
+            stroke_width: self.stroke_width.or(fallback.stroke_width),
+            font_size: self.font_size.or(fallback.font_size),
+        }
+    }
+}
+
+/// 预设列表中的一项（内置预设与配置文件中的预设）
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetInfo {
+    pub name: String,
+    /// 是否为内置预设（配置文件中的同名预设会覆盖内置预设）
+    pub builtin: bool,
+    #[serde(flatten)]
+    pub style: StylePreset,
+}
+
+/// 检查配置文件中的预设名称与样式取值
+pub fn validate_preset(name: &str, preset: &StylePreset) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("presets 中的预设名称不能为空".to_string());
+    }
+    let key = |field: &str| format!("presets.{}.{}", name, field);
+    for (field, value) in [
+        ("stroke_color", &preset.stroke_color),
+        ("fill_color", &preset.fill_color),
+    ] {
+        if let Some(value) = value {
+            color::parse_css_color(value).map_err(|e| format!("{} {}", key(field), e))?;
+        }
+    }
+    if let Some(width) = preset.stroke_width {
+        if !STROKE_WIDTH_RANGE.contains(&width) {
+            return Err(format!(
+                "{} 超出范围 {}-{}: {}",
+                key("stroke_width"),
+                STROKE_WIDTH_RANGE.start(),
+                STROKE_WIDTH_RANGE.end(),
+                width
+            ));
+        }
+    }
+    if let Some(size) = preset.font_size {
+        if !FONT_SIZE_RANGE.contains(&size) {
+            return Err(format!(
+                "{} 超出范围 {}-{}: {}",
+                key("font_size"),
+                FONT_SIZE_RANGE.start(),
+                FONT_SIZE_RANGE.end(),
+                size
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 检查调色板名称与颜色
+pub fn validate_palette(name: &str, colors: &[String]) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("palettes 中的调色板名称不能为空".to_string());
+    }
+    for (index, value) in colors.iter().enumerate() {
+        color::parse_css_color(value)
+            .map_err(|e| format!("palettes.{} 第 {} 个颜色: {}", name, index + 1, e))?;
+    }
+    Ok(())
+}
+
+/// 内置预设
+pub fn builtin_presets() -> Vec<(&'static str, StylePreset)> {
+    vec![
+        (
+            "redact",
+            StylePreset {
+                tool: Some(ToolType::Blur),
+                ..Default::default()
+            },
+        ),
+        (
+            "highlight",
+            StylePreset {
+                tool: Some(ToolType::Rectangle),
+                stroke_color: Some("#facc15".to_string()),
+                stroke_width: Some(3),
+                ..Default::default()
+            },
+        ),
+        (
+            "callout",
+            StylePreset {
+                tool: Some(ToolType::Arrow),
+                stroke_color: Some("#ef4444".to_string()),
+                stroke_width: Some(4),
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+/// 所有可用的预设：内置预设在前，配置文件中的预设按名称排序在后
+pub fn list_presets(custom: &BTreeMap<String, StylePreset>) -> Vec<PresetInfo> {
+    let builtin = builtin_presets()
+        .into_iter()
+        .filter(|(name, _)| !custom.contains_key(*name))
+        .map(|(name, style)| PresetInfo {
+            name: name.to_string(),
+            builtin: true,
+            style,
+        });
+    let custom = custom.iter().map(|(name, style)| PresetInfo {
+        name: name.clone(),
+        builtin: false,
+        style: style.clone(),
+    });
+    builtin.chain(custom).collect()
+}
+
+/// 按名称查找预设（配置文件中的预设优先于同名内置预设）
+pub fn find_preset(
+    name: &str,
+    custom: &BTreeMap<String, StylePreset>,
+) -> Result<StylePreset, String> {
+    if let Some(preset) = custom.get(name) {
+        return Ok(preset.clone());
+    }
+    let presets = builtin_presets();
+    presets
+        .iter()
+        .find(|(preset_name, _)| *preset_name == name)
+        .map(|(_, preset)| preset.clone())
+        .ok_or_else(|| {
+            let names: Vec<_> = list_presets(custom)
+                .into_iter()
+                .map(|preset| preset.name)
+                .collect();
+            format!("未知预设: {}（可用: {}）", name, names.join(", "))
+        })
+}
+
+/// 启动时的工具与样式（CLI --tool/--preset/--stroke-color/--stroke-width）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupOptions {
+    /// 使用的预设名称
+    pub preset: Option<String>,
+    /// 最终生效的工具与样式
+    #[serde(flatten)]
+    pub style: StylePreset,
+}
+
+impl StartupOptions {
+    /// 套用预设：命令行显式指定的字段优先于预设
+    pub fn resolve(self, custom: &BTreeMap<String, StylePreset>) -> Result<Self, String> {
+        let Some(name) = self.preset.as_deref() else {
+            return Ok(self);
+        };
+        let style = self.style.or(find_preset(name, custom)?);
+        Ok(Self { style, ..self })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(name: &str, preset: StylePreset) -> BTreeMap<String, StylePreset> {
+        BTreeMap::from([(name.to_string(), preset)])
+    }
+
+    #[test]
+    fn builtin_presets_are_valid() {
+        for (name, preset) in builtin_presets() {
+            assert!(validate_preset(name, &preset).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn validate_preset_rejects_empty_name_and_bad_color() {
+        assert!(validate_preset(" ", &StylePreset::default()).is_err());
+        let preset = StylePreset {
+            fill_color: Some("nope".to_string()),
+            ..Default::default()
+        };
+        let err = validate_preset("box", &preset).unwrap_err();
+        assert!(err.starts_with("presets.box.fill_color"), "{}", err);
+    }
+
+    #[test]
+    fn validate_preset_reports_ranges_from_consts() {
+        let preset = StylePreset {
+            stroke_width: Some(21),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_preset("box", &preset).unwrap_err(),
+            "presets.box.stroke_width 超出范围 1-20: 21"
+        );
+        let preset = StylePreset {
+            font_size: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_preset("box", &preset).unwrap_err(),
+            "presets.box.font_size 超出范围 8-200: 7"
+        );
+    }
+
+    #[test]
+    fn validate_palette_reports_color_position() {
+        assert!(validate_palette("brand", &["#fff".to_string(), "red".to_string()]).is_ok());
+        assert!(validate_palette("", &[]).is_err());
+        let err = validate_palette("brand", &["#fff".to_string(), "bad".to_string()]).unwrap_err();
+        assert!(err.starts_with("palettes.brand 第 2 个颜色"), "{}", err);
+    }
+
+    #[test]
+    fn custom_preset_overrides_builtin() {
+        let preset = StylePreset {
+            tool: Some(ToolType::Ellipse),
+            ..Default::default()
+        };
+        let custom = custom("highlight", preset.clone());
+        assert_eq!(find_preset("highlight", &custom).unwrap(), preset);
+
+        let listed = list_presets(&custom);
+        let highlight: Vec<_> = listed.iter().filter(|p| p.name == "highlight").collect();
+        assert_eq!(highlight.len(), 1);
+        assert!(!highlight[0].builtin);
+    }
+
+    #[test]
+    fn unknown_preset_lists_available_names() {
+        let err = find_preset("missing", &BTreeMap::new()).unwrap_err();
+        assert_eq!(err, "未知预设: missing（可用: redact, highlight, callout）");
+    }
+
+    #[test]
+    fn startup_fields_beat_preset() {
+        let options = StartupOptions {
+            preset: Some("callout".to_string()),
+            style: StylePreset {
+                stroke_width: Some(9),
+                ..Default::default()
+            },
+        };
+        let resolved = options.resolve(&BTreeMap::new()).unwrap();
+        assert_eq!(resolved.style.tool, Some(ToolType::Arrow));
+        assert_eq!(resolved.style.stroke_color.as_deref(), Some("#ef4444"));
+        assert_eq!(resolved.style.stroke_width, Some(9));
+        assert_eq!(resolved.preset.as_deref(), Some("callout"));
+    }
+
+    #[test]
+    fn startup_without_preset_is_unchanged_and_unknown_preset_fails() {
+        let options = StartupOptions::default();
+        assert_eq!(
+            options.resolve(&BTreeMap::new()).unwrap().style,
+            StylePreset::default()
+        );
+        let options = StartupOptions {
+            preset: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(options.resolve(&BTreeMap::new()).is_err());
+    }
+}
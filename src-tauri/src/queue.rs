@@ -0,0 +1,221 @@
+// MarkPix - 图片标注工具
+// 多图队列：命令行传入多张图片时按顺序切换，并为每张图片保留标注状态
+
+use serde::Serialize;
+
+/// 队列中的一张图片
+#[derive(Debug, Clone)]
+struct QueueItem {
+    path: String,
+    /// 前端保存的标注快照（annotations、cropMask 等），原样返回给前端
+    snapshot: Option<serde_json::Value>,
+}
+
+/// 切换图片时返回给前端的数据
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    pub index: usize,
+    pub total: usize,
+    pub path: String,
+    pub snapshot: Option<serde_json::Value>,
+}
+
+/// 队列概览中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueItemInfo {
+    pub index: usize,
+    pub path: String,
+    /// 是否已有保存的标注
+    pub edited: bool,
+}
+
+/// 队列概览
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueInfo {
+    pub current: usize,
+    pub items: Vec<QueueItemInfo>,
+}
+
+/// 有序图片队列
+#[derive(Debug, Default)]
+pub struct ImageQueue {
+    items: Vec<QueueItem>,
+    current: usize,
+}
+
+impl ImageQueue {
+    pub fn new(paths: Vec<String>) -> Self {
+        Self {
+            items: paths
+                .into_iter()
+                .map(|path| QueueItem {
+                    path,
+                    snapshot: None,
+                })
+                .collect(),
+            current: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// 追加图片（已在队列中的不重复添加），返回第一张图片在队列中的位置
+    pub fn add(&mut self, paths: Vec<String>) -> Option<usize> {
+        let mut first = None;
+        for path in paths {
+            let index = match self.items.iter().position(|item| item.path == path) {
+                Some(index) => index,
+                None => {
+                    self.items.push(QueueItem {
+                        path,
+                        snapshot: None,
+                    });
+                    self.items.len() - 1
+                }
+            };
+            first.get_or_insert(index);
+        }
+        first
+    }
+
+    /// 当前图片路径
+    pub fn current_path(&self) -> Option<&str> {
+        self.items.get(self.current).map(|item| item.path.as_str())
+    }
+
+    /// 保存当前图片的标注快照，None 表示清除
+    pub fn store_snapshot(&mut self, snapshot: Option<serde_json::Value>) {
+        if let Some(item) = self.items.get_mut(self.current) {
+            item.snapshot = snapshot.filter(|value| !value.is_null());
+        }
+    }
+
+    /// 保存当前快照后跳转到指定位置，越界时保持不动并返回 None
+    pub fn select(
+        &mut self,
+        index: usize,
+        snapshot: Option<serde_json::Value>,
+    ) -> Option<QueueEntry> {
+        if index >= self.items.len() {
+            return None;
+        }
+        self.store_snapshot(snapshot);
+        self.current = index;
+        self.entry()
+    }
+
+    /// 相对当前位置移动（不循环）
+    pub fn step(
+        &mut self,
+        delta: isize,
+        snapshot: Option<serde_json::Value>,
+    ) -> Option<QueueEntry> {
+        let index = self.current.checked_add_signed(delta)?;
+        self.select(index, snapshot)
+    }
+
+    /// 当前图片的数据
+    pub fn entry(&self) -> Option<QueueEntry> {
+        self.items.get(self.current).map(|item| QueueEntry {
+            index: self.current,
+            total: self.items.len(),
+            path: item.path.clone(),
+            snapshot: item.snapshot.clone(),
+        })
+    }
+
+    /// 队列概览
+    pub fn info(&self) -> QueueInfo {
+        QueueInfo {
+            current: self.current,
+            items: self
+                .items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| QueueItemInfo {
+                    index,
+                    path: item.path.clone(),
+                    edited: item.snapshot.is_some(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn queue(paths: &[&str]) -> ImageQueue {
+        ImageQueue::new(paths.iter().map(|path| path.to_string()).collect())
+    }
+
+    #[test]
+    fn add_skips_duplicates_and_returns_first_index() {
+        let mut queue = queue(&["a.png", "b.png"]);
+        assert_eq!(queue.add(vec!["b.png".into(), "c.png".into()]), Some(1));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.add(vec!["d.png".into(), "a.png".into()]), Some(3));
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.add(vec![]), None);
+    }
+
+    #[test]
+    fn step_stops_at_both_ends() {
+        let mut queue = queue(&["a.png", "b.png"]);
+        assert!(queue.step(-1, None).is_none());
+        assert_eq!(queue.current_path(), Some("a.png"));
+
+        let entry = queue.step(1, None).unwrap();
+        assert_eq!((entry.index, entry.total), (1, 2));
+        assert!(queue.step(1, None).is_none());
+        assert_eq!(queue.current_path(), Some("b.png"));
+    }
+
+    #[test]
+    fn select_out_of_range_keeps_position_and_snapshot() {
+        let mut queue = queue(&["a.png", "b.png"]);
+        assert!(queue.select(2, Some(json!({"annotations": []}))).is_none());
+        assert_eq!(queue.current_path(), Some("a.png"));
+        assert!(!queue.info().items[0].edited);
+    }
+
+    #[test]
+    fn select_stores_snapshot_of_previous_image() {
+        let mut queue = queue(&["a.png", "b.png"]);
+        let snapshot = json!({"annotations": [1]});
+        let entry = queue.select(1, Some(snapshot.clone())).unwrap();
+        assert_eq!(entry.path, "b.png");
+        assert!(entry.snapshot.is_none());
+
+        let entry = queue.select(0, None).unwrap();
+        assert_eq!(entry.snapshot, Some(snapshot));
+    }
+
+    #[test]
+    fn null_snapshot_clears_edited_state() {
+        let mut queue = queue(&["a.png"]);
+        queue.store_snapshot(Some(json!({"annotations": [1]})));
+        assert!(queue.info().items[0].edited);
+
+        queue.store_snapshot(Some(serde_json::Value::Null));
+        assert!(!queue.info().items[0].edited);
+        assert!(queue.entry().unwrap().snapshot.is_none());
+    }
+
+    #[test]
+    fn empty_queue_has_no_entry() {
+        let mut queue = ImageQueue::default();
+        assert!(queue.entry().is_none());
+        assert!(queue.step(0, None).is_none());
+        queue.store_snapshot(Some(json!({})));
+        assert!(queue.info().items.is_empty());
+    }
+}
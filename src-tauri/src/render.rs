@@ -0,0 +1,1566 @@
+// MarkPix - 图片标注工具
+// 无界面渲染：在 Rust 端按前端 Konva 的绘制规则把标注文档栅格化到图片上
+
+use crate::annotation::{
+    Annotation, AnnotationDocument, ArrowAnnotation, ArrowStyle, BaseAnnotation, BlurAnnotation,
+    BrushAnnotation, BubbleTailPosition, EllipseAnnotation, ImageAnnotation, LineAnnotation,
+    LineStyle, MagnifierAnnotation, MarkerAnnotation, MarkerStyle, RectAnnotation, TextAnnotation,
+    TextStyle,
+};
+use crate::color::{parse_css_color, Rgba};
+use crate::export::{self, ExportOptions};
+use ab_glyph::{Font, FontVec, GlyphId, OutlineCurve};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{ImageReader, RgbaImage};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::OnceLock;
+use tiny_skia::{
+    FillRule, FilterQuality, LineCap, LineJoin, Mask, Paint, PathBuilder, Pattern, Pixmap,
+    PixmapPaint, Shader, SpreadMode, Stroke, StrokeDash, Transform,
+};
+
+/// 前端文字默认内边距
+const DEFAULT_TEXT_PADDING: f32 = 4.0;
+/// 放大镜边框色（与前端一致）
+const MAGNIFIER_BORDER: Rgba = Rgba::rgb(0x3b, 0x82, 0xf6);
+/// 放大镜切线与源区域颜色
+const MAGNIFIER_SOURCE: Rgba = Rgba::rgb(0xef, 0x44, 0x44);
+/// 中文等字符缺字时依次尝试的字体
+const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK SC",
+    "Source Han Sans SC",
+    "WenQuanYi Micro Hei",
+    "Microsoft YaHei",
+    "PingFang SC",
+    "Noto Sans",
+    "DejaVu Sans",
+];
+
+/// 读取图片、应用标注文档并按导出参数写入输出文件
+pub fn render_file(
+    input: &Path,
+    annotations: &Path,
+    output: &Path,
+    options: &ExportOptions,
+) -> Result<(), String> {
+    let document = AnnotationDocument::load(annotations)?;
+    let background = load_image(input)?;
+    let rendered = render_document(&background, &document)?;
+    export::save_image(&rendered, output, options)
+}
+
+/// 读取图片文件（按内容识别格式）
+pub fn load_image(path: &Path) -> Result<RgbaImage, String> {
+    let image = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("读取文件失败: {} ({})", path.display(), e))?
+        .decode()
+        .map_err(|e| format!("解码图片失败: {} ({})", path.display(), e))?;
+    Ok(image.to_rgba8())
+}
+
+/// 将标注文档渲染到背景图上，返回应用裁剪蒙版后的结果
+pub fn render_document(
+    background: &RgbaImage,
+    document: &AnnotationDocument,
+) -> Result<RgbaImage, String> {
+    let base = rgba_to_pixmap(background)?;
+    let mut canvas = base.clone();
+    let mut renderer = Renderer::new(&base, document);
+
+    for annotation in &document.annotations {
+        if annotation.base().visible == Some(false) {
+            continue;
+        }
+        renderer.draw(&mut canvas, annotation)?;
+    }
+
+    let output = pixmap_to_rgba(&canvas);
+    match document.crop_mask {
+        Some(mask) => {
+            // 超出左上边界的部分同时从宽高中扣除，裁剪区域的右下角保持不变
+            let (left, top) = (mask.x.round(), mask.y.round());
+            let x = left.max(0.0) as u32;
+            let y = top.max(0.0) as u32;
+            let width = (mask.width.round() + left.min(0.0)) as u32;
+            let height = (mask.height.round() + top.min(0.0)) as u32;
+            let width = width.min(output.width().saturating_sub(x));
+            let height = height.min(output.height().saturating_sub(y));
+            if width == 0 || height == 0 {
+                return Err("裁剪区域超出图片范围".to_string());
+            }
+            Ok(image::imageops::crop_imm(&output, x, y, width, height).to_image())
+        }
+        None => Ok(output),
+    }
+}
+
+/// 渲染上下文：持有原始背景与已加载的字体、贴图
+struct Renderer<'a> {
+    background: &'a Pixmap,
+    document: &'a AnnotationDocument,
+    fonts: HashMap<(String, bool), Option<std::sync::Arc<FontVec>>>,
+    images: HashMap<String, std::sync::Arc<Pixmap>>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(background: &'a Pixmap, document: &'a AnnotationDocument) -> Self {
+        Self {
+            background,
+            document,
+            fonts: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    fn draw(&mut self, canvas: &mut Pixmap, annotation: &Annotation) -> Result<(), String> {
+        match annotation {
+            Annotation::Rectangle(a) => draw_rect(canvas, a),
+            Annotation::Ellipse(a) => draw_ellipse(canvas, a),
+            Annotation::Arrow(a) => draw_arrow(canvas, a),
+            Annotation::Line(a) => draw_line(canvas, a),
+            Annotation::Brush(a) => draw_brush(canvas, a),
+            Annotation::Text(a) => self.draw_text_annotation(canvas, a),
+            Annotation::Marker(a) => self.draw_marker(canvas, a),
+            Annotation::Blur(a) => self.draw_blur(canvas, a),
+            Annotation::Image(a) => self.draw_image(canvas, a),
+            Annotation::Magnifier(a) => self.draw_magnifier(canvas, a),
+        }
+    }
+
+    fn draw_text_annotation(
+        &mut self,
+        canvas: &mut Pixmap,
+        a: &TextAnnotation,
+    ) -> Result<(), String> {
+        let transform = base_transform(&a.base);
+        let padding = a
+            .padding
+            .filter(|p| *p > 0.0)
+            .unwrap_or(DEFAULT_TEXT_PADDING);
+        let text = if a.text.is_empty() {
+            "双击编辑"
+        } else {
+            a.text.as_str()
+        };
+        let color = parse_css_color(&a.fill)?;
+        // 设置 width 时文本框宽度固定（含内边距），超出的文字换行
+        let fixed_width = a.width.filter(|w| *w > 0.0);
+        let max_width = fixed_width.map(|w| (w - padding * 2.0).max(0.0));
+        let layout = self.layout_text(text, &a.font_family, false, a.font_size, max_width)?;
+        let width = fixed_width.unwrap_or(layout.width + padding * 2.0);
+        let height = layout.height + padding * 2.0;
+
+        if let Some(background) = a.background_color.as_deref().filter(|s| !s.is_empty()) {
+            let background = parse_css_color(background)?;
+            if let Some(rect) = tiny_skia::Rect::from_xywh(0.0, 0.0, width, height) {
+                fill_path(
+                    canvas,
+                    &PathBuilder::from_rect(rect),
+                    background,
+                    1.0,
+                    transform,
+                );
+            }
+        }
+
+        if a.text_style == TextStyle::Bubble {
+            let stroke = parse_css_color(
+                a.bubble_stroke
+                    .as_deref()
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or(&a.fill),
+            )?;
+            let fill = parse_css_color(a.bubble_fill.as_deref().unwrap_or("transparent"))?;
+            let tail = a.bubble_tail_position.unwrap_or_default();
+            if let Some(path) = bubble_path(width, height, tail) {
+                fill_path(canvas, &path, fill, 1.0, transform);
+                stroke_path(canvas, &path, stroke, 1.0, &plain_stroke(2.0), transform);
+            }
+        }
+
+        layout.draw(
+            canvas,
+            color,
+            transform.pre_translate(padding, padding),
+            false,
+        );
+        Ok(())
+    }
+
+    fn draw_marker(&mut self, canvas: &mut Pixmap, a: &MarkerAnnotation) -> Result<(), String> {
+        let transform = base_transform(&a.base);
+        let filled = a.marker_style == MarkerStyle::Filled;
+        let fill = parse_css_color(&a.fill)?;
+        let stroke = match a.stroke.as_deref().filter(|s| !s.is_empty()) {
+            Some(s) => parse_css_color(s)?,
+            None => fill,
+        };
+        let radius = a.size / 2.0;
+
+        if let Some(path) = PathBuilder::from_circle(0.0, 0.0, radius) {
+            if filled {
+                fill_path(canvas, &path, fill, 1.0, transform);
+            }
+            let width = a.stroke_width.unwrap_or(2.0);
+            stroke_path(canvas, &path, stroke, 1.0, &plain_stroke(width), transform);
+        }
+
+        let text_color = if filled {
+            parse_css_color(&a.text_color)?
+        } else {
+            fill
+        };
+        let layout = self.layout_text(
+            &a.value.to_string(),
+            "Arial, sans-serif",
+            true,
+            a.size * 0.5,
+            None,
+        )?;
+        let origin = transform.pre_translate(-layout.width / 2.0, -layout.height / 2.0);
+        layout.draw(canvas, text_color, origin, true);
+        Ok(())
+    }
+
+    fn draw_blur(&mut self, canvas: &mut Pixmap, a: &BlurAnnotation) -> Result<(), String> {
+        if a.width <= 0.0 || a.height <= 0.0 {
+            return Ok(());
+        }
+        let width = a.width.ceil() as u32;
+        let height = a.height.ceil() as u32;
+
+        // 与前端一致：在背景图 + 贴图的合成结果上取样，不包含其他标注
+        let mut composite = self.background.clone();
+        let images: Vec<ImageAnnotation> = self
+            .document
+            .annotations
+            .iter()
+            .filter_map(|annotation| match annotation {
+                Annotation::Image(image) => Some(image.clone()),
+                _ => None,
+            })
+            .collect();
+        for image in &images {
+            let pixmap = self.load_image_source(&image.src)?;
+            draw_pixmap_scaled(
+                &mut composite,
+                &pixmap,
+                image.width,
+                image.height,
+                Transform::from_translate(image.base.x, image.base.y),
+                None,
+            );
+        }
+
+        let mut region = Pixmap::new(width, height).ok_or("马赛克区域无效")?;
+        region.draw_pixmap(
+            -a.base.x.round() as i32,
+            -a.base.y.round() as i32,
+            composite.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+        let radius = if a.blur_radius > 0.0 {
+            a.blur_radius
+        } else {
+            10.0
+        };
+        apply_kuwahara_filter(&mut region, radius);
+
+        let transform = base_transform(&a.base);
+        let corner = if a.corner_radius > 0.0 {
+            a.corner_radius
+        } else {
+            10.0
+        };
+        let clip = rounded_rect_quad_path(a.width, a.height, corner).and_then(|path| {
+            let mut mask = Mask::new(canvas.width(), canvas.height())?;
+            mask.fill_path(&path, FillRule::Winding, true, transform);
+            Some(mask)
+        });
+        draw_pixmap_scaled(canvas, &region, a.width, a.height, transform, clip.as_ref());
+        Ok(())
+    }
+
+    fn draw_image(&mut self, canvas: &mut Pixmap, a: &ImageAnnotation) -> Result<(), String> {
+        let pixmap = self.load_image_source(&a.src)?;
+        draw_pixmap_scaled(
+            canvas,
+            &pixmap,
+            a.width,
+            a.height,
+            base_transform(&a.base),
+            None,
+        );
+        Ok(())
+    }
+
+    fn draw_magnifier(
+        &mut self,
+        canvas: &mut Pixmap,
+        a: &MagnifierAnnotation,
+    ) -> Result<(), String> {
+        let transform = base_transform(&a.base);
+        let target = a.target_radius;
+        let source = a.source_radius;
+        if target <= 0.0 || source <= 0.0 {
+            return Ok(());
+        }
+
+        // 大圆：从原始背景取样并放大
+        if let Some(circle) = PathBuilder::from_circle(0.0, 0.0, target) {
+            let mut mask = Mask::new(canvas.width(), canvas.height()).ok_or("画布尺寸无效")?;
+            mask.fill_path(&circle, FillRule::Winding, true, transform);
+            let zoom = target / source;
+            let sample = Transform::from_translate(-target, -target)
+                .pre_scale(zoom, zoom)
+                .pre_translate(-(a.source_x - source), -(a.source_y - source));
+            let paint = Paint {
+                shader: Pattern::new(
+                    self.background.as_ref(),
+                    SpreadMode::Pad,
+                    FilterQuality::Bicubic,
+                    1.0,
+                    sample,
+                ),
+                anti_alias: true,
+                ..Paint::default()
+            };
+            canvas.fill_path(&circle, &paint, FillRule::Winding, transform, Some(&mask));
+        }
+        if let Some(border) = PathBuilder::from_circle(0.0, 0.0, target - 1.5) {
+            stroke_path(
+                canvas,
+                &border,
+                MAGNIFIER_BORDER,
+                1.0,
+                &plain_stroke(3.0),
+                transform,
+            );
+        }
+
+        // 切线与源区域小圆
+        let rel_x = a.source_x - a.base.x;
+        let rel_y = a.source_y - a.base.y;
+        for line in tangent_lines(rel_x, rel_y, source, target) {
+            if let Some(path) = polyline_path(&line) {
+                stroke_path(
+                    canvas,
+                    &path,
+                    MAGNIFIER_SOURCE,
+                    1.0,
+                    &plain_stroke(1.5),
+                    transform,
+                );
+            }
+        }
+        if let Some(circle) = PathBuilder::from_circle(rel_x, rel_y, source) {
+            let fill = Rgba {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 77,
+            };
+            fill_path(canvas, &circle, fill, 1.0, transform);
+            stroke_path(
+                canvas,
+                &circle,
+                MAGNIFIER_SOURCE,
+                1.0,
+                &plain_stroke(2.0),
+                transform,
+            );
+        }
+        Ok(())
+    }
+
+    /// 加载贴图来源：data URL 或本地路径
+    fn load_image_source(&mut self, src: &str) -> Result<std::sync::Arc<Pixmap>, String> {
+        if let Some(pixmap) = self.images.get(src) {
+            return Ok(pixmap.clone());
+        }
+        let image = if src.starts_with("data:") {
+            let encoded = src.split(',').nth(1).ok_or("无效的图片数据格式")?;
+            let bytes = STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("Base64 解码失败: {}", e))?;
+            ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .map_err(|e| format!("读取贴图失败: {}", e))?
+                .decode()
+                .map_err(|e| format!("解码贴图失败: {}", e))?
+                .to_rgba8()
+        } else {
+            load_image(Path::new(src))?
+        };
+        let pixmap = std::sync::Arc::new(rgba_to_pixmap(&image)?);
+        self.images.insert(src.to_string(), pixmap.clone());
+        Ok(pixmap)
+    }
+
+    /// 按 CSS 字体族列表查找字体，缺失时使用通用无衬线字体
+    fn font(&mut self, family: &str, bold: bool) -> Option<std::sync::Arc<FontVec>> {
+        let key = (family.to_string(), bold);
+        if let Some(font) = self.fonts.get(&key) {
+            return font.clone();
+        }
+
+        let database = font_database();
+        let names: Vec<String> = family
+            .split(',')
+            .map(|name| name.trim().trim_matches(['"', '\'']).to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let mut families: Vec<fontdb::Family> = names
+            .iter()
+            .map(|name| match name.to_ascii_lowercase().as_str() {
+                "sans-serif" | "system-ui" => fontdb::Family::SansSerif,
+                "serif" => fontdb::Family::Serif,
+                "monospace" => fontdb::Family::Monospace,
+                "cursive" => fontdb::Family::Cursive,
+                "fantasy" => fontdb::Family::Fantasy,
+                _ => fontdb::Family::Name(name),
+            })
+            .collect();
+        families.push(fontdb::Family::SansSerif);
+
+        let query = fontdb::Query {
+            families: &families,
+            weight: if bold {
+                fontdb::Weight::BOLD
+            } else {
+                fontdb::Weight::NORMAL
+            },
+            ..fontdb::Query::default()
+        };
+        let font = database
+            .query(&query)
+            .or_else(|| database.faces().next().map(|face| face.id))
+            .and_then(|id| {
+                database.with_face_data(id, |data, index| {
+                    FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+                })
+            })
+            .flatten()
+            .map(std::sync::Arc::new);
+
+        self.fonts.insert(key, font.clone());
+        font
+    }
+
+    /// 排版多行文本（行高 = 字号，与 Konva 默认 lineHeight 1 一致）
+    /// 排版多行文本，指定 max_width 时超出宽度的行按字符换行
+    fn layout_text(
+        &mut self,
+        text: &str,
+        family: &str,
+        bold: bool,
+        font_size: f32,
+        max_width: Option<f32>,
+    ) -> Result<TextLayout, String> {
+        let primary = self
+            .font(family, bold)
+            .ok_or("未找到可用字体，无法渲染文字标注")?;
+        let fallbacks: Vec<_> = FALLBACK_FAMILIES
+            .iter()
+            .filter_map(|name| self.font(name, bold))
+            .collect();
+
+        let mut glyphs = Vec::new();
+        let mut width: f32 = 0.0;
+        let mut row = 0;
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                row += 1;
+            }
+            let mut pen_x = 0.0;
+            let mut previous: Option<(usize, GlyphId)> = None;
+            for ch in line.trim_end_matches('\r').chars() {
+                let (font_index, font) = std::iter::once(&primary)
+                    .chain(fallbacks.iter())
+                    .enumerate()
+                    .find(|(_, font)| font.glyph_id(ch).0 != 0)
+                    .unwrap_or((0, &primary));
+                let id = font.glyph_id(ch);
+                let scale = em_scale(font.as_ref(), font_size);
+                let advance = font.h_advance_unscaled(id) * scale;
+                // 每行至少保留一个字符，避免宽度过窄时无限换行
+                if max_width.is_some_and(|max| pen_x > 0.0 && pen_x + advance > max) {
+                    width = width.max(pen_x);
+                    row += 1;
+                    pen_x = 0.0;
+                    previous = None;
+                    if ch.is_whitespace() {
+                        continue;
+                    }
+                }
+                if let Some((prev_index, prev_id)) = previous {
+                    if prev_index == font_index {
+                        pen_x += font.kern_unscaled(prev_id, id) * scale;
+                    }
+                }
+                glyphs.push(PlacedGlyph {
+                    font: font.clone(),
+                    id,
+                    x: pen_x,
+                    row,
+                    scale,
+                });
+                pen_x += advance;
+                previous = Some((font_index, id));
+            }
+            width = width.max(pen_x);
+        }
+
+        let scale = em_scale(primary.as_ref(), font_size);
+        let baseline_offset = font_size / 2.0
+            + (primary.ascent_unscaled() + primary.descent_unscaled()) * scale / 2.0;
+        Ok(TextLayout {
+            glyphs,
+            width,
+            height: (row + 1) as f32 * font_size,
+            line_height: font_size,
+            baseline_offset,
+        })
+    }
+}
+
+/// 已排版的文本
+struct TextLayout {
+    glyphs: Vec<PlacedGlyph>,
+    width: f32,
+    height: f32,
+    line_height: f32,
+    baseline_offset: f32,
+}
+
+struct PlacedGlyph {
+    font: std::sync::Arc<FontVec>,
+    id: GlyphId,
+    x: f32,
+    row: usize,
+    scale: f32,
+}
+
+impl TextLayout {
+    /// 以 origin 为文本左上角绘制；center 为 true 时每行水平居中
+    fn draw(&self, canvas: &mut Pixmap, color: Rgba, origin: Transform, center: bool) {
+        let mut builder = PathBuilder::new();
+        let row_widths = self.row_widths();
+        for glyph in &self.glyphs {
+            let Some(outline) = glyph.font.outline(glyph.id) else {
+                continue;
+            };
+            let offset_x = if center {
+                (self.width - row_widths.get(glyph.row).copied().unwrap_or(0.0)) / 2.0
+            } else {
+                0.0
+            };
+            let baseline = glyph.row as f32 * self.line_height + self.baseline_offset;
+            let map = |p: ab_glyph::Point| {
+                (
+                    offset_x + glyph.x + p.x * glyph.scale,
+                    baseline - p.y * glyph.scale,
+                )
+            };
+
+            let mut last: Option<(f32, f32)> = None;
+            for curve in &outline.curves {
+                let (start, end) = match curve {
+                    OutlineCurve::Line(a, b) => (map(*a), map(*b)),
+                    OutlineCurve::Quad(a, _, c) => (map(*a), map(*c)),
+                    OutlineCurve::Cubic(a, _, _, d) => (map(*a), map(*d)),
+                };
+                if last != Some(start) {
+                    if last.is_some() {
+                        builder.close();
+                    }
+                    builder.move_to(start.0, start.1);
+                }
+                match curve {
+                    OutlineCurve::Line(_, _) => builder.line_to(end.0, end.1),
+                    OutlineCurve::Quad(_, b, _) => {
+                        let b = map(*b);
+                        builder.quad_to(b.0, b.1, end.0, end.1);
+                    }
+                    OutlineCurve::Cubic(_, b, c, _) => {
+                        let (b, c) = (map(*b), map(*c));
+                        builder.cubic_to(b.0, b.1, c.0, c.1, end.0, end.1);
+                    }
+                }
+                last = Some(end);
+            }
+            if last.is_some() {
+                builder.close();
+            }
+        }
+
+        if let Some(path) = builder.finish() {
+            let mut paint = solid_paint(color, 1.0);
+            paint.anti_alias = true;
+            canvas.fill_path(&path, &paint, FillRule::Winding, origin, None);
+        }
+    }
+
+    fn row_widths(&self) -> Vec<f32> {
+        let mut widths: Vec<f32> = Vec::new();
+        for glyph in &self.glyphs {
+            if widths.len() <= glyph.row {
+                widths.resize(glyph.row + 1, 0.0);
+            }
+            let advance = glyph.font.h_advance_unscaled(glyph.id) * glyph.scale;
+            widths[glyph.row] = widths[glyph.row].max(glyph.x + advance);
+        }
+        widths
+    }
+}
+
+/// 通用字体族的候选字体（按顺序取第一个已安装的）
+const GENERIC_FAMILIES: &[(fontdb::Family<'static>, &[&str])] = &[
+    (
+        fontdb::Family::SansSerif,
+        &[
+            "Arial",
+            "Helvetica",
+            "Noto Sans",
+            "DejaVu Sans",
+            "Liberation Sans",
+            "Microsoft YaHei",
+        ],
+    ),
+    (
+        fontdb::Family::Serif,
+        &[
+            "Times New Roman",
+            "Noto Serif",
+            "DejaVu Serif",
+            "Liberation Serif",
+        ],
+    ),
+    (
+        fontdb::Family::Monospace,
+        &[
+            "Courier New",
+            "Noto Sans Mono",
+            "DejaVu Sans Mono",
+            "Liberation Mono",
+        ],
+    ),
+];
+
+/// 系统字体库（首次使用时加载）
+fn font_database() -> &'static fontdb::Database {
+    static DATABASE: OnceLock<fontdb::Database> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+
+        // fontdb 的通用字体族默认指向固定名称，这里改为实际已安装的字体
+        for (generic, candidates) in GENERIC_FAMILIES {
+            let installed = candidates.iter().find(|name| {
+                database
+                    .faces()
+                    .any(|face| face.families.iter().any(|(family, _)| family == *name))
+            });
+            if let Some(name) = installed {
+                match generic {
+                    fontdb::Family::SansSerif => database.set_sans_serif_family(*name),
+                    fontdb::Family::Serif => database.set_serif_family(*name),
+                    fontdb::Family::Monospace => database.set_monospace_family(*name),
+                    _ => {}
+                }
+            }
+        }
+        database
+    })
+}
+
+/// 字号（CSS em）到字体单位的缩放系数
+fn em_scale(font: &FontVec, font_size: f32) -> f32 {
+    font_size / font.units_per_em().unwrap_or(1000.0)
+}
+
+fn draw_rect(canvas: &mut Pixmap, a: &RectAnnotation) -> Result<(), String> {
+    let transform = base_transform(&a.base);
+    let opacity = shape_opacity(a.fill_opacity);
+    let radius = a.corner_radius.unwrap_or(0.0);
+    if let Some(path) = rounded_rect_arc_path(a.width, a.height, radius) {
+        fill_path(canvas, &path, parse_css_color(&a.fill)?, opacity, transform);
+        stroke_path(
+            canvas,
+            &path,
+            parse_css_color(&a.stroke)?,
+            opacity,
+            &plain_stroke(a.stroke_width),
+            transform,
+        );
+    }
+    Ok(())
+}
+
+fn draw_ellipse(canvas: &mut Pixmap, a: &EllipseAnnotation) -> Result<(), String> {
+    let transform = base_transform(&a.base);
+    let opacity = shape_opacity(a.fill_opacity);
+    let rect = tiny_skia::Rect::from_ltrb(
+        -a.radius_x.abs(),
+        -a.radius_y.abs(),
+        a.radius_x.abs(),
+        a.radius_y.abs(),
+    );
+    if let Some(path) = rect.and_then(PathBuilder::from_oval) {
+        fill_path(canvas, &path, parse_css_color(&a.fill)?, opacity, transform);
+        stroke_path(
+            canvas,
+            &path,
+            parse_css_color(&a.stroke)?,
+            opacity,
+            &plain_stroke(a.stroke_width),
+            transform,
+        );
+    }
+    Ok(())
+}
+
+fn draw_arrow(canvas: &mut Pixmap, a: &ArrowAnnotation) -> Result<(), String> {
+    if a.points.len() < 4 {
+        return Ok(());
+    }
+    let transform = base_transform(&a.base);
+    let color = parse_css_color(&a.stroke)?;
+    let (x1, y1, x2, y2) = (a.points[0], a.points[1], a.points[2], a.points[3]);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 {
+        return Ok(());
+    }
+    let (ux, uy) = (dx / len, dy / len);
+    let (px, py) = (-uy, ux);
+
+    if a.arrow_style == ArrowStyle::Filled {
+        // QQ 风格：尾部 0 宽 + 大头 + 锥形过渡
+        let thickness = a.stroke_width;
+        let head_length = (len * 0.4).min(thickness * 3.0);
+        let head_width = (head_length * 0.5).max(thickness);
+        let body_width = (head_width * 0.35).max(thickness * 0.3);
+        let (bx, by) = (x2 - ux * head_length, y2 - uy * head_length);
+        let points = [
+            x1,
+            y1,
+            bx + px * body_width,
+            by + py * body_width,
+            bx + px * head_width,
+            by + py * head_width,
+            x2,
+            y2,
+            bx - px * head_width,
+            by - py * head_width,
+            bx - px * body_width,
+            by - py * body_width,
+            x1,
+            y1,
+        ];
+        if let Some(path) = polygon_path(&points) {
+            fill_path(canvas, &path, color, 1.0, transform);
+        }
+        return Ok(());
+    }
+
+    // 普通箭头：箭身 + 三角形箭头（箭头不使用虚线）
+    let pointer_length = a.pointer_length.filter(|v| *v > 0.0).unwrap_or(15.0);
+    let pointer_width = a.pointer_width.filter(|v| *v > 0.0).unwrap_or(12.0);
+    let shaft_end = (len - pointer_length).max(0.0);
+    let shaft = [x1, y1, x1 + ux * shaft_end, y1 + uy * shaft_end];
+    if let Some(path) = polyline_path(&shaft) {
+        let stroke = line_stroke(
+            a.stroke_width,
+            a.line_style,
+            LineCap::Round,
+            LineJoin::Round,
+        );
+        stroke_path(canvas, &path, color, 1.0, &stroke, transform);
+    }
+    let (bx, by) = (x2 - ux * pointer_length, y2 - uy * pointer_length);
+    let half = pointer_width / 2.0;
+    let head = [
+        x2,
+        y2,
+        bx + px * half,
+        by + py * half,
+        bx - px * half,
+        by - py * half,
+    ];
+    if let Some(path) = polygon_path(&head) {
+        fill_path(canvas, &path, color, 1.0, transform);
+        let stroke = line_stroke(
+            a.stroke_width,
+            LineStyle::Solid,
+            LineCap::Round,
+            LineJoin::Round,
+        );
+        stroke_path(canvas, &path, color, 1.0, &stroke, transform);
+    }
+    Ok(())
+}
+
+fn draw_line(canvas: &mut Pixmap, a: &LineAnnotation) -> Result<(), String> {
+    if let Some(path) = polyline_path(&a.points) {
+        let stroke = line_stroke(
+            a.stroke_width,
+            a.line_style,
+            LineCap::Round,
+            LineJoin::Round,
+        );
+        stroke_path(
+            canvas,
+            &path,
+            parse_css_color(&a.stroke)?,
+            1.0,
+            &stroke,
+            base_transform(&a.base),
+        );
+    }
+    Ok(())
+}
+
+fn draw_brush(canvas: &mut Pixmap, a: &BrushAnnotation) -> Result<(), String> {
+    let tension = a.tension.filter(|t| *t != 0.0).unwrap_or(0.5);
+    let cap = match a.line_cap.as_deref() {
+        Some("butt") => LineCap::Butt,
+        Some("square") => LineCap::Square,
+        _ => LineCap::Round,
+    };
+    let join = match a.line_join.as_deref() {
+        Some("miter") => LineJoin::Miter,
+        Some("bevel") => LineJoin::Bevel,
+        _ => LineJoin::Round,
+    };
+    let path = if a.points.len() > 4 {
+        spline_path(&a.points, tension)
+    } else if a.points.len() == 2 {
+        // 单击产生的点：画一个零长度线段，依靠圆头端点显示为圆点
+        polyline_path(&[a.points[0], a.points[1], a.points[0] + 0.01, a.points[1]])
+    } else {
+        polyline_path(&a.points)
+    };
+    if let Some(path) = path {
+        let stroke = line_stroke(a.stroke_width, LineStyle::Solid, cap, join);
+        stroke_path(
+            canvas,
+            &path,
+            parse_css_color(&a.stroke)?,
+            1.0,
+            &stroke,
+            base_transform(&a.base),
+        );
+    }
+    Ok(())
+}
+
+/// 标注的节点变换：平移 → 旋转 → 缩放（与 Konva 一致）
+fn base_transform(base: &BaseAnnotation) -> Transform {
+    Transform::from_translate(base.x, base.y)
+        .pre_rotate(base.rotation.unwrap_or(0.0))
+        .pre_scale(base.scale_x.unwrap_or(1.0), base.scale_y.unwrap_or(1.0))
+}
+
+/// 前端仅在 fillOpacity > 0 时设置整体透明度
+fn shape_opacity(fill_opacity: f32) -> f32 {
+    if fill_opacity > 0.0 {
+        fill_opacity.min(1.0)
+    } else {
+        1.0
+    }
+}
+
+fn solid_paint(color: Rgba, opacity: f32) -> Paint<'static> {
+    let alpha = (color.a as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+    Paint {
+        shader: Shader::SolidColor(tiny_skia::Color::from_rgba8(
+            color.r, color.g, color.b, alpha,
+        )),
+        anti_alias: true,
+        ..Paint::default()
+    }
+}
+
+fn fill_path(
+    canvas: &mut Pixmap,
+    path: &tiny_skia::Path,
+    color: Rgba,
+    opacity: f32,
+    transform: Transform,
+) {
+    if color.is_transparent() {
+        return;
+    }
+    canvas.fill_path(
+        path,
+        &solid_paint(color, opacity),
+        FillRule::Winding,
+        transform,
+        None,
+    );
+}
+
+fn stroke_path(
+    canvas: &mut Pixmap,
+    path: &tiny_skia::Path,
+    color: Rgba,
+    opacity: f32,
+    stroke: &Stroke,
+    transform: Transform,
+) {
+    if color.is_transparent() || stroke.width <= 0.0 {
+        return;
+    }
+    canvas.stroke_path(path, &solid_paint(color, opacity), stroke, transform, None);
+}
+
+fn plain_stroke(width: f32) -> Stroke {
+    Stroke {
+        width,
+        ..Stroke::default()
+    }
+}
+
+fn line_stroke(width: f32, style: LineStyle, cap: LineCap, join: LineJoin) -> Stroke {
+    Stroke {
+        width,
+        line_cap: cap,
+        line_join: join,
+        dash: match style {
+            LineStyle::Dashed => StrokeDash::new(vec![10.0, 5.0], 0.0),
+            LineStyle::Solid => None,
+        },
+        ..Stroke::default()
+    }
+}
+
+fn polyline_path(points: &[f32]) -> Option<tiny_skia::Path> {
+    if points.len() < 4 {
+        return None;
+    }
+    let mut builder = PathBuilder::new();
+    builder.move_to(points[0], points[1]);
+    for pair in points[2..].chunks_exact(2) {
+        builder.line_to(pair[0], pair[1]);
+    }
+    builder.finish()
+}
+
+fn polygon_path(points: &[f32]) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    let mut pairs = points.chunks_exact(2);
+    let first = pairs.next()?;
+    builder.move_to(first[0], first[1]);
+    for pair in pairs {
+        builder.line_to(pair[0], pair[1]);
+    }
+    builder.close();
+    builder.finish()
+}
+
+/// Konva 带张力折线：先计算控制点，再用二次/三次贝塞尔连接
+fn spline_path(points: &[f32], tension: f32) -> Option<tiny_skia::Path> {
+    let mut expanded = Vec::new();
+    let mut n = 2;
+    while n + 3 < points.len() {
+        let (x0, y0, x1, y1, x2, y2) = (
+            points[n - 2],
+            points[n - 1],
+            points[n],
+            points[n + 1],
+            points[n + 2],
+            points[n + 3],
+        );
+        let d01 = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+        let d12 = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        let total = d01 + d12;
+        if total > 0.0 {
+            let fa = tension * d01 / total;
+            let fb = tension * d12 / total;
+            expanded.extend_from_slice(&[
+                x1 - fa * (x2 - x0),
+                y1 - fa * (y2 - y0),
+                x1,
+                y1,
+                x1 + fb * (x2 - x0),
+                y1 + fb * (y2 - y0),
+            ]);
+        }
+        n += 2;
+    }
+    if expanded.len() < 6 {
+        return polyline_path(points);
+    }
+
+    let mut builder = PathBuilder::new();
+    builder.move_to(points[0], points[1]);
+    builder.quad_to(expanded[0], expanded[1], expanded[2], expanded[3]);
+    let mut i = 4;
+    while i + 5 < expanded.len() {
+        builder.cubic_to(
+            expanded[i],
+            expanded[i + 1],
+            expanded[i + 2],
+            expanded[i + 3],
+            expanded[i + 4],
+            expanded[i + 5],
+        );
+        i += 6;
+    }
+    let last = expanded.len();
+    let end = points.len();
+    builder.quad_to(
+        expanded[last - 2],
+        expanded[last - 1],
+        points[end - 2],
+        points[end - 1],
+    );
+    builder.finish()
+}
+
+/// 圆角矩形（圆弧角，对应 Konva Rect cornerRadius）
+fn rounded_rect_arc_path(width: f32, height: f32, radius: f32) -> Option<tiny_skia::Path> {
+    let (x0, x1) = (width.min(0.0), width.max(0.0));
+    let (y0, y1) = (height.min(0.0), height.max(0.0));
+    let r = radius.max(0.0).min((x1 - x0) / 2.0).min((y1 - y0) / 2.0);
+    if r <= 0.0 {
+        return tiny_skia::Rect::from_ltrb(x0, y0, x1, y1).map(PathBuilder::from_rect);
+    }
+    // 圆弧的三次贝塞尔近似系数
+    let k = r * 0.552_284_8;
+    let mut builder = PathBuilder::new();
+    builder.move_to(x0 + r, y0);
+    builder.line_to(x1 - r, y0);
+    builder.cubic_to(x1 - r + k, y0, x1, y0 + r - k, x1, y0 + r);
+    builder.line_to(x1, y1 - r);
+    builder.cubic_to(x1, y1 - r + k, x1 - r + k, y1, x1 - r, y1);
+    builder.line_to(x0 + r, y1);
+    builder.cubic_to(x0 + r - k, y1, x0, y1 - r + k, x0, y1 - r);
+    builder.line_to(x0, y0 + r);
+    builder.cubic_to(x0, y0 + r - k, x0 + r - k, y0, x0 + r, y0);
+    builder.close();
+    builder.finish()
+}
+
+/// 圆角矩形（二次曲线角，对应前端马赛克裁剪路径）
+fn rounded_rect_quad_path(width: f32, height: f32, radius: f32) -> Option<tiny_skia::Path> {
+    let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+    let mut builder = PathBuilder::new();
+    builder.move_to(r, 0.0);
+    builder.line_to(width - r, 0.0);
+    builder.quad_to(width, 0.0, width, r);
+    builder.line_to(width, height - r);
+    builder.quad_to(width, height, width - r, height);
+    builder.line_to(r, height);
+    builder.quad_to(0.0, height, 0.0, height - r);
+    builder.line_to(0.0, r);
+    builder.quad_to(0.0, 0.0, r, 0.0);
+    builder.close();
+    builder.finish()
+}
+
+/// 文字气泡外形（含尾巴）
+fn bubble_path(width: f32, height: f32, tail: BubbleTailPosition) -> Option<tiny_skia::Path> {
+    let (w, h) = (width, height);
+    let r = 10f32.min(w / 2.0).min(h / 2.0);
+    let tail_size = 10.0;
+    let tail_width = 8.0;
+    let k = r * 0.552_284_8;
+
+    let mut builder = PathBuilder::new();
+    builder.move_to(r, 0.0);
+    builder.line_to(w - r, 0.0);
+    builder.cubic_to(w - r + k, 0.0, w, r - k, w, r);
+    builder.line_to(w, h - r);
+    builder.cubic_to(w, h - r + k, w - r + k, h, w - r, h);
+    if tail == BubbleTailPosition::Right {
+        builder.line_to(w - tail_width, h);
+        builder.line_to(w - tail_width / 2.0, h + tail_size);
+        builder.line_to(w - tail_width * 2.0, h);
+    } else {
+        builder.line_to(tail_width * 2.0, h);
+        builder.line_to(tail_width / 2.0, h + tail_size);
+        builder.line_to(tail_width, h);
+    }
+    builder.line_to(r, h);
+    builder.cubic_to(r - k, h, 0.0, h - r + k, 0.0, h - r);
+    builder.line_to(0.0, r);
+    builder.cubic_to(0.0, r - k, r - k, 0.0, r, 0.0);
+    builder.close();
+    builder.finish()
+}
+
+/// 放大镜两圆的外切线（相对大圆圆心）
+fn tangent_lines(src_x: f32, src_y: f32, src_radius: f32, tgt_radius: f32) -> [[f32; 4]; 2] {
+    let d = (src_x * src_x + src_y * src_y).sqrt();
+    if d <= (tgt_radius - src_radius).abs() + 1.0 {
+        let line = [0.0, 0.0, src_x, src_y];
+        return [line, line];
+    }
+    let angle = src_y.atan2(src_x);
+    let alpha = ((tgt_radius - src_radius) / d).asin();
+    let angle1 = angle + std::f32::consts::FRAC_PI_2 - alpha;
+    let angle2 = angle - std::f32::consts::FRAC_PI_2 + alpha;
+    [
+        [
+            tgt_radius * angle1.cos(),
+            tgt_radius * angle1.sin(),
+            src_x + src_radius * angle1.cos(),
+            src_y + src_radius * angle1.sin(),
+        ],
+        [
+            tgt_radius * angle2.cos(),
+            tgt_radius * angle2.sin(),
+            src_x + src_radius * angle2.cos(),
+            src_y + src_radius * angle2.sin(),
+        ],
+    ]
+}
+
+/// 将图片拉伸绘制到 (0, 0, width, height) 区域
+fn draw_pixmap_scaled(
+    canvas: &mut Pixmap,
+    pixmap: &Pixmap,
+    width: f32,
+    height: f32,
+    transform: Transform,
+    mask: Option<&Mask>,
+) {
+    let Some(rect) = tiny_skia::Rect::from_xywh(0.0, 0.0, width, height) else {
+        return;
+    };
+    let paint = Paint {
+        shader: Pattern::new(
+            pixmap.as_ref(),
+            SpreadMode::Pad,
+            FilterQuality::Bilinear,
+            1.0,
+            Transform::from_scale(
+                width / pixmap.width() as f32,
+                height / pixmap.height() as f32,
+            ),
+        ),
+        anti_alias: true,
+        ..Paint::default()
+    };
+    canvas.fill_rect(rect, &paint, transform, mask);
+}
+
+/// Kuwahara 滤镜（积分图优化），与前端马赛克效果一致
+fn apply_kuwahara_filter(pixmap: &mut Pixmap, radius: f32) {
+    let width = pixmap.width() as usize;
+    let height = pixmap.height() as usize;
+    let r = (radius.floor() as i64).max(2);
+    let stride = width + 1;
+    let data = pixmap_to_rgba(pixmap).into_raw();
+
+    // 积分图 (R, G, B, R², G², B²)
+    let mut sat = vec![[0f64; 6]; stride * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            let idx = (y * width + x) * 4;
+            let (rv, gv, bv) = (data[idx] as f64, data[idx + 1] as f64, data[idx + 2] as f64);
+            let values = [rv, gv, bv, rv * rv, gv * gv, bv * bv];
+            let cur = (y + 1) * stride + x + 1;
+            let top = y * stride + x + 1;
+            let left = (y + 1) * stride + x;
+            let diag = y * stride + x;
+            for c in 0..6 {
+                sat[cur][c] = values[c] + sat[top][c] + sat[left][c] - sat[diag][c];
+            }
+        }
+    }
+    let sum = |c: usize, x1: usize, y1: usize, x2: usize, y2: usize| {
+        sat[(y2 + 1) * stride + x2 + 1][c]
+            - sat[y1 * stride + x2 + 1][c]
+            - sat[(y2 + 1) * stride + x1][c]
+            + sat[y1 * stride + x1][c]
+    };
+
+    let mut output = data.clone();
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let mut min_variance = f64::INFINITY;
+            let mut best = [0f64; 3];
+            for (x1, y1, x2, y2) in [
+                (x - r, y - r, x, y),
+                (x, y - r, x + r, y),
+                (x - r, y, x, y + r),
+                (x, y, x + r, y + r),
+            ] {
+                let x1 = x1.max(0) as usize;
+                let y1 = y1.max(0) as usize;
+                let x2 = x2.min(width as i64 - 1) as usize;
+                let y2 = y2.min(height as i64 - 1) as usize;
+                if x1 > x2 || y1 > y2 {
+                    continue;
+                }
+                let count = ((x2 - x1 + 1) * (y2 - y1 + 1)) as f64;
+                let mean = [
+                    sum(0, x1, y1, x2, y2) / count,
+                    sum(1, x1, y1, x2, y2) / count,
+                    sum(2, x1, y1, x2, y2) / count,
+                ];
+                let variance = (0..3)
+                    .map(|c| sum(c + 3, x1, y1, x2, y2) / count - mean[c] * mean[c])
+                    .sum::<f64>();
+                if variance < min_variance {
+                    min_variance = variance;
+                    best = mean;
+                }
+            }
+            let idx = (y as usize * width + x as usize) * 4;
+            output[idx] = best[0] as u8;
+            output[idx + 1] = best[1] as u8;
+            output[idx + 2] = best[2] as u8;
+        }
+    }
+
+    if let Some(image) = RgbaImage::from_raw(width as u32, height as u32, output) {
+        if let Ok(filtered) = rgba_to_pixmap(&image) {
+            *pixmap = filtered;
+        }
+    }
+}
+
+/// RGBA 图片转为预乘 Alpha 的 Pixmap
+fn rgba_to_pixmap(image: &RgbaImage) -> Result<Pixmap, String> {
+    let mut pixmap = Pixmap::new(image.width(), image.height()).ok_or("图片尺寸无效")?;
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(image.pixels()) {
+        let [r, g, b, a] = src.0;
+        *dst = tiny_skia::ColorU8::from_rgba(r, g, b, a).premultiply();
+    }
+    Ok(pixmap)
+}
+
+/// Pixmap 转回非预乘的 RGBA 图片
+fn pixmap_to_rgba(pixmap: &Pixmap) -> RgbaImage {
+    let mut data = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        data.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    RgbaImage::from_raw(pixmap.width(), pixmap.height(), data).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba as Pixel;
+
+    const WHITE: Pixel<u8> = Pixel([255, 255, 255, 255]);
+    const RED: Pixel<u8> = Pixel([255, 0, 0, 255]);
+    const GREEN: Pixel<u8> = Pixel([0, 255, 0, 255]);
+
+    fn solid_background(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, WHITE)
+    }
+
+    fn document(value: serde_json::Value) -> AnnotationDocument {
+        AnnotationDocument::from_json(&value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn keeps_background_size_without_crop() {
+        let background = solid_background(64, 48);
+        let output = render_document(&background, &AnnotationDocument::default()).unwrap();
+        assert_eq!(output.dimensions(), (64, 48));
+        assert_eq!(output, background);
+    }
+
+    #[test]
+    fn draws_rectangle_stroke_in_annotation_color() {
+        let background = solid_background(64, 48);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "rectangle", "id": "r", "x": 10, "y": 10,
+                "width": 40, "height": 30, "stroke": "#ff0000", "strokeWidth": 4,
+                "fill": "transparent", "fillOpacity": 0
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        // 描边以边框为中心，左边框覆盖 x = 8..12
+        assert_eq!(*output.get_pixel(10, 25), RED);
+        assert_eq!(*output.get_pixel(30, 10), RED);
+        // 透明填充，内部与外部保持背景色
+        assert_eq!(*output.get_pixel(30, 25), WHITE);
+        assert_eq!(*output.get_pixel(2, 2), WHITE);
+    }
+
+    #[test]
+    fn crop_mask_trims_output() {
+        let mut background = solid_background(64, 48);
+        background.put_pixel(10, 5, RED);
+        let doc = document(serde_json::json!({
+            "annotations": [],
+            "cropMask": { "x": 10, "y": 5, "width": 30, "height": 20 }
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(output.dimensions(), (30, 20));
+        assert_eq!(*output.get_pixel(0, 0), RED);
+        assert_eq!(*output.get_pixel(1, 1), WHITE);
+    }
+
+    #[test]
+    fn crop_mask_overhang_is_subtracted() {
+        let mut background = solid_background(200, 100);
+        background.put_pixel(89, 39, RED);
+        let doc = document(serde_json::json!({
+            "annotations": [],
+            "cropMask": { "x": -10, "y": -20, "width": 100, "height": 60 }
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(output.dimensions(), (90, 40));
+        assert_eq!(*output.get_pixel(89, 39), RED);
+    }
+
+    #[test]
+    fn crop_mask_outside_image_is_rejected() {
+        let background = solid_background(64, 48);
+        let doc = document(serde_json::json!({
+            "cropMask": { "x": 100, "y": 0, "width": 10, "height": 10 }
+        }));
+        assert!(render_document(&background, &doc).is_err());
+    }
+
+    #[test]
+    fn blur_changes_only_its_region() {
+        // 棋盘格区域经过马赛克后变为灰色，区域外保持不变
+        let mut background = solid_background(80, 80);
+        for y in 20..60 {
+            for x in 20..60 {
+                if (x / 2 + y / 2) % 2 == 0 {
+                    background.put_pixel(x, y, Pixel([0, 0, 0, 255]));
+                }
+            }
+        }
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "blur", "id": "b", "x": 20, "y": 20,
+                "width": 40, "height": 40, "blurRadius": 5, "cornerRadius": 0
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        let center = output.get_pixel(40, 40).0;
+        assert!(center[0] > 0 && center[0] < 255, "未模糊: {:?}", center);
+        assert_eq!(*output.get_pixel(5, 5), WHITE);
+        assert_eq!(*output.get_pixel(70, 70), WHITE);
+    }
+
+    #[test]
+    fn magnifier_samples_source_from_background() {
+        // 源区域中心为绿色色块，放大后的大圆中心应显示同一颜色
+        let mut background = solid_background(120, 100);
+        for y in 17..23 {
+            for x in 87..93 {
+                background.put_pixel(x, y, GREEN);
+            }
+        }
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "magnifier", "id": "m", "x": 50, "y": 55,
+                "sourceX": 90, "sourceY": 20, "sourceRadius": 10, "targetRadius": 40,
+                "scale": 4
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(*output.get_pixel(50, 55), GREEN);
+        // 放大 4 倍后色块半径约 12 像素，更外侧取样到白色背景
+        assert_eq!(*output.get_pixel(50, 27), WHITE);
+        // 大圆外保持背景
+        assert_eq!(*output.get_pixel(2, 98), WHITE);
+    }
+
+    /// 区域内满足条件的像素数
+    fn count_pixels(
+        image: &RgbaImage,
+        (x0, y0, x1, y1): (u32, u32, u32, u32),
+        predicate: impl Fn(&Pixel<u8>) -> bool,
+    ) -> usize {
+        (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .filter(|&(x, y)| predicate(image.get_pixel(x, y)))
+            .count()
+    }
+
+    fn is_reddish(pixel: &Pixel<u8>) -> bool {
+        pixel[0] > 200 && pixel[1] < 80 && pixel[2] < 80
+    }
+
+    /// 文字渲染依赖系统字体，没有字体的环境跳过
+    fn fonts_available() -> bool {
+        let available = font_database().faces().next().is_some();
+        if !available {
+            eprintln!("跳过：系统中没有可用字体");
+        }
+        available
+    }
+
+    #[test]
+    fn draws_ellipse_fill_and_stroke() {
+        let background = solid_background(80, 60);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "ellipse", "id": "e", "x": 40, "y": 30,
+                "radiusX": 20, "radiusY": 12, "stroke": "#ff0000", "strokeWidth": 4,
+                "fill": "#00ff00", "fillOpacity": 1
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(*output.get_pixel(40, 30), GREEN);
+        assert_eq!(*output.get_pixel(59, 30), RED);
+        assert_eq!(*output.get_pixel(40, 18), RED);
+        // 外接矩形的角落在椭圆之外
+        assert_eq!(*output.get_pixel(58, 14), WHITE);
+        assert_eq!(*output.get_pixel(5, 5), WHITE);
+    }
+
+    #[test]
+    fn draws_arrow_shaft_and_head() {
+        let background = solid_background(100, 80);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "arrow", "id": "a", "x": 0, "y": 0,
+                "points": [10, 40, 90, 40], "stroke": "#ff0000", "strokeWidth": 4
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(*output.get_pixel(30, 40), RED);
+        // 箭头三角形比箭身宽（默认宽 12，长 15）
+        assert_eq!(*output.get_pixel(80, 43), RED);
+        assert_eq!(*output.get_pixel(30, 43), WHITE);
+        assert_eq!(*output.get_pixel(96, 40), WHITE);
+        assert_eq!(*output.get_pixel(30, 30), WHITE);
+    }
+
+    #[test]
+    fn draws_filled_arrow_tapering_to_the_tail() {
+        let background = solid_background(100, 80);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "arrow", "id": "a", "x": 0, "y": 0, "arrowStyle": "filled",
+                "points": [10, 40, 90, 40], "stroke": "#ff0000", "strokeWidth": 6
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert_eq!(*output.get_pixel(80, 40), RED);
+        // 箭头最宽处半宽 9，箭身中段远窄于箭头
+        assert_eq!(*output.get_pixel(76, 45), RED);
+        assert_eq!(*output.get_pixel(50, 40), RED);
+        assert_eq!(*output.get_pixel(50, 45), WHITE);
+    }
+
+    #[test]
+    fn draws_filled_and_outlined_markers() {
+        if !fonts_available() {
+            return;
+        }
+        let background = solid_background(80, 80);
+        let marker = |style: &str| {
+            document(serde_json::json!({
+                "annotations": [{
+                    "type": "marker", "id": "m", "x": 40, "y": 40, "value": 1,
+                    "markerStyle": style, "size": 30, "fill": "#ff0000", "textColor": "#ffffff"
+                }]
+            }))
+        };
+        let near_white = |pixel: &Pixel<u8>| pixel.0.iter().all(|channel| *channel > 200);
+
+        let filled = render_document(&background, &marker("filled")).unwrap();
+        assert_eq!(*filled.get_pixel(30, 40), RED);
+        assert_eq!(*filled.get_pixel(5, 5), WHITE);
+        // 圆心附近的数字为文字颜色
+        assert!(count_pixels(&filled, (34, 34, 46, 46), near_white) > 0);
+
+        let outlined = render_document(&background, &marker("outlined")).unwrap();
+        assert_eq!(*outlined.get_pixel(25, 40), RED);
+        assert_eq!(*outlined.get_pixel(31, 40), WHITE);
+        // 空心样式的数字使用填充色
+        assert!(count_pixels(&outlined, (34, 34, 46, 46), is_reddish) > 0);
+    }
+
+    #[test]
+    fn draws_text_inside_its_padding_box() {
+        if !fonts_available() {
+            return;
+        }
+        let background = solid_background(200, 80);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "text", "id": "t", "x": 10, "y": 10, "text": "Hello",
+                "fontSize": 24, "fontFamily": "sans-serif", "fill": "#ff0000", "padding": 4
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert!(count_pixels(&output, (14, 14, 120, 50), is_reddish) > 20);
+        // 文字不会越过左上角的内边距
+        assert_eq!(count_pixels(&output, (0, 0, 200, 13), |p| *p != WHITE), 0);
+        assert_eq!(count_pixels(&output, (0, 0, 13, 80), |p| *p != WHITE), 0);
+    }
+
+    #[test]
+    fn draws_text_bubble_behind_text() {
+        if !fonts_available() {
+            return;
+        }
+        let background = solid_background(200, 100);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "text", "id": "t", "x": 20, "y": 10, "text": "Hi",
+                "fontSize": 24, "fontFamily": "sans-serif", "fill": "#ff0000",
+                "textStyle": "bubble", "bubbleFill": "#00ff00"
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        assert!(count_pixels(&output, (0, 0, 200, 100), |p| *p == GREEN) > 50);
+        assert!(count_pixels(&output, (0, 0, 200, 100), is_reddish) > 20);
+    }
+
+    #[test]
+    fn draws_text_background_behind_padding_box() {
+        if !fonts_available() {
+            return;
+        }
+        let background = solid_background(200, 80);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "text", "id": "t", "x": 10, "y": 10, "text": "Hi",
+                "fontSize": 24, "fontFamily": "sans-serif", "fill": "#ff0000",
+                "padding": 4, "backgroundColor": "#00ff00"
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        // 内边距区域只有背景色，文本框外保持原样
+        assert_eq!(*output.get_pixel(11, 11), GREEN);
+        assert_eq!(*output.get_pixel(11, 10 + 24 + 4 + 2), GREEN);
+        assert_eq!(*output.get_pixel(9, 9), WHITE);
+        assert!(count_pixels(&output, (14, 14, 120, 38), is_reddish) > 20);
+    }
+
+    #[test]
+    fn wraps_text_at_fixed_width() {
+        if !fonts_available() {
+            return;
+        }
+        let background = solid_background(200, 120);
+        let doc = document(serde_json::json!({
+            "annotations": [{
+                "type": "text", "id": "t", "x": 10, "y": 10, "text": "MMMM MMMM MMMM",
+                "fontSize": 20, "fontFamily": "sans-serif", "fill": "#ff0000",
+                "padding": 4, "width": 60, "backgroundColor": "#00ff00"
+            }]
+        }));
+        let output = render_document(&background, &doc).unwrap();
+        // 文本框宽 60：右侧没有文字也没有背景
+        assert_eq!(count_pixels(&output, (71, 0, 200, 120), |p| *p != WHITE), 0);
+        assert_eq!(*output.get_pixel(69, 11), GREEN);
+        // 换行后第二行有文字
+        assert!(count_pixels(&output, (14, 34, 70, 54), is_reddish) > 20);
+    }
+}
@@ -0,0 +1,475 @@
+// MarkPix - 图片标注工具
+// 各绘图工具的默认样式（与前端 defaultToolConfig 的默认值保持一致）
+
+use crate::color;
+use crate::tools::ToolType;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+/// 描边粗细范围：工具栏滑块为 1-20，但滚轮调节可到 50，保存的默认样式需要容纳后者；
+/// 预设与 --stroke-width 只对应滑块，见 presets::STROKE_WIDTH_RANGE
+const STROKE_WIDTH_RANGE: RangeInclusive<u32> = 1..=50;
+/// 圆角范围
+const CORNER_RADIUS_RANGE: RangeInclusive<u32> = 0..=50;
+/// 画笔粗细范围
+const BRUSH_SIZE_RANGE: RangeInclusive<u32> = 1..=50;
+/// 序号标记大小范围
+const MARKER_SIZE_RANGE: RangeInclusive<u32> = 20..=60;
+/// 模糊强度范围
+const BLUR_RADIUS_RANGE: RangeInclusive<u32> = 5..=30;
+/// 放大镜倍率范围
+const MAGNIFIER_SCALE_RANGE: RangeInclusive<f64> = 1.5..=5.0;
+/// 填充不透明度范围
+const OPACITY_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+
+const DEFAULT_STROKE_COLOR: &str = "#ef4444";
+const DEFAULT_STROKE_WIDTH: u32 = 3;
+
+/// 各工具的默认样式（文字工具见 text_tool_config）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolDefaults {
+    pub rectangle: RectangleDefaults,
+    pub ellipse: EllipseDefaults,
+    pub arrow: ArrowDefaults,
+    pub line: LineDefaults,
+    pub brush: BrushDefaults,
+    pub marker: MarkerDefaults,
+    pub blur: BlurDefaults,
+    pub magnifier: MagnifierDefaults,
+}
+
+/// 矩形
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RectangleDefaults {
+    pub stroke_color: String,
+    pub stroke_width: u32,
+    pub fill_color: String,
+    /// 填充不透明度 0-1
+    pub fill_opacity: f64,
+    pub corner_radius: u32,
+}
+
+impl Default for RectangleDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            stroke_width: DEFAULT_STROKE_WIDTH,
+            fill_color: "transparent".to_string(),
+            fill_opacity: 0.0,
+            corner_radius: 0,
+        }
+    }
+}
+
+/// 椭圆
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EllipseDefaults {
+    pub stroke_color: String,
+    pub stroke_width: u32,
+    pub fill_color: String,
+    /// 填充不透明度 0-1
+    pub fill_opacity: f64,
+}
+
+impl Default for EllipseDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            stroke_width: DEFAULT_STROKE_WIDTH,
+            fill_color: "transparent".to_string(),
+            fill_opacity: 0.0,
+        }
+    }
+}
+
+/// 箭头
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArrowDefaults {
+    pub stroke_color: String,
+    pub stroke_width: u32,
+    /// 线条样式：solid 或 dashed
+    pub line_style: String,
+    /// 箭头样式：normal 或 filled
+    pub arrow_style: String,
+}
+
+impl Default for ArrowDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            stroke_width: DEFAULT_STROKE_WIDTH,
+            line_style: "solid".to_string(),
+            arrow_style: "filled".to_string(),
+        }
+    }
+}
+
+/// 直线
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineDefaults {
+    pub stroke_color: String,
+    pub stroke_width: u32,
+    /// 线条样式：solid 或 dashed
+    pub line_style: String,
+}
+
+impl Default for LineDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            stroke_width: DEFAULT_STROKE_WIDTH,
+            line_style: "solid".to_string(),
+        }
+    }
+}
+
+/// 画笔
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrushDefaults {
+    pub stroke_color: String,
+    pub brush_size: u32,
+}
+
+impl Default for BrushDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            brush_size: 4,
+        }
+    }
+}
+
+/// 序号标记
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkerDefaults {
+    /// 标记颜色
+    pub stroke_color: String,
+    /// 标记样式：filled 或 outlined
+    pub marker_style: String,
+    /// 序号类型：number 或 letter
+    pub marker_type: String,
+    pub marker_size: u32,
+}
+
+impl Default for MarkerDefaults {
+    fn default() -> Self {
+        Self {
+            stroke_color: DEFAULT_STROKE_COLOR.to_string(),
+            marker_style: "filled".to_string(),
+            marker_type: "number".to_string(),
+            marker_size: 28,
+        }
+    }
+}
+
+/// 马赛克/模糊
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlurDefaults {
+    pub blur_radius: u32,
+    pub corner_radius: u32,
+}
+
+impl Default for BlurDefaults {
+    fn default() -> Self {
+        Self {
+            blur_radius: 10,
+            corner_radius: 10,
+        }
+    }
+}
+
+/// 放大镜
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MagnifierDefaults {
+    /// 放大倍率
+    pub magnifier_scale: f64,
+}
+
+impl Default for MagnifierDefaults {
+    fn default() -> Self {
+        Self {
+            magnifier_scale: 2.0,
+        }
+    }
+}
+
+impl ToolDefaults {
+    /// 工具的默认样式，未单独保存默认样式的工具（选择、文字、裁剪等）返回错误
+    pub fn get(&self, tool: ToolType) -> Result<serde_json::Value, String> {
+        let name = tool_name(tool)?;
+        let root = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        root.get(&name)
+            .cloned()
+            .ok_or_else(|| format!("{} 工具没有可保存的默认样式", name))
+    }
+
+    /// 用部分字段覆盖单个工具的默认样式，返回校验后的新配置
+    pub fn with_tool(&self, tool: ToolType, patch: serde_json::Value) -> Result<Self, String> {
+        let name = tool_name(tool)?;
+        let serde_json::Value::Object(fields) = patch else {
+            return Err(format!("tool_defaults.{} 的值必须是对象", name));
+        };
+        let mut root = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        let target = root
+            .get_mut(&name)
+            .and_then(|value| value.as_object_mut())
+            .ok_or_else(|| format!("{} 工具没有可保存的默认样式", name))?;
+        for (key, value) in fields {
+            if !target.contains_key(&key) {
+                return Err(format!("未知配置项: tool_defaults.{}.{}", name, key));
+            }
+            target.insert(key, value);
+        }
+        let defaults: Self = serde_json::from_value(root)
+            .map_err(|e| format!("tool_defaults.{} 的值无效: {}", name, e))?;
+        defaults.validate()?;
+        Ok(defaults)
+    }
+
+    /// 检查颜色、样式名与数值范围
+    pub fn validate(&self) -> Result<(), String> {
+        let rectangle = &self.rectangle;
+        check_color("rectangle.stroke_color", &rectangle.stroke_color)?;
+        check_range(
+            "rectangle.stroke_width",
+            rectangle.stroke_width,
+            &STROKE_WIDTH_RANGE,
+        )?;
+        check_color("rectangle.fill_color", &rectangle.fill_color)?;
+        check_range(
+            "rectangle.fill_opacity",
+            rectangle.fill_opacity,
+            &OPACITY_RANGE,
+        )?;
+        check_range(
+            "rectangle.corner_radius",
+            rectangle.corner_radius,
+            &CORNER_RADIUS_RANGE,
+        )?;
+
+        let ellipse = &self.ellipse;
+        check_color("ellipse.stroke_color", &ellipse.stroke_color)?;
+        check_range(
+            "ellipse.stroke_width",
+            ellipse.stroke_width,
+            &STROKE_WIDTH_RANGE,
+        )?;
+        check_color("ellipse.fill_color", &ellipse.fill_color)?;
+        check_range("ellipse.fill_opacity", ellipse.fill_opacity, &OPACITY_RANGE)?;
+
+        let arrow = &self.arrow;
+        check_color("arrow.stroke_color", &arrow.stroke_color)?;
+        check_range(
+            "arrow.stroke_width",
+            arrow.stroke_width,
+            &STROKE_WIDTH_RANGE,
+        )?;
+        check_choice("arrow.line_style", &arrow.line_style, &["solid", "dashed"])?;
+        check_choice(
+            "arrow.arrow_style",
+            &arrow.arrow_style,
+            &["normal", "filled"],
+        )?;
+
+        let line = &self.line;
+        check_color("line.stroke_color", &line.stroke_color)?;
+        check_range("line.stroke_width", line.stroke_width, &STROKE_WIDTH_RANGE)?;
+        check_choice("line.line_style", &line.line_style, &["solid", "dashed"])?;
+
+        check_color("brush.stroke_color", &self.brush.stroke_color)?;
+        check_range("brush.brush_size", self.brush.brush_size, &BRUSH_SIZE_RANGE)?;
+
+        let marker = &self.marker;
+        check_color("marker.stroke_color", &marker.stroke_color)?;
+        check_choice(
+            "marker.marker_style",
+            &marker.marker_style,
+            &["filled", "outlined"],
+        )?;
+        check_choice(
+            "marker.marker_type",
+            &marker.marker_type,
+            &["number", "letter"],
+        )?;
+        check_range("marker.marker_size", marker.marker_size, &MARKER_SIZE_RANGE)?;
+
+        check_range(
+            "blur.blur_radius",
+            self.blur.blur_radius,
+            &BLUR_RADIUS_RANGE,
+        )?;
+        check_range(
+            "blur.corner_radius",
+            self.blur.corner_radius,
+            &CORNER_RADIUS_RANGE,
+        )?;
+
+        check_range(
+            "magnifier.magnifier_scale",
+            self.magnifier.magnifier_scale,
+            &MAGNIFIER_SCALE_RANGE,
+        )?;
+        Ok(())
+    }
+}
+
+/// 工具在 tool_defaults 中的名称
+fn tool_name(tool: ToolType) -> Result<String, String> {
+    match serde_json::to_value(tool) {
+        Ok(serde_json::Value::String(name)) => Ok(name),
+        _ => Err(format!("无效的工具: {:?}", tool)),
+    }
+}
+
+fn check_color(key: &str, value: &str) -> Result<(), String> {
+    color::parse_css_color(value)
+        .map(|_| ())
+        .map_err(|e| format!("tool_defaults.{} {}", key, e))
+}
+
+fn check_choice(key: &str, value: &str, choices: &[&str]) -> Result<(), String> {
+    if choices.contains(&value) {
+        return Ok(());
+    }
+    Err(format!(
+        "tool_defaults.{} 无效: {}（可选 {}）",
+        key,
+        value,
+        choices.join(", ")
+    ))
+}
+
+fn check_range<T: PartialOrd + Display>(
+    key: &str,
+    value: T,
+    range: &RangeInclusive<T>,
+) -> Result<(), String> {
+    // NaN 与任何值比较都为 false，同样视为超出范围
+    if range.contains(&value) {
+        return Ok(());
+    }
+    Err(format!(
+        "tool_defaults.{} 超出范围 {}-{}: {}",
+        key,
+        range.start(),
+        range.end(),
+        value
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(ToolDefaults::default().validate().is_ok());
+    }
+
+    #[test]
+    fn with_tool_updates_only_given_fields() {
+        let defaults = ToolDefaults::default()
+            .with_tool(ToolType::Arrow, json!({"stroke_width": 8}))
+            .unwrap();
+        assert_eq!(defaults.arrow.stroke_width, 8);
+        assert_eq!(defaults.arrow.stroke_color, DEFAULT_STROKE_COLOR);
+        assert_eq!(defaults.rectangle, RectangleDefaults::default());
+        assert_eq!(
+            defaults.get(ToolType::Arrow).unwrap()["stroke_width"],
+            json!(8)
+        );
+    }
+
+    #[test]
+    fn with_tool_rejects_unknown_keys() {
+        let err = ToolDefaults::default()
+            .with_tool(ToolType::Line, json!({"arrow_style": "normal"}))
+            .unwrap_err();
+        assert_eq!(err, "未知配置项: tool_defaults.line.arrow_style");
+    }
+
+    #[test]
+    fn with_tool_rejects_non_object_patch() {
+        let err = ToolDefaults::default()
+            .with_tool(ToolType::Brush, json!(4))
+            .unwrap_err();
+        assert_eq!(err, "tool_defaults.brush 的值必须是对象");
+    }
+
+    #[test]
+    fn with_tool_rejects_wrong_types() {
+        let err = ToolDefaults::default()
+            .with_tool(ToolType::Brush, json!({"brush_size": "big"}))
+            .unwrap_err();
+        assert!(err.starts_with("tool_defaults.brush 的值无效"), "{}", err);
+    }
+
+    #[test]
+    fn tools_without_defaults_are_rejected() {
+        let defaults = ToolDefaults::default();
+        assert!(defaults.get(ToolType::Text).is_err());
+        assert!(defaults.with_tool(ToolType::Select, json!({})).is_err());
+    }
+
+    #[test]
+    fn validate_checks_ranges() {
+        let mut defaults = ToolDefaults::default();
+        defaults.line.stroke_width = 50;
+        assert!(defaults.validate().is_ok());
+        defaults.line.stroke_width = 51;
+        assert_eq!(
+            defaults.validate().unwrap_err(),
+            "tool_defaults.line.stroke_width 超出范围 1-50: 51"
+        );
+
+        let mut defaults = ToolDefaults::default();
+        defaults.marker.marker_size = 19;
+        assert!(defaults.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_nan() {
+        let mut defaults = ToolDefaults::default();
+        defaults.rectangle.fill_opacity = f64::NAN;
+        assert!(defaults.validate().is_err());
+
+        let mut defaults = ToolDefaults::default();
+        defaults.magnifier.magnifier_scale = f64::NAN;
+        assert!(defaults.validate().is_err());
+    }
+
+    #[test]
+    fn validate_checks_choices() {
+        let mut defaults = ToolDefaults::default();
+        defaults.arrow.line_style = "dotted".to_string();
+        assert_eq!(
+            defaults.validate().unwrap_err(),
+            "tool_defaults.arrow.line_style 无效: dotted（可选 solid, dashed）"
+        );
+    }
+
+    #[test]
+    fn validate_checks_colors() {
+        let mut defaults = ToolDefaults::default();
+        defaults.rectangle.fill_color = "rgba(0, 0, 0, 0.5)".to_string();
+        assert!(defaults.validate().is_ok());
+        defaults.marker.stroke_color = "not-a-color".to_string();
+        let err = defaults.validate().unwrap_err();
+        assert!(
+            err.starts_with("tool_defaults.marker.stroke_color"),
+            "{}",
+            err
+        );
+    }
+}
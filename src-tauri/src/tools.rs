@@ -0,0 +1,36 @@
+// MarkPix - 图片标注工具
+// 标注工具类型（与前端 ToolType 保持一致）
+
+use serde::{Deserialize, Serialize};
+
+/// 编辑器工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolType {
+    /// 选择工具
+    Select,
+    /// 平移画布
+    Pan,
+    /// 矩形
+    Rectangle,
+    /// 椭圆
+    Ellipse,
+    /// 箭头
+    Arrow,
+    /// 直线
+    Line,
+    /// 文字
+    Text,
+    /// 画笔
+    Brush,
+    /// 序号标记
+    Marker,
+    /// 马赛克/模糊
+    Blur,
+    /// 裁剪
+    Crop,
+    /// 图片贴图
+    Image,
+    /// 放大镜
+    Magnifier,
+}
@@ -0,0 +1,129 @@
+// MarkPix - 图片标注工具
+// 后台模式的托盘图标：打开剪贴板图片、最近打开的图片、退出
+
+use crate::instance::InstanceRequest;
+use crate::{clipboard, AppState};
+use tauri::menu::{Menu, MenuBuilder, MenuItem, SubmenuBuilder};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+/// 托盘图标 ID
+const TRAY_ID: &str = "main";
+/// 菜单项 ID
+const OPEN_CLIPBOARD_ID: &str = "open_clipboard";
+const QUIT_ID: &str = "quit";
+/// 最近打开的菜单项 ID 前缀，后接在 recent_images 中的位置
+const RECENT_PREFIX: &str = "recent:";
+
+/// 创建托盘图标
+pub fn create(app: &AppHandle) -> Result<(), String> {
+    let menu = build_menu(app, &[]).map_err(|e| format!("创建托盘菜单失败: {}", e))?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("MarkPix")
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id.as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder
+        .build(app)
+        .map(|_| ())
+        .map_err(|e| format!("创建托盘图标失败: {}", e))
+}
+
+/// 按最近打开的图片重建托盘菜单
+pub fn refresh(app: &AppHandle, recent: &[String]) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app, recent) {
+        Ok(menu) => {
+            if let Err(err) = tray.set_menu(Some(menu)) {
+                eprintln!("警告: 更新托盘菜单失败: {}", err);
+            }
+        }
+        Err(err) => eprintln!("警告: 更新托盘菜单失败: {}", err),
+    }
+}
+
+fn build_menu(app: &AppHandle, recent: &[String]) -> tauri::Result<Menu<Wry>> {
+    let mut recent_menu = SubmenuBuilder::new(app, "最近打开");
+    if recent.is_empty() {
+        let empty = MenuItem::with_id(app, "recent_empty", "（无）", false, None::<&str>)?;
+        recent_menu = recent_menu.item(&empty);
+    }
+    for (index, path) in recent.iter().enumerate() {
+        let item = MenuItem::with_id(
+            app,
+            format!("{}{}", RECENT_PREFIX, index),
+            path,
+            true,
+            None::<&str>,
+        )?;
+        recent_menu = recent_menu.item(&item);
+    }
+    let recent_menu = recent_menu.build()?;
+
+    MenuBuilder::new(app)
+        .text(OPEN_CLIPBOARD_ID, "打开剪贴板图片")
+        .item(&recent_menu)
+        .separator()
+        .text(QUIT_ID, "退出")
+        .build()
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    let image = match id {
+        OPEN_CLIPBOARD_ID => clipboard::save_image_to_temp()
+            .map(|path| path.to_string_lossy().to_string())
+            .map_err(|e| e.to_string()),
+        QUIT_ID => {
+            app.exit(0);
+            return;
+        }
+        _ => {
+            let Some(index) = id
+                .strip_prefix(RECENT_PREFIX)
+                .and_then(|index| index.parse::<usize>().ok())
+            else {
+                return;
+            };
+            let recent = app
+                .state::<AppState>()
+                .recent_images
+                .lock()
+                .unwrap()
+                .clone();
+            match recent.get(index) {
+                Some(path) if std::path::Path::new(path).is_file() => Ok(path.clone()),
+                Some(path) => Err(format!("文件不存在: {}", path)),
+                None => return,
+            }
+        }
+    };
+
+    let result = image.and_then(|image| {
+        crate::open_request(
+            app,
+            InstanceRequest {
+                images: vec![image],
+                ..Default::default()
+            },
+        )
+    });
+    if let Err(err) = result {
+        show_error(app, &format!("打开图片失败: {}", err));
+    }
+}
+
+/// 后台模式下窗口通常是隐藏的，错误除了打印到终端还以对话框提示
+fn show_error(app: &AppHandle, message: &str) {
+    eprintln!("错误: {}", message);
+    app.dialog()
+        .message(message)
+        .title("MarkPix")
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
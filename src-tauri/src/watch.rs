@@ -0,0 +1,288 @@
+// MarkPix - 图片标注工具
+// 监视目录：其他工具写入新截图后通知编辑器打开；监视配置文件的外部修改
+
+use crate::input;
+use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// 文件最后一次变化后需保持静止的时间，之后才视为写入完成
+const QUIET_PERIOD: Duration = Duration::from_millis(500);
+/// 检查待处理文件的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 正在运行的目录或文件监视（drop 后停止监视）
+pub struct FolderWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// 展开 `~/` 并解析为规范路径，目录不存在时返回错误
+pub fn resolve_dir(dir: &str) -> Result<PathBuf, String> {
+    let path = match dir.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(dir)),
+        None => PathBuf::from(dir),
+    };
+    if !path.is_dir() {
+        return Err(format!("监视目录不存在: {}", path.display()));
+    }
+    path.canonicalize()
+        .map_err(|e| format!("无法访问监视目录: {} ({})", path.display(), e))
+}
+
+/// 监视目录（不递归），新图片写入完成后调用 on_image
+///
+/// - 文件在 [`QUIET_PERIOD`] 内没有新的变化才视为写入完成，避免打开写了一半的文件
+/// - `is_ignored` 返回 true 的文件（如 markpix 自己刚保存的结果）不会触发
+/// - 隐藏文件与非图片文件（按内容识别）被忽略，同一文件只触发一次
+pub fn watch<I, F>(dirs: &[PathBuf], is_ignored: I, on_image: F) -> Result<FolderWatcher, String>
+where
+    I: Fn(&Path) -> bool + Send + 'static,
+    F: Fn(PathBuf) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        if let Ok(event) = result {
+            let _ = sender.send(event);
+        }
+    })
+    .map_err(|e| format!("创建目录监视失败: {}", e))?;
+    for dir in dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("监视目录失败: {} ({})", dir.display(), e))?;
+    }
+
+    std::thread::spawn(move || debounce(receiver, QUIET_PERIOD, is_ignored, on_image));
+    Ok(FolderWatcher { _watcher: watcher })
+}
+
+/// 监视单个文件（如配置文件），内容变化并静止 [`QUIET_PERIOD`] 后调用 on_change
+///
+/// 监视的是所在目录：编辑器保存时常写入临时文件后重命名覆盖，直接监视文件会在第一次保存后失效。
+pub fn watch_file<F>(path: &Path, on_change: F) -> Result<FolderWatcher, String>
+where
+    F: Fn() + Send + 'static,
+{
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("无效的文件路径: {}", path.display()))?
+        .to_os_string();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        let touches_file = event
+            .paths
+            .iter()
+            .any(|changed| changed.file_name() == Some(file_name.as_os_str()));
+        let is_change = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Data(_))
+                | EventKind::Modify(ModifyKind::Any)
+                | EventKind::Modify(ModifyKind::Name(_))
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+        if touches_file && is_change {
+            let _ = sender.send(());
+        }
+    })
+    .map_err(|e| format!("创建文件监视失败: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("监视目录失败: {} ({})", dir.display(), e))?;
+
+    std::thread::spawn(move || {
+        let mut changed: Option<Instant> = None;
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(()) => changed = Some(Instant::now()),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            if changed.is_some_and(|at| at.elapsed() >= QUIET_PERIOD) {
+                changed = None;
+                on_change();
+            }
+        }
+    });
+    Ok(FolderWatcher { _watcher: watcher })
+}
+
+/// 合并同一文件的连续事件，静止 quiet 后再检查并通知，事件通道关闭后返回
+fn debounce<I, F>(receiver: mpsc::Receiver<Event>, quiet: Duration, is_ignored: I, on_image: F)
+where
+    I: Fn(&Path) -> bool,
+    F: Fn(PathBuf),
+{
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    let mut opened: HashSet<PathBuf> = HashSet::new();
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Remove(_)) {
+                    for path in &event.paths {
+                        pending.remove(path);
+                    }
+                } else if let Some(path) = written_path(&event) {
+                    pending.insert(path, Instant::now());
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| now.duration_since(**changed) >= quiet)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            pending.remove(&path);
+            let path = path.canonicalize().unwrap_or(path);
+            if !path.is_file()
+                || opened.contains(&path)
+                || is_ignored(&path)
+                || input::detect_file_format(&path).is_err()
+            {
+                continue;
+            }
+            opened.insert(path.clone());
+            on_image(path);
+        }
+    }
+}
+
+/// 事件涉及的写入目标（新建、写入、关闭写入、移入），隐藏文件返回 None
+fn written_path(event: &Event) -> Option<PathBuf> {
+    let path = match event.kind {
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Data(_))
+        | EventKind::Modify(ModifyKind::Any)
+        | EventKind::Modify(ModifyKind::Name(RenameMode::To))
+        | EventKind::Access(AccessKind::Close(AccessMode::Write)) => event.paths.first(),
+        // 重命名同时报告新旧路径时，新路径在后
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event.paths.last(),
+        _ => None,
+    }?;
+    let hidden = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_none_or(|name| name.starts_with('.'));
+    (!hidden).then(|| path.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::CreateKind;
+    use std::cell::RefCell;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn event(kind: EventKind, paths: &[&Path]) -> Event {
+        paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(path.to_path_buf())
+        })
+    }
+
+    /// 依次送入事件后关闭通道，返回打开的图片
+    fn run(
+        events: Vec<Event>,
+        quiet: Duration,
+        is_ignored: impl Fn(&Path) -> bool,
+    ) -> Vec<PathBuf> {
+        let (sender, receiver) = mpsc::channel();
+        for event in events {
+            sender.send(event).unwrap();
+        }
+        drop(sender);
+        let opened = RefCell::new(Vec::new());
+        debounce(receiver, quiet, is_ignored, |path| {
+            opened.borrow_mut().push(path)
+        });
+        opened.into_inner()
+    }
+
+    #[test]
+    fn written_path_uses_new_name_of_rename() {
+        let rename = event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &[Path::new("/tmp/a.tmp"), Path::new("/tmp/a.png")],
+        );
+        assert_eq!(written_path(&rename), Some(PathBuf::from("/tmp/a.png")));
+
+        let from = event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+            &[Path::new("/tmp/a.tmp")],
+        );
+        assert_eq!(written_path(&from), None);
+    }
+
+    #[test]
+    fn written_path_skips_hidden_files() {
+        let create = EventKind::Create(CreateKind::File);
+        assert_eq!(
+            written_path(&event(create, &[Path::new("/tmp/.a.png")])),
+            None
+        );
+        let rename = event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &[Path::new("/tmp/a.png"), Path::new("/tmp/.a.png.swp")],
+        );
+        assert_eq!(written_path(&rename), None);
+    }
+
+    #[test]
+    fn debounce_opens_each_image_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("shot.png");
+        std::fs::write(&image, PNG_HEADER).unwrap();
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "not an image").unwrap();
+
+        let create = EventKind::Create(CreateKind::File);
+        let events = vec![
+            event(create, &[&image]),
+            event(create, &[&text]),
+            event(EventKind::Modify(ModifyKind::Any), &[&image]),
+        ];
+        let opened = run(events, Duration::ZERO, |_| false);
+        assert_eq!(opened, vec![image.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn debounce_waits_for_quiet_period() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("shot.png");
+        std::fs::write(&image, PNG_HEADER).unwrap();
+
+        let events = vec![event(EventKind::Create(CreateKind::File), &[&image])];
+        assert!(run(events, Duration::from_secs(60), |_| false).is_empty());
+    }
+
+    #[test]
+    fn debounce_respects_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let saved = dir.path().join("saved.png");
+        std::fs::write(&saved, PNG_HEADER).unwrap();
+        let canonical = saved.canonicalize().unwrap();
+
+        let events = vec![event(EventKind::Create(CreateKind::File), &[&saved])];
+        let opened = run(events, Duration::ZERO, |path| path == canonical);
+        assert!(opened.is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 // 自定义动作面板
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useEditorStore } from "@/store/editorStore";
 import { Button } from "@/components/ui/Button";
@@ -14,6 +14,9 @@ import {
 interface CustomActionsPanelProps {
   getCanvasDataUrl: () => Promise<string | null>;
   imagePath?: string | null;
+  /** 通过快捷键触发、等待执行的动作序号 */
+  pendingActionIndex?: number | null;
+  onPendingActionStarted?: () => void;
 }
 
 // 图标映射
@@ -23,7 +26,12 @@ const iconMap: Record<string, React.ReactNode> = {
   terminal: <Terminal size={16} />,
 };
 
-export function CustomActionsPanel({ getCanvasDataUrl, imagePath }: CustomActionsPanelProps) {
+export function CustomActionsPanel({
+  getCanvasDataUrl,
+  imagePath,
+  pendingActionIndex = null,
+  onPendingActionStarted,
+}: CustomActionsPanelProps) {
   const { customActions } = useEditorStore();
   const [executingIndex, setExecutingIndex] = useState<number | null>(null);
   const [result, setResult] = useState<string | null>(null);
@@ -60,6 +68,13 @@ export function CustomActionsPanel({ getCanvasDataUrl, imagePath }: CustomAction
     }
   };
 
+  // 执行快捷键触发的动作（正在执行其他动作时等待其完成）
+  useEffect(() => {
+    if (pendingActionIndex === null || executingIndex !== null) return;
+    onPendingActionStarted?.();
+    handleExecute(pendingActionIndex);
+  }, [pendingActionIndex, executingIndex]);
+
   if (customActions.length === 0) return null;
 
   return (
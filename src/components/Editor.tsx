@@ -1,6 +1,7 @@
 // 编辑器主组件
 import { useRef, useEffect, useCallback, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { readImage } from "@tauri-apps/plugin-clipboard-manager";
 import { getCurrentWindow, LogicalSize } from "@tauri-apps/api/window";
@@ -8,19 +9,28 @@ import { useEditorStore } from "@/store/editorStore";
 import { AnnotationCanvas } from "./canvas/AnnotationCanvas";
 import { Toolbar, FloatingToolConfig } from "./toolbar/Toolbar";
 import { CustomActionsPanel } from "./CustomActionsPanel";
+import { actionForEvent, CUSTOM_ACTION_PREFIX, TOOL_ACTIONS, withShortcut } from "@/lib/keybindings";
 import { SettingsDialog } from "./SettingsDialog";
 import { ColorPicker } from "@/components/ui/ColorPicker";
 import { Select } from "@/components/ui/Select";
 import type {
   Annotation,
   CropArea,
+  ConfigChanged,
   CustomAction,
+  ExitBehavior,
+  FolderImage,
   ImageInfo,
+  InstanceOpen,
+  QueueEntry,
+  QueueInfo,
+  QueueSnapshot,
+  StartupOptions,
   WhiteboardConfig,
   WhiteboardTexture,
 } from "@/types";
 import Konva from "konva";
-import { Keyboard, Mouse, Zap, FolderOpen } from "lucide-react";
+import { Keyboard, Mouse, Zap, FolderOpen, ChevronLeft, ChevronRight } from "lucide-react";
 
 // 工具栏高度和边距常量
 const TOOLBAR_HEIGHT = 48;
@@ -30,6 +40,28 @@ const MIN_WINDOW_HEIGHT = 500;
 const MAX_WINDOW_WIDTH = 1920;
 const MAX_WINDOW_HEIGHT = 1080;
 
+// 空白页帮助中展示的快捷键（按键来自解析后的快捷键配置）
+const HELP_TOOL_SHORTCUTS: [string, string][] = [
+  ["select", "选择工具"],
+  ["pan", "平移画布"],
+  ["rectangle", "矩形"],
+  ["ellipse", "椭圆"],
+  ["arrow", "箭头"],
+  ["line", "直线"],
+  ["text", "文字"],
+  ["brush", "画笔"],
+  ["marker", "序号标记"],
+  ["blur", "马赛克"],
+  ["crop", "裁剪"],
+];
+const HELP_COMMAND_SHORTCUTS: [string, string][] = [
+  ["undo", "撤销"],
+  ["redo", "重做"],
+  ["save", "保存"],
+  ["copy", "复制"],
+  ["delete", "删除选中"],
+];
+
 interface AnnotationBounds {
   minX: number;
   minY: number;
@@ -193,6 +225,11 @@ function shiftAnnotationAfterDirectCrop(annotation: Annotation, crop: CropArea):
   };
 }
 
+// 应用 CLI 指定的启动工具与样式（仅本次会话生效，不写回配置）
+function applyStartupOptions(startup: StartupOptions) {
+  useEditorStore.getState().applyStylePreset(startup);
+}
+
 export function Editor() {
   const containerRef = useRef<HTMLDivElement>(null);
   const mousePositionRef = useRef<{ x: number; y: number } | null>(null);
@@ -208,14 +245,18 @@ export function Editor() {
   );
   const [toast, setToast] = useState<{ message: string; type: "success" | "error" } | null>(null);
   const [cliOutputPattern, setCliOutputPattern] = useState<string | null>(null);
+  const [exitBehavior, setExitBehavior] = useState<ExitBehavior | null>(null);
+  const [queuePosition, setQueuePosition] = useState<{ index: number; total: number } | null>(null);
   const [isDragging, setIsDragging] = useState(false);
   const [isFullscreenMode, setIsFullscreenMode] = useState(false);
+  const [showCustomActions, setShowCustomActions] = useState(false);
+  // 快捷键触发的自定义动作序号（由自定义动作面板执行并显示结果）
+  const [pendingCustomAction, setPendingCustomAction] = useState<number | null>(null);
 
   const {
     image,
     setImage,
     setCustomActions,
-    currentTool,
     setCurrentTool,
     undo,
     redo,
@@ -232,6 +273,8 @@ export function Editor() {
     setLastCopiedSnapshot,
     hasChangedSinceCopy,
     setWhiteboardConfig,
+    keybindings,
+    customActions,
   } = useEditorStore();
 
   // 监听容器大小变化
@@ -371,6 +414,18 @@ export function Editor() {
         if (cliPattern) {
           setCliOutputPattern(cliPattern);
         }
+
+        // 获取退出行为（early-exit / copy-on-exit / save-on-exit）
+        setExitBehavior(await invoke<ExitBehavior>("get_exit_behavior"));
+
+        // 应用 CLI 指定的启动工具与样式
+        applyStartupOptions(await invoke<StartupOptions>("get_startup_options"));
+
+        // 命令行传入多张图片时显示队列导航
+        const queue = await invoke<QueueInfo>("list_queue");
+        if (queue.items.length > 1) {
+          setQueuePosition({ index: queue.current, total: queue.items.length });
+        }
       } catch (error) {
         console.error("初始化失败:", error);
       }
@@ -405,8 +460,8 @@ export function Editor() {
     }
   }, []);
 
-  // 从路径加载图片
-  const loadImageFromPath = async (path: string) => {
+  // 从路径加载图片，snapshot 用于恢复队列中该图片之前的标注
+  const loadImageFromPath = useCallback(async (path: string, snapshot?: QueueSnapshot | null) => {
     try {
       const dataUrl = await invoke<string>("read_image_file", { path });
       const img = new Image();
@@ -419,6 +474,13 @@ export function Editor() {
           path: path,
         };
         setImage(imageInfo);
+        if (snapshot) {
+          useEditorStore.setState({
+            annotations: snapshot.annotations,
+            cropMask: snapshot.cropMask,
+            markerCounter: snapshot.markerCounter,
+          });
+        }
         
         // 自动调整窗口大小
         await adjustWindowSize(img.width, img.height);
@@ -428,7 +490,74 @@ export function Editor() {
       console.error("加载图片失败:", error);
       alert(`加载图片失败: ${error}`);
     }
-  };
+  }, [setImage, adjustWindowSize]);
+
+  // 切换队列中的图片：保存当前标注快照，加载目标图片及其标注
+  const navigateQueue = useCallback(async (
+    command: "next_image" | "previous_image" | "select_image",
+    args: { index?: number } = {},
+  ) => {
+    const state = useEditorStore.getState();
+    const snapshot: QueueSnapshot | null =
+      state.annotations.length > 0 || state.cropMask
+        ? {
+            annotations: state.annotations,
+            cropMask: state.cropMask,
+            markerCounter: state.markerCounter,
+          }
+        : null;
+    try {
+      const entry = await invoke<QueueEntry | null>(command, { ...args, snapshot });
+      if (!entry) return;
+      state.setCropMask(null);
+      state.resetMarkerCounter();
+      setQueuePosition({ index: entry.index, total: entry.total });
+      await loadImageFromPath(entry.path, entry.snapshot);
+    } catch (error) {
+      console.error("切换图片失败:", error);
+    }
+  }, [loadImageFromPath]);
+
+  // 单实例模式：后续启动转交的图片加入队列后切换过去，并应用其输出模式与启动样式
+  useEffect(() => {
+    const unlisten = listen<InstanceOpen>("instance-open", async ({ payload }) => {
+      // -o 只对本次请求生效
+      setCliOutputPattern(payload.output_pattern);
+      applyStartupOptions(payload.startup);
+      if (payload.index !== null) {
+        await navigateQueue("select_image", { index: payload.index });
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(console.error);
+    };
+  }, [navigateQueue]);
+
+  // 监视目录（--watch / watch_dirs）出现新图片时切换过去，当前标注保留在队列中
+  useEffect(() => {
+    const unlisten = listen<FolderImage>("folder-image", async ({ payload }) => {
+      await navigateQueue("select_image", { index: payload.index });
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(console.error);
+    };
+  }, [navigateQueue]);
+
+  // 后台模式（--daemon）关闭窗口时后端已清空队列，这里清空画布等待下一张图片
+  useEffect(() => {
+    const unlisten = listen("session-reset", () => {
+      const state = useEditorStore.getState();
+      state.setImage(null);
+      state.setCropMask(null);
+      state.resetMarkerCounter();
+      state.setCurrentTool("select");
+      setQueuePosition(null);
+      setCliOutputPattern(null);
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(console.error);
+    };
+  }, []);
 
   // 打开文件
   const handleOpenFile = async () => {
@@ -565,24 +694,28 @@ export function Editor() {
         return;
       }
 
-      // 生成默认文件名，根据配置的模式
-      const baseName = image.name?.replace(/\.[^.]+$/, "") || "image";
-      const now = new Date();
-      const pad = (n: number) => n.toString().padStart(2, "0");
-      const timestamp = `${now.getFullYear()}_${pad(now.getMonth() + 1)}_${pad(now.getDate())}-${pad(now.getHours())}-${pad(now.getMinutes())}-${pad(now.getSeconds())}`;
+      // 管道模式（-o -）：写入 stdout 并由后端退出
+      if (cliOutputPattern === "-") {
+        await invoke("write_image_to_stdout", { data: dataUrl });
+        return;
+      }
 
-      // CLI 参数优先于配置
-      let defaultName = cliOutputPattern || outputPattern || "{input_file_base}_{YYYY_MM_DD-hh-mm-ss}_markpix.png";
-      defaultName = defaultName
-        .replace(/{input_file_base}/g, baseName)
-        .replace(/{input_file}/g, image.path || image.name || "")
-        .replace(/{YYYY_MM_DD-hh-mm-ss}/g, timestamp);
+      // 由后端按输出模式生成默认文件名（CLI 参数优先于配置）
+      const defaultName = await invoke<string>("expand_output_pattern", {
+        pattern: outputPattern || null,
+        data: dataUrl,
+        inputPath: image.path ?? image.name ?? null,
+      });
 
       const filePath = await save({
         defaultPath: defaultName,
         filters: [
           { name: "PNG", extensions: ["png"] },
           { name: "JPEG", extensions: ["jpg", "jpeg"] },
+          { name: "WebP", extensions: ["webp"] },
+          { name: "AVIF", extensions: ["avif"] },
+          { name: "BMP", extensions: ["bmp"] },
+          { name: "QOI", extensions: ["qoi"] },
         ],
       });
 
@@ -655,6 +788,19 @@ export function Editor() {
     setTimeout(() => setToast(null), 2000);
   }, []);
 
+  // 配置文件在外部被修改（后端已校验并更新），重新读取主题、自定义动作等
+  useEffect(() => {
+    const unlisten = listen<ConfigChanged>("config-changed", async ({ payload }) => {
+      await useEditorStore.getState().loadConfig();
+      // early_exit / copy_on_exit / save_on_exit 也可能随配置变化
+      setExitBehavior(await invoke<ExitBehavior>("get_exit_behavior"));
+      showToast(`配置已更新: ${payload.sections.join(", ")}`);
+    });
+    return () => {
+      unlisten.then((fn) => fn()).catch(console.error);
+    };
+  }, [showToast]);
+
   const handleOpenWhiteboardDialog = useCallback(() => {
     if (image) return;
     const currentConfig = useEditorStore.getState().whiteboardConfig;
@@ -790,8 +936,24 @@ export function Editor() {
     }
   };
 
+  // 按退出行为由后端复制/保存最终图片后退出，失败时保留窗口并提示
+  const finishAndExit = useCallback(async () => {
+    try {
+      const dataUrl = await getCanvasDataUrl();
+      await invoke("finish_and_exit", { data: dataUrl, inputPath: image?.path ?? null });
+    } catch (error) {
+      console.error("退出处理失败:", error);
+      showToast(`退出处理失败: ${error}`, "error");
+    }
+  }, [getCanvasDataUrl, image, showToast]);
+
   // 关闭窗口处理
   const handleClose = useCallback(async () => {
+    // 配置了退出时复制或保存：无需确认，直接处理结果
+    if (image && (exitBehavior?.copy_on_exit || exitBehavior?.save_on_exit)) {
+      await finishAndExit();
+      return;
+    }
     // 如果有图片且复制后有改动，显示确认对话框
     if (image && hasChangedSinceCopy()) {
       setShowCloseConfirm(true);
@@ -799,7 +961,7 @@ export function Editor() {
       // 没有图片或复制后无改动，直接关闭
       await invoke("exit_app");
     }
-  }, [image, hasChangedSinceCopy]);
+  }, [image, hasChangedSinceCopy, exitBehavior, finishAndExit]);
 
   // 确认关闭（不保存）
   const handleConfirmClose = useCallback(async () => {
@@ -1127,33 +1289,49 @@ export function Editor() {
                         target.tagName === "TEXTAREA" ||
                         target.isContentEditable;
 
-      // Ctrl+V 粘贴（在输入框外才触发自定义粘贴）
-      if (e.ctrlKey && e.key === "v" && !isInInput) {
+      const action = actionForEvent(keybindings, e);
+
+      // 粘贴（在输入框外才触发自定义粘贴）
+      if (action === "paste" && !isInInput) {
         handlePaste();
         return;
       }
 
-      // Ctrl+Z 撤销（在输入框外）
-      if (e.ctrlKey && e.key === "z" && !isInInput) {
+      // 撤销（在输入框外）
+      if (action === "undo" && !isInInput) {
         e.preventDefault();
         undo();
         return;
       }
 
-      // Ctrl+Y 重做（在输入框外）
-      if (e.ctrlKey && e.key === "y" && !isInInput) {
+      // 重做（在输入框外）
+      if (action === "redo" && !isInInput) {
         e.preventDefault();
         redo();
         return;
       }
 
-      // Ctrl+S 保存
-      if (e.ctrlKey && e.key === "s") {
+      // 保存
+      if (action === "save") {
         e.preventDefault();
         handleSave();
         return;
       }
 
+      // 管道模式下 Enter 完成标注并输出到 stdout
+      if (e.key === "Enter" && cliOutputPattern === "-" && !isInInput) {
+        e.preventDefault();
+        handleSave();
+        return;
+      }
+
+      // 切换队列中的图片
+      if ((action === "next_image" || action === "previous_image") && queuePosition && !isInInput) {
+        e.preventDefault();
+        navigateQueue(action === "next_image" ? "next_image" : "previous_image");
+        return;
+      }
+
       // F12 打开开发者工具
       if (e.key === "F12") {
         e.preventDefault();
@@ -1165,40 +1343,37 @@ export function Editor() {
         return;
       }
 
-      // Ctrl+C 复制（在输入框外）
-      if (e.ctrlKey && e.key === "c" && image && !isInInput) {
+      // 复制（在输入框外）
+      if (action === "copy" && image && !isInInput) {
         e.preventDefault();
         handleCopy();
         return;
       }
 
-      // Delete 或 Backspace 删除选中（在输入框外）
-      if ((e.key === "Delete" || e.key === "Backspace") && selectedIds.length > 0 && !isInInput) {
+      // 删除选中（在输入框外）
+      if (action === "delete" && selectedIds.length > 0 && !isInInput) {
         e.preventDefault();
         selectedIds.forEach((id) => deleteAnnotation(id));
         return;
       }
 
-      // 工具快捷键（在输入框外才生效）
+      // 工具与自定义动作快捷键（在输入框外才生效）
       if (isInInput) return;
-      
-      const toolKeys: Record<string, typeof currentTool> = {
-        v: "select",
-        h: "pan",
-        r: "rectangle",
-        e: "ellipse",
-        a: "arrow",
-        l: "line",
-        t: "text",
-        b: "brush",
-        m: "marker",
-        u: "blur",
-        c: "crop",
-        z: "magnifier",
-      };
 
-      if (!e.ctrlKey && !e.altKey && toolKeys[e.key.toLowerCase()]) {
-        setCurrentTool(toolKeys[e.key.toLowerCase()]);
+      if (action?.startsWith(CUSTOM_ACTION_PREFIX) && image) {
+        const name = action.slice(CUSTOM_ACTION_PREFIX.length);
+        const index = customActions.findIndex((a) => a.name === name);
+        if (index >= 0) {
+          e.preventDefault();
+          setShowCustomActions(true);
+          setPendingCustomAction(index);
+        }
+        return;
+      }
+
+      const tool = TOOL_ACTIONS.find((t) => t === action);
+      if (tool) {
+        setCurrentTool(tool);
       }
     };
 
@@ -1214,9 +1389,13 @@ export function Editor() {
     deleteAnnotation,
     setCurrentTool,
     image,
+    cliOutputPattern,
+    queuePosition,
+    navigateQueue,
+    keybindings,
+    customActions,
   ]);
 
-  const [showCustomActions, setShowCustomActions] = useState(false);
   const whiteboardPresets = [
     { label: "720p", width: 1280, height: 720 },
     { label: "1080p", width: 1920, height: 1080 },
@@ -1260,9 +1439,39 @@ export function Editor() {
         {/* 工具配置面板 */}
         <FloatingToolConfig />
 
+        {/* 多图队列导航 */}
+        {queuePosition && (
+          <div className="absolute bottom-4 right-4 z-40 flex items-center gap-1 rounded-lg border bg-background/90 px-1 py-1 text-sm shadow-md">
+            <button
+              className="rounded p-1 hover:bg-muted disabled:opacity-40"
+              disabled={queuePosition.index === 0}
+              onClick={() => navigateQueue("previous_image")}
+              title={withShortcut("上一张", keybindings, "previous_image")}
+            >
+              <ChevronLeft size={16} />
+            </button>
+            <span className="px-1 tabular-nums">
+              {queuePosition.index + 1} / {queuePosition.total}
+            </span>
+            <button
+              className="rounded p-1 hover:bg-muted disabled:opacity-40"
+              disabled={queuePosition.index >= queuePosition.total - 1}
+              onClick={() => navigateQueue("next_image")}
+              title={withShortcut("下一张", keybindings, "next_image")}
+            >
+              <ChevronRight size={16} />
+            </button>
+          </div>
+        )}
+
         {/* 自定义动作面板 */}
         {showCustomActions && image && (
-          <CustomActionsPanel getCanvasDataUrl={getCanvasDataUrl} imagePath={image?.path} />
+          <CustomActionsPanel
+            getCanvasDataUrl={getCanvasDataUrl}
+            imagePath={image?.path}
+            pendingActionIndex={pendingCustomAction}
+            onPendingActionStarted={() => setPendingCustomAction(null)}
+          />
         )}
 
         {/* 欢迎提示 */}
@@ -1279,17 +1488,9 @@ export function Editor() {
                 <div>
                   <p className="font-medium text-sm mb-2 text-foreground flex items-center gap-1"><Keyboard size={14} /> 快捷键</p>
                   <div className="space-y-1">
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">V</kbd> 选择工具</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">H</kbd> 平移画布</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">R</kbd> 矩形</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">E</kbd> 椭圆</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">A</kbd> 箭头</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">L</kbd> 直线</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">T</kbd> 文字</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">B</kbd> 画笔</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">M</kbd> 序号标记</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">U</kbd> 马赛克</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">C</kbd> 裁剪</p>
+                    {HELP_TOOL_SHORTCUTS.filter(([action]) => keybindings[action]).map(([action, label]) => (
+                      <p key={action}><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">{keybindings[action].join(" / ")}</kbd> {label}</p>
+                    ))}
                   </div>
                 </div>
 
@@ -1307,11 +1508,9 @@ export function Editor() {
                   
                   <p className="font-medium text-sm mt-4 mb-2 text-foreground flex items-center gap-1"><Zap size={14} /> 常用操作</p>
                   <div className="space-y-1">
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">Ctrl+Z</kbd> 撤销</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">Ctrl+Y</kbd> 重做</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">Ctrl+S</kbd> 保存</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">Ctrl+C</kbd> 复制</p>
-                    <p><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">Del/Backspace</kbd> 删除选中</p>
+                    {HELP_COMMAND_SHORTCUTS.filter(([action]) => keybindings[action]).map(([action, label]) => (
+                      <p key={action}><kbd className="px-1 bg-gray-200 dark:bg-gray-700 rounded">{keybindings[action].join(" / ")}</kbd> {label}</p>
+                    ))}
                   </div>
                 </div>
               </div>
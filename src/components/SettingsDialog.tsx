@@ -4,10 +4,11 @@ import { invoke } from "@tauri-apps/api/core";
 import { useEditorStore } from "@/store/editorStore";
 import { Button } from "@/components/ui/Button";
 import { cn } from "@/lib/utils";
-import { X, Sun, Moon, Monitor, FolderOpen, Github, Plus, Trash2, Edit2, Check } from "lucide-react";
+import { X, Sun, Moon, Monitor, FolderOpen, Github, Plus, Trash2, Edit2, Check, Download } from "lucide-react";
 import { open as openUrl } from "@tauri-apps/plugin-shell";
+import { open as openFileDialog } from "@tauri-apps/plugin-dialog";
 import { getVersion } from "@tauri-apps/api/app";
-import type { CustomAction } from "@/types";
+import type { ConfigDiagnostic, ConfigSource, CustomAction, PresetInfo, StylePreset } from "@/types";
 
 interface SettingsDialogProps {
   open: boolean;
@@ -15,17 +16,30 @@ interface SettingsDialogProps {
 }
 
 export function SettingsDialog({ open, onClose }: SettingsDialogProps) {
-  const { theme, setTheme, customActions, setCustomActions, outputPattern, setOutputPattern, saveConfig, loadConfig } = useEditorStore();
+  const { theme, setTheme, customActions, setCustomActions, outputPattern, setOutputPattern, saveConfig, loadConfig, applyStylePreset } = useEditorStore();
   const [configPath, setConfigPath] = useState<string>("");
+  const [configReadOnly, setConfigReadOnly] = useState(false);
   const [editingIndex, setEditingIndex] = useState<number | null>(null);
   const [editForm, setEditForm] = useState<CustomAction>({ name: "", command: "", icon: "" });
   const [isAdding, setIsAdding] = useState(false);
   const [appVersion, setAppVersion] = useState<string>("0.0.0");
+  const [diagnostics, setDiagnostics] = useState<ConfigDiagnostic[]>([]);
+  const [presets, setPresets] = useState<PresetInfo[]>([]);
+  const [presetName, setPresetName] = useState("");
+
+  const refreshPresets = () => {
+    invoke<PresetInfo[]>("list_presets").then(setPresets).catch(console.error);
+  };
 
   // 获取配置文件路径并加载配置
   useEffect(() => {
     if (open) {
-      invoke<string>("get_config_path").then(setConfigPath);
+      invoke<ConfigSource>("get_config_source").then((source) => {
+        setConfigPath(source.path);
+        setConfigReadOnly(source.read_only);
+      });
+      invoke<ConfigDiagnostic[]>("get_config_diagnostics").then(setDiagnostics);
+      refreshPresets();
       loadConfig();
       getVersion().then(setAppVersion);
     }
@@ -82,6 +96,69 @@ export function SettingsDialog({ open, onClose }: SettingsDialogProps) {
     setEditForm({ name: "", command: "", icon: "" });
   };
 
+  // 套用预设
+  const handleApplyPreset = async (name: string) => {
+    try {
+      applyStylePreset(await invoke<StylePreset>("apply_preset", { name }));
+      onClose();
+    } catch (error) {
+      alert(`套用预设失败: ${error}`);
+    }
+  };
+
+  // 把当前工具与样式保存为预设
+  const handleSavePreset = async () => {
+    const name = presetName.trim();
+    if (!name) {
+      alert("预设名称不能为空");
+      return;
+    }
+    const { currentTool, toolConfig } = useEditorStore.getState();
+    const preset: StylePreset = {
+      tool: currentTool,
+      stroke_color: toolConfig.strokeColor,
+      fill_color: toolConfig.fillColor,
+      stroke_width: toolConfig.strokeWidth,
+      font_size: toolConfig.fontSize,
+    };
+    try {
+      await invoke("save_current_as_preset", { name, preset });
+      setPresetName("");
+    } catch (error) {
+      alert(`保存预设失败: ${error}`);
+    }
+    refreshPresets();
+    loadConfig();
+  };
+
+  // 删除预设
+  const handleDeletePreset = async (name: string) => {
+    try {
+      await invoke("delete_preset", { name });
+    } catch (error) {
+      alert(`删除预设失败: ${error}`);
+    }
+    refreshPresets();
+    loadConfig();
+  };
+
+  // 从其他配置文件导入预设与调色板
+  const handleImportPresets = async () => {
+    try {
+      const selected = await openFileDialog({
+        multiple: false,
+        filters: [{ name: "配置文件", extensions: ["toml"] }],
+      });
+      if (!selected) return;
+      const imported = await invoke<string[]>("import_presets", { path: selected });
+      alert(`已导入: ${imported.join(", ")}`);
+    } catch (error) {
+      alert(`导入失败: ${error}`);
+    }
+    refreshPresets();
+    loadConfig();
+  };
+
   // 打开配置文件目录
   const handleOpenConfigDir = async () => {
     try {
@@ -127,6 +204,28 @@ export function SettingsDialog({ open, onClose }: SettingsDialogProps) {
 
         {/* 设置内容 */}
         <div className="space-y-6">
+          {/* 配置文件错误（本次使用默认配置） */}
+          {diagnostics.length > 0 && (
+            <div className="p-3 rounded-lg border border-red-200 dark:border-red-800 bg-red-50 dark:bg-red-900/20 text-xs text-red-700 dark:text-red-300 space-y-2">
+              {diagnostics.map((diagnostic, index) => (
+                <div key={index} className="space-y-1">
+                  <p className="font-medium">
+                    配置文件有误，本次已忽略：
+                    <code className="break-all">
+                      {diagnostic.path}
+                      {diagnostic.line !== null && `:${diagnostic.line}`}
+                      {diagnostic.column !== null && `:${diagnostic.column}`}
+                    </code>
+                  </p>
+                  <p className="break-all">{diagnostic.message}</p>
+                  {diagnostic.backup && (
+                    <p className="break-all">原文件已备份到 <code>{diagnostic.backup}</code></p>
+                  )}
+                </div>
+              ))}
+            </div>
+          )}
+
           {/* 主题设置 */}
           <div>
             <h3 className="text-sm font-medium mb-3 text-gray-700 dark:text-gray-300">主题</h3>
@@ -167,6 +266,65 @@ export function SettingsDialog({ open, onClose }: SettingsDialogProps) {
               <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{input_file_base}`}</code>: 基础文件名</p>
               <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{input_file}`}</code>: 完整路径</p>
               <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{YYYY_MM_DD-hh-mm-ss}`}</code>: 时间戳</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{ext}`}</code>: 原图扩展名</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{date:%Y%m%d}`}</code>: 按 strftime 格式的日期时间（可含 / 分目录）</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{width}`}</code> <code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{height}`}</code>: 图片尺寸</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{counter:3}`}</code>: 递增编号（跳过已存在的文件，3 为补零位数）</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{hash8}`}</code>: 图片内容哈希</p>
+              <p><code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{hostname}`}</code> <code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">{`{user}`}</code>: 主机名与用户名</p>
+            </div>
+          </div>
+
+          {/* 样式预设 */}
+          <div>
+            <div className="flex items-center justify-between mb-3">
+              <h3 className="text-sm font-medium text-gray-700 dark:text-gray-300">样式预设</h3>
+              <Button variant="ghost" size="icon-sm" onClick={handleImportPresets} title="从配置文件导入预设与调色板">
+                <Download size={16} />
+              </Button>
+            </div>
+            <div className="space-y-2 max-h-48 overflow-y-auto">
+              {presets.map((preset) => (
+                <div
+                  key={preset.name}
+                  className="flex items-center gap-2 p-2 bg-gray-50 dark:bg-gray-800 rounded-lg"
+                >
+                  {preset.stroke_color && (
+                    <span
+                      className="w-4 h-4 rounded border border-gray-200 dark:border-gray-700 shrink-0"
+                      style={{ backgroundColor: preset.stroke_color }}
+                    />
+                  )}
+                  <div className="flex-1 min-w-0">
+                    <p className="text-sm font-medium text-gray-900 dark:text-gray-100 truncate">{preset.name}</p>
+                    <p className="text-xs text-gray-500 dark:text-gray-400 truncate">
+                      {[preset.tool, preset.stroke_width && `${preset.stroke_width}px`, preset.builtin && "内置"]
+                        .filter(Boolean)
+                        .join(" · ")}
+                    </p>
+                  </div>
+                  <Button variant="ghost" size="sm" onClick={() => handleApplyPreset(preset.name)} className="h-7 text-xs">
+                    套用
+                  </Button>
+                  {!preset.builtin && (
+                    <Button variant="ghost" size="icon-sm" onClick={() => handleDeletePreset(preset.name)} className="text-red-500 hover:text-red-600">
+                      <Trash2 size={14} />
+                    </Button>
+                  )}
+                </div>
+              ))}
+            </div>
+            <div className="flex gap-2 mt-2">
+              <input
+                type="text"
+                value={presetName}
+                onChange={(e) => setPresetName(e.target.value)}
+                placeholder="预设名称"
+                className="flex-1 px-2 py-1 text-xs border border-gray-200 dark:border-gray-700 rounded bg-white dark:bg-gray-900 text-gray-900 dark:text-gray-100"
+              />
+              <Button variant="outline" size="sm" onClick={handleSavePreset} className="h-7 text-xs">
+                <Plus size={12} className="mr-1" /> 保存当前样式
+              </Button>
             </div>
           </div>
 
@@ -283,6 +441,7 @@ export function SettingsDialog({ open, onClose }: SettingsDialogProps) {
             <div className="mt-3 pt-3 border-t border-gray-200 dark:border-gray-700">
               <p className="text-xs text-gray-500 dark:text-gray-400 mb-2">
                 配置文件：<code className="px-1 py-0.5 bg-gray-100 dark:bg-gray-800 rounded text-xs break-all">{configPath}</code>
+                {configReadOnly && <span className="ml-1 text-amber-600 dark:text-amber-400">（只读，修改不会保存）</span>}
               </p>
               <Button variant="outline" size="sm" onClick={handleOpenConfigDir} className="w-full">
                 <FolderOpen size={14} className="mr-2" />
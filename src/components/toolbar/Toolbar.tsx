@@ -36,23 +36,24 @@ import {
   ChevronDown,
   ChevronUp,
 } from "lucide-react";
+import { withShortcut } from "@/lib/keybindings";
 import type { ToolType } from "@/types";
 
 // 工具定义
 const tools: { type: ToolType; icon: React.ReactNode; label: string }[] = [
-  { type: "select", icon: <MousePointer2 size={18} />, label: "选择 (V)" },
-  { type: "pan", icon: <Hand size={18} />, label: "平移 (H)" },
-  { type: "rectangle", icon: <Square size={18} />, label: "矩形 (R)" },
-  { type: "ellipse", icon: <Circle size={18} />, label: "椭圆 (E)" },
-  { type: "arrow", icon: <ArrowRight size={18} />, label: "箭头 (A)" },
-  { type: "line", icon: <Minus size={18} />, label: "直线 (L)" },
-  { type: "text", icon: <Type size={18} />, label: "文字 (T)" },
-  { type: "brush", icon: <Pencil size={18} />, label: "画笔 (B)" },
-  { type: "marker", icon: <Hash size={18} />, label: "序号 (M)" },
-  { type: "blur", icon: <Grid3X3 size={18} />, label: "马赛克 (U)" },
-  { type: "crop", icon: <Crop size={18} />, label: "裁剪 (C)" },
-  { type: "image", icon: <ImagePlus size={18} />, label: "插入图片 (I)" },
-  { type: "magnifier", icon: <Search size={18} />, label: "放大镜 (Z)" },
+  { type: "select", icon: <MousePointer2 size={18} />, label: "选择" },
+  { type: "pan", icon: <Hand size={18} />, label: "平移" },
+  { type: "rectangle", icon: <Square size={18} />, label: "矩形" },
+  { type: "ellipse", icon: <Circle size={18} />, label: "椭圆" },
+  { type: "arrow", icon: <ArrowRight size={18} />, label: "箭头" },
+  { type: "line", icon: <Minus size={18} />, label: "直线" },
+  { type: "text", icon: <Type size={18} />, label: "文字" },
+  { type: "brush", icon: <Pencil size={18} />, label: "画笔" },
+  { type: "marker", icon: <Hash size={18} />, label: "序号" },
+  { type: "blur", icon: <Grid3X3 size={18} />, label: "马赛克" },
+  { type: "crop", icon: <Crop size={18} />, label: "裁剪" },
+  { type: "image", icon: <ImagePlus size={18} />, label: "插入图片" },
+  { type: "magnifier", icon: <Search size={18} />, label: "放大镜" },
 ];
 
 interface ToolbarProps {
@@ -94,6 +95,7 @@ export function Toolbar({
     deleteAnnotation,
     clearSelection,
     customActions,
+    keybindings,
   } = useEditorStore();
 
   const handleZoomIn = () => setViewState({ scale: viewState.scale * 1.2 });
@@ -135,12 +137,12 @@ export function Toolbar({
               <FolderOpen size={16} />
             </Button>
           </Tooltip>
-          <Tooltip content="保存" side="bottom">
+          <Tooltip content={withShortcut("保存", keybindings, "save")} side="bottom">
             <Button variant="ghost" size="icon-sm" onClick={onSave} disabled={!image}>
               <Download size={16} />
             </Button>
           </Tooltip>
-          <Tooltip content="复制到剪贴板" side="bottom">
+          <Tooltip content={withShortcut("复制到剪贴板", keybindings, "copy")} side="bottom">
             <Button variant="ghost" size="icon-sm" onClick={onCopy} disabled={!image}>
               <Copy size={16} />
             </Button>
@@ -150,7 +152,7 @@ export function Toolbar({
         {/* 绘图工具 */}
         <div className="flex items-center gap-0.5 px-1 border-r border-gray-200 dark:border-gray-700 pointer-events-auto">
           {tools.map((tool) => (
-            <Tooltip key={tool.type} content={tool.label} side="bottom">
+            <Tooltip key={tool.type} content={withShortcut(tool.label, keybindings, tool.type)} side="bottom">
               <Button
                 variant={currentTool === tool.type ? "default" : "ghost"}
                 size="icon-sm"
@@ -169,17 +171,17 @@ export function Toolbar({
 
         {/* 编辑操作 */}
         <div className="flex items-center gap-0.5 px-1 border-r border-gray-200 dark:border-gray-700 pointer-events-auto">
-          <Tooltip content="撤销 (Ctrl+Z)" side="bottom">
+          <Tooltip content={withShortcut("撤销", keybindings, "undo")} side="bottom">
             <Button variant="ghost" size="icon-sm" onClick={undo} disabled={!canUndo()}>
               <Undo2 size={16} />
             </Button>
           </Tooltip>
-          <Tooltip content="重做 (Ctrl+Y)" side="bottom">
+          <Tooltip content={withShortcut("重做", keybindings, "redo")} side="bottom">
             <Button variant="ghost" size="icon-sm" onClick={redo} disabled={!canRedo()}>
               <Redo2 size={16} />
             </Button>
           </Tooltip>
-          <Tooltip content="删除选中 (Delete)" side="bottom">
+          <Tooltip content={withShortcut("删除选中", keybindings, "delete")} side="bottom">
             <Button
               variant="ghost"
               size="icon-sm"
@@ -1,6 +1,7 @@
 // 颜色选择器组件
 import { useState, useRef, useEffect } from "react";
 import { cn } from "@/lib/utils";
+import { useEditorStore } from "@/store/editorStore";
 
 interface ColorPickerProps {
   value: string;
@@ -26,6 +27,7 @@ export function ColorPicker({ value, onChange, className }: ColorPickerProps) {
   const [isOpen, setIsOpen] = useState(false);
   const [customColor, setCustomColor] = useState(value);
   const containerRef = useRef<HTMLDivElement>(null);
+  const palettes = useEditorStore((state) => state.palettes);
 
   // 点击外部关闭
   useEffect(() => {
@@ -99,6 +101,29 @@ export function ColorPicker({ value, onChange, className }: ColorPickerProps) {
             ))}
           </div>
 
+          {/* 配置文件中的调色板 */}
+          {Object.entries(palettes).map(([name, colors]) => (
+            <div key={name} className="pt-2 mb-3 border-t border-border">
+              <p className="text-xs text-muted-foreground mb-1.5">{name}</p>
+              <div className="grid grid-cols-6 gap-1.5">
+                {colors.map((color, index) => (
+                  <button
+                    key={`${color}-${index}`}
+                    type="button"
+                    onClick={() => handleColorSelect(color)}
+                    className={cn(
+                      "w-6 h-6 rounded-md border border-border",
+                      "hover:scale-110 transition-transform",
+                      value === color && "ring-2 ring-ring ring-offset-1"
+                    )}
+                    style={{ backgroundColor: color }}
+                    title={color}
+                  />
+                ))}
+              </div>
+            </div>
+          ))}
+
           {/* 自定义颜色输入 */}
           <div className="flex items-center gap-2 pt-2 border-t border-border">
             <label className="text-xs text-muted-foreground">自定义</label>
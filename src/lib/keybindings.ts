@@ -0,0 +1,91 @@
+// 快捷键匹配：把按键事件转换为与 Rust 端规范化结果一致的按键组合
+
+import type { ToolType } from "@/types";
+
+/**
+ * 动作 ID 到按键组合（如 "Ctrl+Shift+Z"）的映射，由后端 get_keybindings 提供
+ */
+export type Keybindings = Record<string, string[]>;
+
+/** 自定义动作的动作 ID 前缀 */
+export const CUSTOM_ACTION_PREFIX = "custom:";
+
+/** 可通过快捷键切换的工具 */
+export const TOOL_ACTIONS: ToolType[] = [
+  "select",
+  "pan",
+  "rectangle",
+  "ellipse",
+  "arrow",
+  "line",
+  "text",
+  "brush",
+  "marker",
+  "blur",
+  "crop",
+  "magnifier",
+];
+
+const MODIFIER_KEYS = ["Control", "Alt", "Shift", "Meta"];
+
+/**
+ * 按键事件对应的规范化按键组合，单独按下修饰键时返回 null
+ *
+ * 字母转为大写；单个符号键本身已体现 Shift，不再记录 Shift。
+ * ignoreShift 为 true 时总是忽略 Shift。
+ */
+export function chordFromEvent(e: KeyboardEvent, ignoreShift = false): string | null {
+  if (MODIFIER_KEYS.includes(e.key)) return null;
+  let key = e.key;
+  let shift = e.shiftKey && !ignoreShift;
+  if (key === " ") {
+    key = "Space";
+  } else if (key === "+") {
+    key = "Plus";
+    shift = false;
+  } else if (key === "Esc") {
+    key = "Escape";
+  } else if (key.length === 1) {
+    if (!/^[\p{L}\p{N}]$/u.test(key)) {
+      shift = false;
+    }
+    key = key.toUpperCase();
+  }
+  const parts: string[] = [];
+  if (e.ctrlKey) parts.push("Ctrl");
+  if (e.altKey) parts.push("Alt");
+  if (shift) parts.push("Shift");
+  if (e.metaKey) parts.push("Meta");
+  parts.push(key);
+  return parts.join("+");
+}
+
+function findAction(keybindings: Keybindings, chord: string): string | null {
+  for (const [action, chords] of Object.entries(keybindings)) {
+    if (chords.includes(chord)) return action;
+  }
+  return null;
+}
+
+/**
+ * 按键事件触发的动作 ID，未绑定时返回 null
+ *
+ * 按住 Shift 且没有精确匹配时，仍按不带 Shift 的组合切换工具（如 Shift+R 选择矩形），与大小写无关。
+ */
+export function actionForEvent(keybindings: Keybindings, e: KeyboardEvent): string | null {
+  const chord = chordFromEvent(e);
+  if (!chord) return null;
+  const action = findAction(keybindings, chord);
+  if (action || !e.shiftKey) return action;
+  const unshifted = chordFromEvent(e, true);
+  const tool = unshifted ? findAction(keybindings, unshifted) : null;
+  return tool && (TOOL_ACTIONS as string[]).includes(tool) ? tool : null;
+}
+
+/**
+ * 在提示文字后附加动作的第一个快捷键，如 "撤销 (Ctrl+Z)"；未绑定时原样返回
+ */
+export function withShortcut(label: string, keybindings: Keybindings, action: string): string {
+  const chord = keybindings[action]?.[0];
+  return chord ? `${label} (${chord})` : label;
+}
@@ -2,6 +2,7 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
 import { generateId } from "@/lib/utils";
+import type { Keybindings } from "@/lib/keybindings";
 
 import type {
   Annotation,
@@ -16,6 +17,8 @@ import type {
   ToolbarOrientation,
   ThemeMode,
   AppConfig,
+  StylePreset,
+  ToolDefaults,
   WhiteboardConfig,
 } from "@/types";
 
@@ -83,6 +86,91 @@ function serializeTextToolConfig(toolConfig: ToolConfig) {
   };
 }
 
+/**
+ * 有持久化默认样式的工具：ToolConfig 字段与 tool_defaults 中字段的对应关系
+ */
+const TOOL_DEFAULT_FIELDS: Record<keyof ToolDefaults, Partial<Record<keyof ToolConfig, string>>> = {
+  rectangle: {
+    strokeColor: "stroke_color",
+    strokeWidth: "stroke_width",
+    fillColor: "fill_color",
+    fillOpacity: "fill_opacity",
+    cornerRadius: "corner_radius",
+  },
+  ellipse: {
+    strokeColor: "stroke_color",
+    strokeWidth: "stroke_width",
+    fillColor: "fill_color",
+    fillOpacity: "fill_opacity",
+  },
+  arrow: {
+    strokeColor: "stroke_color",
+    strokeWidth: "stroke_width",
+    lineStyle: "line_style",
+    arrowStyle: "arrow_style",
+  },
+  line: {
+    strokeColor: "stroke_color",
+    strokeWidth: "stroke_width",
+    lineStyle: "line_style",
+  },
+  brush: {
+    strokeColor: "stroke_color",
+    brushSize: "brush_size",
+  },
+  marker: {
+    strokeColor: "stroke_color",
+    markerStyle: "marker_style",
+    markerType: "marker_type",
+    markerSize: "marker_size",
+  },
+  blur: {
+    blurRadius: "blur_radius",
+    blurCornerRadius: "corner_radius",
+  },
+  magnifier: {
+    magnifierScale: "magnifier_scale",
+  },
+};
+
+function isDefaultsTool(tool: ToolType): tool is keyof ToolDefaults {
+  return tool in TOOL_DEFAULT_FIELDS;
+}
+
+/**
+ * 把工具的持久化默认样式转换为 ToolConfig 字段
+ */
+function toolDefaultsToConfig(
+  tool: ToolType,
+  toolDefaults: ToolDefaults | null
+): Partial<ToolConfig> {
+  if (!toolDefaults || !isDefaultsTool(tool)) return {};
+  const values = toolDefaults[tool] as Record<string, unknown>;
+  const config: Record<string, unknown> = {};
+  for (const [configKey, defaultsKey] of Object.entries(TOOL_DEFAULT_FIELDS[tool])) {
+    if (defaultsKey && values[defaultsKey] !== undefined) {
+      config[configKey] = values[defaultsKey];
+    }
+  }
+  return config as Partial<ToolConfig>;
+}
+
+/**
+ * 取出配置修改中属于该工具默认样式的字段
+ */
+function configToToolDefaults(tool: ToolType, config: Partial<ToolConfig>): Record<string, unknown> {
+  if (!isDefaultsTool(tool)) return {};
+  const fields = TOOL_DEFAULT_FIELDS[tool];
+  const defaults: Record<string, unknown> = {};
+  for (const [configKey, value] of Object.entries(config)) {
+    const defaultsKey = fields[configKey as keyof ToolConfig];
+    if (defaultsKey && value !== undefined) {
+      defaults[defaultsKey] = value;
+    }
+  }
+  return defaults;
+}
+
 /**
  * 历史记录最大长度
  */
@@ -103,6 +191,14 @@ interface EditorState {
   // 工具配置
   toolConfig: ToolConfig;
   setToolConfig: (config: Partial<ToolConfig>) => void;
+  // 各工具的持久化默认样式（配置文件 tool_defaults）
+  toolDefaults: ToolDefaults | null;
+  // 套用样式预设（启动参数或配置文件中的预设）
+  applyStylePreset: (preset: StylePreset) => void;
+  // 配置文件中的调色板
+  palettes: Record<string, string[]>;
+  // 生效的快捷键（动作 ID → 按键组合）
+  keybindings: Keybindings;
 
   // 标注对象
   annotations: Annotation[];
@@ -203,7 +299,10 @@ export const useEditorStore = create<EditorState>((set, get) => ({
   // 当前工具
   currentTool: "select",
   setCurrentTool: (tool) => {
-    set({ currentTool: tool });
+    set((state) => ({
+      currentTool: tool,
+      toolConfig: { ...state.toolConfig, ...toolDefaultsToConfig(tool, state.toolDefaults) },
+    }));
     // 切换工具时清除选中
     if (tool !== "select") {
       set({ selectedIds: [] });
@@ -225,7 +324,37 @@ export const useEditorStore = create<EditorState>((set, get) => ({
     if (shouldPersistTextConfig && config.fontFamily !== "") {
       get().saveConfig();
     }
+    // 绘图工具的修改保存为该工具的默认样式
+    const tool = get().currentTool;
+    const defaultsPatch = configToToolDefaults(tool, config);
+    if (isDefaultsTool(tool) && Object.keys(defaultsPatch).length > 0) {
+      set((state) => ({
+        toolDefaults: state.toolDefaults
+          ? { ...state.toolDefaults, [tool]: { ...state.toolDefaults[tool], ...defaultsPatch } }
+          : null,
+      }));
+      invoke("update_tool_defaults", { tool, defaults: defaultsPatch }).catch((error) => {
+        console.error("保存工具默认样式失败:", error);
+      });
+    }
+  },
+  toolDefaults: null,
+  applyStylePreset: (preset) => {
+    if (preset.tool) {
+      get().setCurrentTool(preset.tool);
+    }
+    const stylePatch: Partial<ToolConfig> = {};
+    if (preset.stroke_color) stylePatch.strokeColor = preset.stroke_color;
+    if (preset.fill_color) stylePatch.fillColor = preset.fill_color;
+    if (preset.stroke_width) stylePatch.strokeWidth = preset.stroke_width;
+    if (preset.font_size) stylePatch.fontSize = preset.font_size;
+    // 预设只影响本次编辑，不写入工具的默认样式
+    set((state) => ({
+      toolConfig: { ...state.toolConfig, ...stylePatch },
+    }));
   },
+  palettes: {},
+  keybindings: {},
 
   // 标注对象
   annotations: [],
@@ -445,7 +574,10 @@ export const useEditorStore = create<EditorState>((set, get) => ({
       const config = await invoke<AppConfig>("get_config");
       const currentToolConfig = get().toolConfig;
       const persistedTextToolConfig = config.text_tool_config;
+      const toolDefaults = config.tool_defaults ?? null;
       set({ 
+        toolDefaults,
+        palettes: config.palettes ?? {},
         theme: config.theme, 
         outputPattern: config.output_pattern,
         customActions: config.custom_actions,
@@ -475,9 +607,14 @@ export const useEditorStore = create<EditorState>((set, get) => ({
           ...(persistedTextToolConfig?.bubble_tail_position !== undefined
             ? { bubbleTailPosition: persistedTextToolConfig.bubble_tail_position }
             : {}),
+          ...toolDefaultsToConfig(get().currentTool, toolDefaults),
         },
       });
       
+      invoke<Keybindings>("get_keybindings")
+        .then((keybindings) => set({ keybindings }))
+        .catch((error) => console.error("加载快捷键失败:", error));
+
       // 应用加载的主题（但不触发 saveConfig）
       const root = document.documentElement;
       const theme = config.theme;
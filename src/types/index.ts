@@ -337,6 +337,166 @@ export type ToolbarOrientation = "horizontal" | "vertical";
  */
 export type ThemeMode = "light" | "dark" | "auto";
 
+/**
+ * 复制、保存后的退出行为
+ */
+/**
+ * 多图队列中单张图片的标注快照
+ */
+export interface QueueSnapshot {
+  annotations: Annotation[];
+  cropMask: CropMask | null;
+  markerCounter: number;
+}
+
+/**
+ * 切换队列图片时后端返回的数据
+ */
+export interface QueueEntry {
+  index: number;
+  total: number;
+  path: string;
+  snapshot: QueueSnapshot | null;
+}
+
+/**
+ * 队列概览
+ */
+export interface QueueInfo {
+  current: number;
+  items: { index: number; path: string; edited: boolean }[];
+}
+
+export interface ExitBehavior {
+  early_exit: boolean;
+  copy_on_exit: boolean;
+  save_on_exit: boolean;
+}
+
+/**
+ * 样式预设：未设置的字段保持编辑器当前值
+ */
+export interface StylePreset {
+  tool?: ToolType;
+  stroke_color?: string;
+  fill_color?: string;
+  stroke_width?: number;
+  font_size?: number;
+}
+
+/**
+ * 预设列表中的一项
+ */
+export interface PresetInfo extends StylePreset {
+  name: string;
+  /** 内置预设不能删除 */
+  builtin: boolean;
+}
+
+/**
+ * 启动时的工具与样式（CLI --tool/--preset/--stroke-color/--stroke-width）
+ */
+export interface StartupOptions extends StylePreset {
+  preset: string | null;
+}
+
+/**
+ * 单实例模式下后续启动转交的请求
+ */
+export interface InstanceOpen {
+  index: number | null;
+  output_pattern: string | null;
+  startup: StartupOptions;
+}
+
+/**
+ * 监视目录中出现的新图片
+ */
+export interface FolderImage {
+  index: number;
+  path: string;
+}
+
+/**
+ * 配置文件重新加载后变化的配置节
+ */
+export interface ConfigChanged {
+  /** 变化的顶层配置项，如 theme、custom_actions */
+  sections: string[];
+  config: AppConfig;
+}
+
+/**
+ * 实际使用的配置文件
+ */
+export interface ConfigSource {
+  path: string;
+  /** 是否由 -c 指定 */
+  explicit: boolean;
+  /** 文件不可写时设置不会保存 */
+  read_only: boolean;
+}
+
+/**
+ * 加载配置时发现的问题
+ */
+export interface ConfigDiagnostic {
+  path: string;
+  kind: "read" | "parse" | "invalid";
+  message: string;
+  line: number | null;
+  column: number | null;
+  /** 原配置文件的备份路径 */
+  backup: string | null;
+}
+
+/**
+ * 各绘图工具的默认样式（文字工具见 text_tool_config）
+ */
+export interface ToolDefaults {
+  rectangle: {
+    stroke_color: string;
+    stroke_width: number;
+    fill_color: string;
+    fill_opacity: number;
+    corner_radius: number;
+  };
+  ellipse: {
+    stroke_color: string;
+    stroke_width: number;
+    fill_color: string;
+    fill_opacity: number;
+  };
+  arrow: {
+    stroke_color: string;
+    stroke_width: number;
+    line_style: LineStyle;
+    arrow_style: ArrowStyle;
+  };
+  line: {
+    stroke_color: string;
+    stroke_width: number;
+    line_style: LineStyle;
+  };
+  brush: {
+    stroke_color: string;
+    brush_size: number;
+  };
+  marker: {
+    stroke_color: string;
+    marker_style: MarkerStyle;
+    marker_type: MarkerType;
+    marker_size: number;
+  };
+  blur: {
+    blur_radius: number;
+    corner_radius: number;
+  };
+  magnifier: {
+    magnifier_scale: number;
+  };
+}
+
 /**
  * 应用配置
  */
@@ -344,6 +504,13 @@ export interface AppConfig {
   theme: ThemeMode;
   output_pattern: string;
   custom_actions: CustomAction[];
+  early_exit?: boolean;
+  copy_on_exit?: boolean;
+  save_on_exit?: boolean;
+  export?: {
+    quality?: number;
+    background_color?: string;
+  };
   text_tool_config?: {
     stroke_color?: string;
     font_size?: number;
@@ -354,4 +521,8 @@ export interface AppConfig {
     bubble_fill?: string;
     bubble_tail_position?: BubbleTailPosition;
   };
+  tool_defaults?: ToolDefaults;
+  presets?: Record<string, StylePreset>;
+  /** 命名的调色板（颜色列表） */
+  palettes?: Record<string, string[]>;
 }